@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::physics::{Body, SimulationState, Vec2, DEFAULT_GRAVITY_CONSTANT};
+
+// Fixtures live in the source tree, so these commands are only useful in a development checkout
+const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+const TIME_STEP: f64 = 0.01;
+const TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExpectedBody {
+    pub id: u32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+// A scenario plus the bodies it should end up with after `steps` steps at TIME_STEP
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GoldenFixture {
+    pub steps: u32,
+    pub scenario: SimulationState,
+    pub expected: Vec<ExpectedBody>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GoldenRunResult {
+    pub name: String,
+    pub passed: bool,
+    pub max_error: f64,
+    pub detail: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GoldenRunReport {
+    pub passed: bool,
+    pub runs: Vec<GoldenRunResult>,
+}
+
+// Scenes written as fixtures when missing, chosen to exercise gravity and collisions
+fn builtin_fixtures() -> Vec<(&'static str, SimulationState, u32)> {
+    let g = DEFAULT_GRAVITY_CONSTANT;
+    
+    let head_on = SimulationState::from_bodies(vec![
+        Body::new(1, 50.0, Vec2::new(-60.0, 0.0), Vec2::new(20.0, 0.0), 10.0, "#ffffff"),
+        Body::new(2, 20.0, Vec2::new(60.0, 2.0), Vec2::new(-20.0, 0.0), 8.0, "#ffffff"),
+    ], g);
+    
+    let cluster = SimulationState::from_bodies((0..16)
+        .map(|i| {
+            let (col, row) = ((i % 4) as f64, (i / 4) as f64);
+            Body::new(i + 1, 10.0 + i as f64, Vec2::new(col * 30.0, row * 30.0), Vec2::new(row - 1.5, 1.5 - col), 4.0, "#ffffff")
+        })
+        .collect(), g);
+    
+    vec![
+        ("default_system", SimulationState::new(), 2000),
+        ("head_on_collision", head_on, 600),
+        ("cluster", cluster, 1000),
+    ]
+}
+
+fn run(fixture: &GoldenFixture) -> Vec<ExpectedBody> {
+    let mut sim = fixture.scenario.clone();
+    sim.time_step = TIME_STEP;
+    sim.time_multiplier = 1.0;
+    sim.is_running = true;
+    for _ in 0..fixture.steps {
+        sim.step();
+    }
+    
+    sim.bodies.iter()
+        .map(|b| ExpectedBody { id: b.id, position: b.position, velocity: b.velocity })
+        .collect()
+}
+
+fn compare(expected: &[ExpectedBody], actual: &[ExpectedBody]) -> Result<f64, String> {
+    if expected.len() != actual.len() {
+        return Err(format!("expected {} bodies, got {}", expected.len(), actual.len()));
+    }
+    
+    let mut max_error: f64 = 0.0;
+    for (e, a) in expected.iter().zip(actual) {
+        if e.id != a.id {
+            return Err(format!("expected body {}, got {}", e.id, a.id));
+        }
+        for (want, got) in [
+            (e.position.x, a.position.x), (e.position.y, a.position.y),
+            (e.velocity.x, a.velocity.x), (e.velocity.y, a.velocity.y),
+        ] {
+            max_error = max_error.max((want - got).abs() / want.abs().max(1.0));
+        }
+    }
+    Ok(max_error)
+}
+
+fn fixture_files(dir: &Path) -> Result<Vec<(String, std::path::PathBuf)>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("could not read {}: {}", dir.display(), e))?;
+    let mut files: Vec<(String, std::path::PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some((path.file_stem()?.to_string_lossy().into_owned(), path)))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn read_fixture(path: &Path) -> Result<GoldenFixture, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("invalid fixture {}: {}", path.display(), e))
+}
+
+// Runs every fixture in the directory and compares the final bodies against the stored ones
+pub fn check_all(dir: &Path) -> Result<GoldenRunReport, String> {
+    let mut runs = Vec::new();
+    for (name, path) in fixture_files(dir)? {
+        let result = read_fixture(&path).and_then(|fixture| compare(&fixture.expected, &run(&fixture)));
+        runs.push(match result {
+            Ok(max_error) => GoldenRunResult {
+                name,
+                passed: max_error <= TOLERANCE,
+                max_error,
+                detail: String::from("max relative error in final positions and velocities"),
+            },
+            Err(detail) => GoldenRunResult { name, passed: false, max_error: f64::INFINITY, detail },
+        });
+    }
+    
+    Ok(GoldenRunReport {
+        passed: !runs.is_empty() && runs.iter().all(|r| r.passed),
+        runs,
+    })
+}
+
+// Rewrites the expected bodies of every fixture from the current physics, and adds missing builtin ones
+pub fn regenerate_all(dir: &Path) -> Result<Vec<String>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+    
+    let mut fixtures: Vec<(String, GoldenFixture)> = Vec::new();
+    for (name, path) in fixture_files(dir)? {
+        fixtures.push((name, read_fixture(&path)?));
+    }
+    for (name, scenario, steps) in builtin_fixtures() {
+        if !fixtures.iter().any(|(existing, _)| existing == name) {
+            fixtures.push((String::from(name), GoldenFixture { steps, scenario, expected: Vec::new() }));
+        }
+    }
+    
+    let mut written = Vec::new();
+    for (name, mut fixture) in fixtures {
+        fixture.expected = run(&fixture);
+        let json = serde_json::to_string_pretty(&fixture).map_err(|e| e.to_string())?;
+        let path = dir.join(format!("{}.json", name));
+        std::fs::write(&path, json + "\n").map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        written.push(name);
+    }
+    Ok(written)
+}
+
+#[tauri::command(async)]
+pub fn check_golden_runs() -> Result<GoldenRunReport, String> {
+    crate::metrics::begin("check_golden_runs");
+    check_all(Path::new(FIXTURE_DIR))
+}
+
+// Only for intentional physics changes: the new behavior becomes the reference
+#[tauri::command(async)]
+pub fn regenerate_golden_runs() -> Result<Vec<String>, String> {
+    crate::metrics::begin("regenerate_golden_runs");
+    regenerate_all(Path::new(FIXTURE_DIR))
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::physics::{next_body_id, solve_kepler, Body, SimulationState, Vec2};
+
+// Scene units: 1 AU is this many simulation distance units
+const AU_SCALE: f64 = 100.0;
+const SUN_MASS: f64 = 8.0e3;
+const SUN_RADIUS: f64 = 25.0;
+// Horizons element tables carry no masses, so every imported body gets the same small mass
+const BODY_MASS: f64 = 1.0;
+const BODY_RADIUS: f64 = 5.0;
+// Gaussian gravitational constant, the Sun's mean motion at 1 AU in radians per day
+const GAUSS_K: f64 = 0.01720209895;
+
+// One row of a Horizons osculating elements table, angles in degrees and distances in AU
+struct ElementsRow {
+    name: String,
+    epoch_jd: f64,
+    eccentricity: f64,
+    semi_major_axis: f64,
+    longitude_of_node: f64,
+    argument_of_perihelion: f64,
+    mean_anomaly: Option<f64>,
+    true_anomaly: Option<f64>,
+    mean_motion: Option<f64>,
+}
+
+// Columns are found by their header name: JDTDB (epoch), EC, A, OM, W and MA or TA are used, N (mean
+// motion, deg/day) is used to move MA to the requested epoch. An optional Target, Name or Object column
+// tells several bodies apart, without one all rows describe a single body. IN is ignored since the
+// orbit is projected into the simulation plane, as are QR, Tp, AD, PR and the calendar date.
+fn parse_rows(csv: &str) -> Result<Vec<ElementsRow>, String> {
+    let lines: Vec<&str> = csv.lines().collect();
+    
+    // Exports wrap the data in $$SOE/$$EOE with the header line somewhere above
+    let (header, data): (&str, &[&str]) = match lines.iter().position(|l| l.trim() == "$$SOE") {
+        Some(start) => {
+            let end = lines.iter().position(|l| l.trim() == "$$EOE").unwrap_or(lines.len());
+            let header = lines[..start].iter().rev()
+                .find(|l| l.contains("JDTDB"))
+                .copied()
+                .ok_or("no JDTDB header line before $$SOE")?;
+            (header, &lines[start + 1..end.max(start + 1)])
+        }
+        None => {
+            let start = lines.iter().position(|l| !l.trim().is_empty()).ok_or("the file is empty")?;
+            (lines[start], &lines[start + 1..])
+        }
+    };
+    
+    let columns: HashMap<String, usize> = header.split(',')
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_uppercase(), i))
+        .collect();
+    let column = |name: &str| columns.get(name).copied();
+    let required = |name: &str| column(name).ok_or(format!("missing column {}", name));
+    
+    let epoch = required("JDTDB")?;
+    let eccentricity = required("EC")?;
+    let semi_major_axis = required("A")?;
+    let node = required("OM")?;
+    let perihelion = required("W")?;
+    let (mean_anomaly, true_anomaly) = (column("MA"), column("TA"));
+    if mean_anomaly.is_none() && true_anomaly.is_none() {
+        return Err(String::from("missing column MA or TA"));
+    }
+    let mean_motion = column("N");
+    let name = column("TARGET").or(column("NAME")).or(column("OBJECT"));
+    
+    let mut rows = Vec::new();
+    for (line_number, line) in data.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        let number = |index: usize| -> Result<f64, String> {
+            fields.get(index)
+                .and_then(|f| f.parse::<f64>().ok())
+                .filter(|v| v.is_finite())
+                .ok_or(format!("data row {}: column {} is not a number", line_number + 1, index + 1))
+        };
+        
+        rows.push(ElementsRow {
+            name: name.and_then(|i| fields.get(i)).map(|n| n.to_string()).unwrap_or_default(),
+            epoch_jd: number(epoch)?,
+            eccentricity: number(eccentricity)?,
+            semi_major_axis: number(semi_major_axis)?,
+            longitude_of_node: number(node)?,
+            argument_of_perihelion: number(perihelion)?,
+            mean_anomaly: mean_anomaly.map(number).transpose()?,
+            true_anomaly: true_anomaly.map(number).transpose()?,
+            mean_motion: mean_motion.map(number).transpose()?,
+        });
+    }
+    
+    if rows.is_empty() {
+        return Err(String::from("no data rows"));
+    }
+    Ok(rows)
+}
+
+// True anomaly in radians at epoch_jd. With a mean anomaly the row is propagated along its Kepler orbit,
+// a true anomaly alone is only used as is.
+fn true_anomaly_at(row: &ElementsRow, epoch_jd: f64) -> f64 {
+    let e = row.eccentricity;
+    match row.mean_anomaly {
+        Some(mean_anomaly) => {
+            let mean_motion = row.mean_motion
+                .map(f64::to_radians)
+                .unwrap_or(GAUSS_K / row.semi_major_axis.powf(1.5));
+            let m = mean_anomaly.to_radians() + mean_motion * (epoch_jd - row.epoch_jd);
+            let eccentric_anomaly = solve_kepler(m, e);
+            2.0 * ((1.0 + e).sqrt() * (eccentric_anomaly / 2.0).sin())
+                .atan2((1.0 - e).sqrt() * (eccentric_anomaly / 2.0).cos())
+        }
+        None => row.true_anomaly.unwrap_or(0.0).to_radians(),
+    }
+}
+
+// Scene with the Sun at the origin and one body per object in the file, using each object's row closest
+// to epoch_jd. Velocities follow from the elements with the given G, so orbit shapes match the file.
+pub fn scene_from_csv(csv: &str, epoch_jd: f64, gravity_constant: f64) -> Result<SimulationState, String> {
+    if !epoch_jd.is_finite() {
+        return Err(String::from("epoch_jd must be a finite Julian Date"));
+    }
+    
+    let rows = parse_rows(csv)?;
+    let mut closest: Vec<&ElementsRow> = Vec::new();
+    for row in &rows {
+        match closest.iter_mut().find(|r| r.name == row.name) {
+            Some(best) => {
+                if (row.epoch_jd - epoch_jd).abs() < (best.epoch_jd - epoch_jd).abs() {
+                    *best = row;
+                }
+            }
+            None => closest.push(row),
+        }
+    }
+    
+    let mut bodies = vec![Body::new(1, SUN_MASS, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), SUN_RADIUS, "#ffcc00")];
+    
+    for row in closest {
+        let e = row.eccentricity;
+        if !(0.0..1.0).contains(&e) || row.semi_major_axis <= 0.0 {
+            return Err(format!("{} is not on a bound orbit", if row.name.is_empty() { "the body" } else { &row.name }));
+        }
+        
+        // x = r*cos(Omega + omega + nu), y = r*sin(Omega + omega + nu)
+        let perihelion_longitude = (row.longitude_of_node + row.argument_of_perihelion).to_radians();
+        let nu = true_anomaly_at(row, epoch_jd);
+        let a = row.semi_major_axis * AU_SCALE;
+        let p = a * (1.0 - e * e);
+        let r = p / (1.0 + e * nu.cos());
+        let theta = perihelion_longitude + nu;
+        
+        let mu = gravity_constant * (SUN_MASS + BODY_MASS);
+        let speed_scale = (mu / p).sqrt();
+        
+        let velocity = Vec2::new(
+            -speed_scale * (theta.sin() + e * perihelion_longitude.sin()),
+            speed_scale * (theta.cos() + e * perihelion_longitude.cos()),
+        );
+        bodies.push(Body::new(next_body_id(&bodies), BODY_MASS, Vec2::new(r * theta.cos(), r * theta.sin()), velocity, BODY_RADIUS, "#ffffff"));
+    }
+    
+    let mut scene = SimulationState::from_bodies(bodies, gravity_constant);
+    scene.assign_distinct_colors(true);
+    Ok(scene)
+}
@@ -0,0 +1,145 @@
+pub mod golden;
+pub mod horizons;
+pub mod metrics;
+pub mod physics;
+pub mod presets;
+pub mod selftest;
+pub mod spill;
+pub mod trail_export;
+pub mod tutorials;
+
+pub fn run() {
+    tauri::Builder::default()
+        .manage(physics::AppState::new(physics::SimulationState::new()))
+        .invoke_system(String::from(metrics::INVOKE_INITIALIZATION_SCRIPT), metrics::respond)
+        .invoke_handler(metrics::instrument(tauri::generate_handler![
+            physics::get_simulation_state,
+            physics::set_simulation_running,
+            physics::reset_simulation,
+            physics::step_simulation,
+            physics::update_body,
+            physics::set_time_multiplier,
+            physics::auto_distinct_colors,
+            physics::watch_value,
+            physics::unwatch,
+            physics::secular_precession,
+            physics::cancel_operation,
+            physics::run_until_event,
+            physics::export_orbits_geojson,
+            selftest::run_self_test,
+            physics::set_time_step,
+            physics::set_gravity_constant,
+            physics::lock_simulation_config,
+            physics::unlock_simulation_config,
+            physics::all_orbital_elements,
+            physics::get_trails,
+            physics::compute_action_angle_variables,
+            physics::set_radial_drag,
+            physics::get_binding_report,
+            physics::compute_minimum_encounter_distance,
+            physics::predict_collision_outcome,
+            physics::set_time_step_from_courant_number,
+            physics::set_auto_courant,
+            physics::set_auto_pause_region,
+            physics::set_trail_spill,
+            physics::compute_poincare_section,
+            physics::normalized_potentials,
+            physics::load_simulation,
+            physics::load_nasa_horizons_csv,
+            physics::energy_report_csv,
+            physics::reset_simulation_with_phases,
+            physics::set_body_fixed,
+            physics::step_bodies_independently,
+            physics::morph_to,
+            golden::check_golden_runs,
+            golden::regenerate_golden_runs,
+            physics::roche_lobe_occupants,
+            physics::estimate_n_body_chaos_threshold,
+            physics::query_region,
+            physics::set_drag_coefficient,
+            physics::set_velocity_damping_except,
+            physics::setup_plummer,
+            physics::track_com,
+            physics::untrack_com,
+            physics::get_simulation_checksum,
+            physics::circularization_guidance,
+            physics::set_readonly,
+            physics::add_orbit_constraint,
+            physics::update_orbit_constraint,
+            physics::remove_orbit_constraint,
+            physics::list_orbit_constraints,
+            physics::dedupe_coincident,
+            physics::set_radial_drift_rate,
+            physics::compute_lyapunov_time,
+            physics::potential_released,
+            physics::acquire_snapshot,
+            physics::get_snapshot_state,
+            physics::release_snapshot,
+            physics::set_ring_force,
+            physics::remove_ring_force,
+            physics::list_ring_forces,
+            physics::spin_orbit_ratio,
+            physics::simulate_impulse_sequence,
+            physics::set_tidal_locking,
+            physics::set_idle_tick_rate,
+            physics::get_simulation_state_subset,
+            physics::two_body_masses,
+            physics::add_body,
+            physics::remove_body,
+            physics::export_trails,
+            physics::setup_disk_with_gap,
+            physics::get_merger_tree,
+            physics::get_body_ancestors,
+            physics::most_tidally_stressed,
+            metrics::get_command_metrics,
+            metrics::reset_command_metrics,
+            physics::randomize_body_colors,
+            physics::set_golden_angle_colors,
+            physics::set_total_energy,
+            physics::set_follow_target,
+            physics::get_follow_anchor,
+            physics::set_integrator,
+            physics::set_integration_method,
+            physics::compute_transfer_window,
+            physics::time_to_com,
+            physics::apply_group_impulse,
+            physics::translate_group,
+            physics::set_group_velocity_to_orbit,
+            physics::set_emit_rate_hz,
+            physics::render_ascii_art,
+            physics::scattering_survey,
+            physics::get_system_energy,
+            physics::get_diagnostics,
+            physics::rezero_time,
+            physics::circular_speed_grid,
+            physics::get_momentum,
+            physics::set_collision_mode,
+            physics::list_tutorials,
+            physics::load_tutorial,
+            physics::get_next_tutorial_step,
+            physics::steps_per_orbit,
+            physics::save_simulation,
+            physics::start_burn,
+            physics::set_body_fluid,
+            physics::set_tidal_disruption,
+            physics::get_presets,
+            physics::load_preset,
+            physics::fractal_dimension,
+            physics::set_softening,
+            physics::set_pin_mass_ratio,
+            physics::set_trail_config,
+            physics::tidal_disruption_radius,
+            physics::start_simulation_loop,
+            physics::stop_simulation_loop,
+            physics::predict_trajectory,
+            physics::get_constants,
+            physics::set_constants,
+        ]))
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::Ready => physics::start_idle_ticker(app.clone()),
+            tauri::RunEvent::Exit => physics::shutdown_trail_spill(app),
+            _ => {}
+        });
+}
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use tauri::api::ipc::{format_callback, format_callback_result, CallbackFn};
+use tauri::{Invoke, InvokeResponse, Runtime, Window};
+
+// Percentiles are taken over the most recent calls of each command, means over all of them
+const SAMPLES_KEPT: usize = 512;
+
+#[derive(Default)]
+struct CommandStats {
+    calls: u64,
+    total_duration: Duration,
+    total_lock_wait: Duration,
+    total_payload_bytes: u64,
+    max_payload_bytes: usize,
+    durations: VecDeque<Duration>,
+    lock_waits: VecDeque<Duration>,
+}
+
+impl CommandStats {
+    fn record(&mut self, duration: Duration, lock_wait: Duration, payload_bytes: usize) {
+        self.calls += 1;
+        self.total_duration += duration;
+        self.total_lock_wait += lock_wait;
+        self.total_payload_bytes += payload_bytes as u64;
+        self.max_payload_bytes = self.max_payload_bytes.max(payload_bytes);
+        for (samples, value) in [(&mut self.durations, duration), (&mut self.lock_waits, lock_wait)] {
+            if samples.len() == SAMPLES_KEPT {
+                samples.pop_front();
+            }
+            samples.push_back(value);
+        }
+    }
+}
+
+// Times in milliseconds. duration covers the whole call from the arguments to the serialized response,
+// including the lock wait. Payloads are the response scripts handed to the webview.
+#[derive(Clone, Serialize)]
+pub struct CommandMetrics {
+    pub command: String,
+    pub calls: u64,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+    pub mean_lock_wait_ms: f64,
+    pub p95_lock_wait_ms: f64,
+    pub mean_payload_bytes: f64,
+    pub max_payload_bytes: usize,
+}
+
+// Keyed by command name, so the report comes out sorted
+static METRICS: Mutex<BTreeMap<String, CommandStats>> = Mutex::new(BTreeMap::new());
+
+// tauri's default script, spelled out because invoke_system replaces it together with the responder
+pub const INVOKE_INITIALIZATION_SCRIPT: &str = "Object.defineProperty(window, '__TAURI_POST_MESSAGE__', { value: (message) => \
+    window.ipc.postMessage(JSON.stringify(message, (_k, val) => { if (val instanceof Map) { let o = {}; \
+    val.forEach((v, k) => o[k] = v); return o; } else { return val; } })) })";
+
+struct Call {
+    command: String,
+    start: Instant,
+    lock_wait: Duration,
+}
+
+thread_local! {
+    // The command running on this thread, None outside of a command
+    static CALL: RefCell<Option<Call>> = const { RefCell::new(None) };
+}
+
+// A command that panicked while recording must not break the metrics of every later one
+fn metrics() -> MutexGuard<'static, BTreeMap<String, CommandStats>> {
+    METRICS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn p95(samples: &VecDeque<Duration>) -> f64 {
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort();
+    let index = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+    sorted.get(index).copied().map(millis).unwrap_or(0.0)
+}
+
+// Adds to the lock wait of the command running on this thread, waits outside of commands are not counted
+pub fn add_lock_wait(wait: Duration) {
+    CALL.with(|call| {
+        if let Some(call) = call.borrow_mut().as_mut() {
+            call.lock_wait += wait;
+        }
+    });
+}
+
+// Starts measuring a command on this thread. Async commands call it first thing in their body, since the
+// runtime thread they run on is not the one instrument saw them on. tauri answers on the same thread right
+// after the body returns, so respond finishes the measurement.
+pub fn begin(command: &str) {
+    let call = Call { command: command.to_string(), start: Instant::now(), lock_wait: Duration::ZERO };
+    CALL.with(|current| current.replace(Some(call)));
+}
+
+// Records the command running on this thread once its response is serialized
+fn finish(response_bytes: usize) {
+    if let Some(call) = CALL.with(|current| current.take()) {
+        metrics().entry(call.command).or_default().record(call.start.elapsed(), call.lock_wait, response_bytes);
+    }
+}
+
+// Wraps the generated invoke handler so every registered command is measured under its name. A synchronous
+// command has answered through respond before the handler returns, an async one only got queued on the
+// runtime and has to begin its own measurement there.
+pub fn instrument<R: Runtime>(handler: impl Fn(Invoke<R>) + Send + Sync + 'static) -> impl Fn(Invoke<R>) + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        begin(invoke.message.command());
+        handler(invoke);
+        CALL.with(|current| current.take());
+    }
+}
+
+// The invoke responder, the same as tauri's own one (which is not exported) apart from recording the call
+pub fn respond(window: Window, response: InvokeResponse, success: CallbackFn, error: CallbackFn) {
+    let script = format_callback_result(response.into_result(), success, error)
+        .unwrap_or_else(|e| format_callback(error, &e.to_string()).expect("unable to serialize response string to json"));
+    finish(script.len());
+    let _ = window.eval(&script);
+}
+
+#[tauri::command]
+pub fn get_command_metrics() -> Vec<CommandMetrics> {
+    let metrics = metrics();
+    metrics.iter()
+        .map(|(command, stats)| {
+            let calls = stats.calls.max(1) as f64;
+            CommandMetrics {
+                command: command.clone(),
+                calls: stats.calls,
+                mean_ms: millis(stats.total_duration) / calls,
+                p95_ms: p95(&stats.durations),
+                mean_lock_wait_ms: millis(stats.total_lock_wait) / calls,
+                p95_lock_wait_ms: p95(&stats.lock_waits),
+                mean_payload_bytes: stats.total_payload_bytes as f64 / calls,
+                max_payload_bytes: stats.max_payload_bytes,
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn reset_command_metrics() {
+    metrics().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn a_call_is_recorded_once_its_response_is_sent() {
+        begin("metrics_test_command");
+        add_lock_wait(Duration::from_millis(3));
+        finish(120);
+        add_lock_wait(Duration::from_millis(50));
+        finish(999);
+        
+        let report = get_command_metrics();
+        let stats = report.iter().find(|m| m.command == "metrics_test_command").unwrap();
+        assert_eq!(stats.calls, 1);
+        assert_eq!(stats.max_payload_bytes, 120);
+        assert!((stats.mean_lock_wait_ms - 3.0).abs() < 1e-9);
+    }
+}
@@ -1,5 +1,12 @@
 use serde::{Serialize, Deserialize};
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::spill::{SpilledTrail, TrailSpill};
+use crate::trail_export::{ExportTrail, TrailExportFormat, TrailExportOptions, TrailExportSummary};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Body {
@@ -9,6 +16,84 @@ pub struct Body {
     pub velocity: Vec2,
     pub radius: f64,
     pub color: String,
+    // Fixed bodies still attract others but are never moved by forces, drag or collisions
+    #[serde(default)]
+    pub fixed: bool,
+    // Angular velocity in radians per unit time, counter-clockwise positive like orbits
+    #[serde(default)]
+    pub spin: f64,
+    // Rotation angle in radians, advanced by spin every step
+    #[serde(default)]
+    pub orientation: f64,
+    // Marks left by hard impacts, oldest first, at most MAX_SCARS_PER_BODY
+    #[serde(default)]
+    pub scars: Vec<Scar>,
+}
+
+// Where and how hard a body was hit, for drawing surface marks that turn with the body
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scar {
+    // Direction of the contact point from the body's center in radians, measured from the body's
+    // orientation so it stays on the same spot as the body spins
+    pub angle: f64,
+    // Mark size as a fraction of the body's radius
+    pub size: f64,
+    pub step: u64,
+}
+
+impl Scar {
+    // The scar left by an impact at angle (world frame) on a body turned by orientation, None below the
+    // energy threshold
+    fn from_impact(impact_energy: f64, angle: f64, orientation: f64, step: u64) -> Option<Scar> {
+        if impact_energy <= SCAR_ENERGY_THRESHOLD {
+            return None;
+        }
+        Some(Scar {
+            angle: (angle - orientation).rem_euclid(2.0 * std::f64::consts::PI),
+            size: (0.1 * (impact_energy / SCAR_ENERGY_THRESHOLD).cbrt()).min(MAX_SCAR_SIZE),
+            step,
+        })
+    }
+}
+
+impl Body {
+    // A free body without spin or scars
+    pub fn new(id: u32, mass: f64, position: Vec2, velocity: Vec2, radius: f64, color: &str) -> Self {
+        Body {
+            id,
+            mass,
+            position,
+            velocity,
+            radius,
+            color: String::from(color),
+            fixed: false,
+            spin: 0.0,
+            orientation: 0.0,
+            scars: Vec::new(),
+        }
+    }
+    
+    fn add_scar(&mut self, scar: Scar) {
+        if self.scars.len() >= MAX_SCARS_PER_BODY {
+            self.scars.remove(0);
+        }
+        self.scars.push(scar);
+    }
+    
+    // Names of the numeric fields that hold NaN or infinity
+    fn non_finite_fields(&self) -> Vec<&'static str> {
+        let fields = [
+            ("mass", self.mass),
+            ("radius", self.radius),
+            ("position.x", self.position.x),
+            ("position.y", self.position.y),
+            ("velocity.x", self.velocity.x),
+            ("velocity.y", self.velocity.y),
+            ("spin", self.spin),
+            ("orientation", self.orientation),
+        ];
+        fields.iter().filter(|(_, v)| !v.is_finite()).map(|(name, _)| *name).collect()
+    }
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -22,304 +107,7309 @@ impl Vec2 {
         Self { x, y }
     }
     
+    #[must_use]
     pub fn distance(&self, other: &Vec2) -> f64 {
         let dx = self.x - other.x;
         let dy = self.y - other.y;
         (dx * dx + dy * dy).sqrt()
     }
+    
+    #[must_use]
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+    
+    // Cheaper than length for comparisons, no sqrt
+    #[must_use]
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+    
+    #[must_use]
+    pub fn length(self) -> f64 {
+        self.length_squared().sqrt()
+    }
+    
+    // Unit vector in the same direction, the zero vector when the length is below 1e-9
+    #[must_use]
+    pub fn normalized(self) -> Vec2 {
+        let length = self.length();
+        if length < 1e-9 {
+            Vec2::new(0.0, 0.0)
+        } else {
+            self / length
+        }
+    }
+    
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+    
+    // Rotated a quarter turn counter-clockwise
+    #[must_use]
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+    
+    // self at t = 0, other at t = 1
+    #[must_use]
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        self + (other - self) * t
+    }
+    
+    // Rotated counter-clockwise by angle radians
+    #[must_use]
+    pub fn rotate(self, angle: f64) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+impl std::ops::Add for Vec2 {
+    type Output = Vec2;
+    
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl std::ops::Sub for Vec2 {
+    type Output = Vec2;
+    
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl std::ops::Mul<f64> for Vec2 {
+    type Output = Vec2;
+    
+    fn mul(self, factor: f64) -> Vec2 {
+        self.scale(factor)
+    }
+}
+
+impl std::ops::Div<f64> for Vec2 {
+    type Output = Vec2;
+    
+    fn div(self, divisor: f64) -> Vec2 {
+        Vec2::new(self.x / divisor, self.y / divisor)
+    }
+}
+
+impl std::ops::Neg for Vec2 {
+    type Output = Vec2;
+    
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl std::ops::AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl std::ops::SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+// Palette for auto_distinct_colors; the stride is coprime with the size so neighbouring ids land far apart on the hue wheel
+const DISTINCT_PALETTE_SIZE: usize = 12;
+const DISTINCT_PALETTE_STRIDE: usize = 5;
+
+// Golden angle colors: consecutive bodies are 137.5 degrees apart so no two hues ever line up, stars stay orange
+const GOLDEN_ANGLE_DEGREES: f64 = 137.5;
+const STAR_HUE: f64 = 40.0;
+
+fn golden_angle_color(index: usize, hue_offset: f64) -> String {
+    hsv_to_hex(hue_offset + index as f64 * GOLDEN_ANGLE_DEGREES, 0.8, 0.9)
+}
+
+fn hsv_to_hex(hue: f64, saturation: f64, value: f64) -> String {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let m = value - c;
+    
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    
+    let to_byte = |channel: f64| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    format!("#{:02x}{:02x}{:02x}", to_byte(r), to_byte(g), to_byte(b))
+}
+
+pub type WatchId = u32;
+
+const MAX_WATCHES: usize = 32;
+
+pub type TrackId = u32;
+
+// Center-of-mass path of a group of bodies
+#[derive(Clone)]
+struct ComTrack {
+    id: TrackId,
+    body_ids: Vec<u32>,
+    trail: VecDeque<TrailPoint>,
 }
 
+// get_trails keys for the barycenter and COM tracks, body trails are keyed by their id
+const BARYCENTER_TRAIL_KEY: &str = "barycenter";
+const COM_TRACK_KEY_PREFIX: &str = "track-";
+
 #[derive(Clone, Serialize, Deserialize)]
-pub struct SimulationState {
-    pub bodies: Vec<Body>,
-    pub time_step: f64,
-    pub time_multiplier: f64,
-    pub gravity_constant: f64,
-    pub is_running: bool,
-    pub elapsed_time: f64,
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchQuantity {
+    Speed,
+    Altitude,
+    Eccentricity,
+    DistanceTo { other_id: u32 },
+    HillFill,
 }
 
-impl SimulationState {
-    // Alla måste börja med en vel relativt till annat obj. ex. solen för planeter / planet för månar
-    pub fn new() -> Self {
-        let g = 6.67430e-1; 
-        
-        let mut bodies = Vec::new();
-        
-        // solen
-        bodies.push(Body {
-            id: 1,
-            mass: 8.0e3,
-            position: Vec2::new(0.0, 0.0),
-            velocity: Vec2::new(0.0, 0.0),
-            radius: 25.0,
-            color: String::from("#ffcc00"),
-        });
-        
-        let sun_mass = 8.0e3;
-        
-        let planet_data = [
-            (1.0e3, 120.0, 10.0, "#ff9999"), //planet 1
-            (1.5e3, 200.0, 12.0, "#3366ff"), //planet 2
-            (3.0e3, 350.0, 18.0, "#ff6600"), //planet 3
-            (2.0e3, 450.0, 15.0, "#33ccff"), //planet 4
-        ];
-        
-        for (i, (mass, distance, radius, color)) in planet_data.iter().enumerate() {
-            let orbital_speed = f64::sqrt(g * sun_mass / distance);
-            
-            let angle = std::f64::consts::PI * 2.0 * (i as f64) / planet_data.len() as f64;
-            
-            let pos_x = angle.cos() * distance;
-            let pos_y = angle.sin() * distance;
-            
-            let vel_x = -angle.sin() * orbital_speed;
-            let vel_y = angle.cos() * orbital_speed;
-            
-            bodies.push(Body {
-                id: (i + 2) as u32,
-                mass: *mass,
-                position: Vec2::new(pos_x, pos_y),
-                velocity: Vec2::new(vel_x, vel_y),
-                radius: *radius,
-                color: String::from(*color),
-            });
-        }
-        
-        // Skapa månar för planet 3
-        let planet_index = 2;
-        
-        let planet_mass: f64;
-        let planet_pos_x: f64;
-        let planet_pos_y: f64; 
-        let planet_vel_x: f64;
-        let planet_vel_y: f64;
-        
-        {
-            let planet = &bodies[planet_index];
-            planet_mass = planet.mass;
-            planet_pos_x = planet.position.x;
-            planet_pos_y = planet.position.y;
-            planet_vel_x = planet.velocity.x;
-            planet_vel_y = planet.velocity.y;
-        }
-        
-        let moon_data = [
-            (100.0, 35.0, 4.0, "#cccccc"), //Bigger moon
-            (50.0, 25.0, 3.0, "#aaaaaa"), //Smaller moon
-        ];
-        
-        for (i, (mass, distance, radius, color)) in moon_data.iter().enumerate() {
-            let orbital_speed = f64::sqrt(g * planet_mass / distance);
-            
-            let angle = std::f64::consts::PI * (i as f64) / moon_data.len() as f64;
-            
-            let pos_x = planet_pos_x + angle.cos() * distance;
-            let pos_y = planet_pos_y + angle.sin() * distance;
-            
-            let vel_x = planet_vel_x - angle.sin() * orbital_speed;
-            let vel_y = planet_vel_y + angle.cos() * orbital_speed;
-            
-            bodies.push(Body {
-                id: (bodies.len() + 1) as u32,
-                mass: *mass,
-                position: Vec2::new(pos_x, pos_y),
-                velocity: Vec2::new(vel_x, vel_y),
-                radius: *radius,
-                color: String::from(*color),
-            });
-        }
-        
-        Self {
-            bodies,
-            time_step: 0.01,
-            time_multiplier: 1.0,
-            gravity_constant: g,
-            is_running: false,
-            elapsed_time: 0.0,
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WatchSpec {
+    pub body_id: u32,
+    pub quantity: WatchQuantity,
+    pub threshold: f64,
+}
+
+#[derive(Clone)]
+struct Watch {
+    id: WatchId,
+    spec: WatchSpec,
+    last_emitted: Option<f64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ValueChanged {
+    pub watch_id: WatchId,
+    pub body_id: u32,
+    pub value: f64,
+}
+
+// Events raised while stepping, drained and emitted to the frontend by the commands
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum EngineEvent {
+    ValueChanged(ValueChanged),
+    StateCorruption(StateCorruption),
+    RecordingPaused(RecordingPaused),
+    Heartbeat(Heartbeat),
+    FollowTargetChanged(FollowTargetChanged),
+    TimeRezeroed(TimeRezeroed),
+    TutorialCheckpoint(TutorialCheckpoint),
+    BodiesMerged(MergerEvent),
+    TidalDisruption(TidalDisruption),
+}
+
+impl EngineEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EngineEvent::ValueChanged(_) => "value-changed",
+            EngineEvent::StateCorruption(_) => "state-corruption",
+            EngineEvent::RecordingPaused(_) => "recording-paused",
+            EngineEvent::Heartbeat(_) => "engine-heartbeat",
+            EngineEvent::FollowTargetChanged(_) => "follow-target-changed",
+            EngineEvent::TimeRezeroed(_) => "time-rezeroed",
+            EngineEvent::TutorialCheckpoint(_) => "tutorial-checkpoint",
+            EngineEvent::BodiesMerged(_) => "bodies-merged",
+            EngineEvent::TidalDisruption(_) => "tidal-disruption",
         }
     }
-    
-    pub fn step(&mut self) {
-        if !self.is_running {
+}
+
+// An event with the step it belongs to: every state published with a step_count of at least step
+// already shows what the event describes
+#[derive(Clone, Serialize)]
+pub struct StampedEvent {
+    pub step: u64,
+    #[serde(flatten)]
+    pub event: EngineEvent,
+}
+
+// A state for the frontend and the events queued up to it. The state goes out as simulation-tick
+// before the events, so a listener sees a state before any event about a change it contains, e.g. a
+// merged-away body is already gone from the last state when its bodies-merged event arrives.
+pub struct Publication {
+    pub state: SimulationState,
+    pub events: Vec<StampedEvent>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CorruptField {
+    pub body_id: u32,
+    pub field: String,
+}
+
+// Sent by the idle ticker so tools can tell a paused engine from a dead one
+#[derive(Clone, Serialize)]
+pub struct Heartbeat {
+    pub elapsed_time: f64,
+    pub step_count: u64,
+    pub is_running: bool,
+}
+
+// The followed body merged into another one, which is followed from now on, or was removed (body_id None)
+#[derive(Clone, Serialize)]
+pub struct FollowTargetChanged {
+    pub elapsed_time: f64,
+    pub previous_body_id: u32,
+    pub body_id: Option<u32>,
+}
+
+// elapsed_time became 0, everything stamped with a time moved back by previous_elapsed_time.
+// previous_step_count is set when the step counter was reset too.
+#[derive(Clone, Serialize)]
+pub struct TimeRezeroed {
+    pub previous_elapsed_time: f64,
+    pub previous_step_count: Option<u64>,
+}
+
+// Stepping reached a tutorial checkpoint, index is its position in the tutorial
+#[derive(Clone, Serialize)]
+pub struct TutorialCheckpoint {
+    pub index: usize,
+    pub elapsed_time: f64,
+    pub step: TutorialStep,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecordingPaused {
+    pub elapsed_time: f64,
+    pub reason: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StateCorruption {
+    pub elapsed_time: f64,
+    pub fields: Vec<CorruptField>,
+}
+
+const DEFAULT_FOLLOW_LOOKAHEAD: f64 = 1.0;
+const DEFAULT_FOLLOW_SMOOTHING_TIME: f64 = 0.5;
+
+// Body the camera follows and the smoothed anchor kept for it, advanced every step
+#[derive(Clone)]
+struct FollowTarget {
+    body_id: u32,
+    lookahead: f64,
+    smoothing_time: f64,
+    anchor: Vec2,
+    anchor_velocity: Vec2,
+}
+
+impl FollowTarget {
+    // Critically damped spring toward the target position, integrated exactly enough to stay stable at any
+    // time step (the SmoothDamp approximation of exp(-omega * dt))
+    fn advance(&mut self, target: Vec2, dt: f64) {
+        if self.smoothing_time <= 0.0 {
+            self.anchor = target;
+            self.anchor_velocity = Vec2::new(0.0, 0.0);
             return;
         }
         
-        let effective_time_step = self.time_step * self.time_multiplier;
-        
-        let forces = self.calculate_forces();
-        
-        for (i, body) in self.bodies.iter_mut().enumerate() {
-            let force = &forces[i];
-            let acc_x = force.x / body.mass;
-            let acc_y = force.y / body.mass;
-            
-            body.velocity.x += acc_x * effective_time_step;
-            body.velocity.y += acc_y * effective_time_step;
-            
-            body.position.x += body.velocity.x * effective_time_step;
-            body.position.y += body.velocity.y * effective_time_step;
-        }
-        
-        self.handle_collisions();
-        
-        self.elapsed_time += effective_time_step;
+        let omega = 2.0 / self.smoothing_time;
+        let x = omega * dt;
+        let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+        let offset = self.anchor - target;
+        let pull = (self.anchor_velocity + offset * omega) * dt;
+        self.anchor_velocity = (self.anchor_velocity - pull * omega) * decay;
+        self.anchor = target + (offset + pull) * decay;
     }
-    
-    fn handle_collisions(&mut self) {
-        let mut collision_data = Vec::new();
-        
-        for i in 0..self.bodies.len() {
-            for j in (i+1)..self.bodies.len() {
-                let body1 = &self.bodies[i];
-                let body2 = &self.bodies[j];
-                
-                let distance = body1.position.distance(&body2.position);
-                
-                if distance < body1.radius + body2.radius {
-                    let dx = body2.position.x - body1.position.x;
-                    let dy = body2.position.y - body1.position.y;
-                    let inv_dist = 1.0 / distance.max(0.001);
-                    let nx = dx * inv_dist;
-                    let ny = dy * inv_dist;
-                    
-                    let dvx = body2.velocity.x - body1.velocity.x;
-                    let dvy = body2.velocity.y - body1.velocity.y;
-                    let relative_vel_dot_normal = dvx * nx + dvy * ny;
-                    
-                    if relative_vel_dot_normal < 0.0 {
-                        let restitution = 0.7;
-                        let inv_mass1 = 1.0 / body1.mass;
-                        let inv_mass2 = 1.0 / body2.mass;
-                        let impulse_scalar = -(1.0 + restitution) * relative_vel_dot_normal /
-                                            (inv_mass1 + inv_mass2);
-                        
-                        let impulse_x = impulse_scalar * nx;
-                        let impulse_y = impulse_scalar * ny;
-                        
-                        let vel_change_i = Vec2::new(
-                            -impulse_x * inv_mass1,
-                            -impulse_y * inv_mass1
-                        );
-                        
-                        let vel_change_j = Vec2::new(
-                            impulse_x * inv_mass2,
-                            impulse_y * inv_mass2
-                        );
-                        
-                        let penetration = (body1.radius + body2.radius) - distance;
-                        let percent = 0.4; 
-                        let correction_x = nx * penetration * percent;
-                        let correction_y = ny * penetration * percent;
-                        
-                        let pos_corr_i = Vec2::new(
-                            -correction_x * inv_mass1 / (inv_mass1 + inv_mass2),
-                            -correction_y * inv_mass1 / (inv_mass1 + inv_mass2)
-                        );
-                        
-                        let pos_corr_j = Vec2::new(
-                            correction_x * inv_mass2 / (inv_mass1 + inv_mass2),
-                            correction_y * inv_mass2 / (inv_mass1 + inv_mass2)
-                        );
-                        
-                        collision_data.push((i, j, vel_change_i, vel_change_j, pos_corr_i, pos_corr_j));
-                    }
-                }
-            }
-        }
-        
-        for (i, j, vel_i, vel_j, pos_i, pos_j) in collision_data {
-            self.bodies[i].velocity.x += vel_i.x;
-            self.bodies[i].velocity.y += vel_i.y;
-            self.bodies[j].velocity.x += vel_j.x;
-            self.bodies[j].velocity.y += vel_j.y;
-            
-            self.bodies[i].position.x += pos_i.x;
-            self.bodies[i].position.y += pos_i.y;
-            self.bodies[j].position.x += pos_j.x;
-            self.bodies[j].position.y += pos_j.y;
-        }
+}
+
+#[derive(Clone, Serialize)]
+pub struct FollowAnchor {
+    pub body_id: u32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    // Where the body will be `lookahead` time units from now at its current acceleration
+    pub predicted_position: Vec2,
+    pub smoothed_position: Vec2,
+}
+
+// Brings a saved scenario up to SCENARIO_SCHEMA_VERSION, one version at a time
+fn migrate_scenario(mut value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let version = match value.get("schema_version") {
+        None => default_schema_version(),
+        Some(v) => v.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or("invalid scenario file: schema_version must be a whole number")?,
+    };
+    if version > SCENARIO_SCHEMA_VERSION {
+        return Err(format!("the scenario has schema version {}, this version reads up to {}", version, SCENARIO_SCHEMA_VERSION));
     }
     
-    fn calculate_forces(&self) -> Vec<Vec2> {
-        let mut forces = vec![Vec2::new(0.0, 0.0); self.bodies.len()];
-        
-        for i in 0..self.bodies.len() {
-            for j in (i+1)..self.bodies.len() {
-                let body1 = &self.bodies[i];
-                let body2 = &self.bodies[j];
-                
-                let dist = body1.position.distance(&body2.position);
-                
-                let min_dist = (body1.radius + body2.radius) * 0.8;
-                let clamped_dist = dist.max(min_dist);
-                
-                let force_magnitude = self.gravity_constant * body1.mass * body2.mass / (clamped_dist * clamped_dist);
-                
-                let dx = body2.position.x - body1.position.x;
-                let dy = body2.position.y - body1.position.y;
-                
-                let force_x = force_magnitude * dx / dist;
-                let force_y = force_magnitude * dy / dist;
-                
-                forces[i].x += force_x;
-                forces[i].y += force_y;
-                
-                forces[j].x -= force_x;
-                forces[j].y -= force_y;
+    if let Some(object) = value.as_object_mut() {
+        // Version 2 moved gravity_constant and softening into the constants block
+        if version < 2 {
+            let mut constants = serde_json::Map::new();
+            for key in ["gravity_constant", "softening"] {
+                if let Some(v) = object.remove(key) {
+                    constants.insert(String::from(key), v);
+                }
             }
+            object.insert(String::from("constants"), serde_json::Value::Object(constants));
         }
-        forces
+        object.insert(String::from("schema_version"), serde_json::Value::from(SCENARIO_SCHEMA_VERSION));
     }
+    Ok(value)
 }
 
+fn emit_events(window: &tauri::Window, publication: Option<Publication>) {
+    let Some(publication) = publication else { return };
+    let _ = window.emit("simulation-tick", &publication.state);
+    for event in publication.events {
+        let _ = window.emit(event.event.name(), &event);
+    }
+}
 
-lazy_static::lazy_static! {
-    static ref SIMULATION: Arc<Mutex<SimulationState>> = Arc::new(Mutex::new(SimulationState::new()));
+fn emit_publication(app: &tauri::AppHandle, publication: Publication) {
+    let _ = app.emit_all("simulation-tick", &publication.state);
+    for event in publication.events {
+        let _ = app.emit_all(event.event.name(), &event);
+    }
 }
 
-//Tauri commands
-#[tauri::command]
-pub fn get_simulation_state() -> SimulationState {
-    SIMULATION.lock().unwrap().clone()
+// Publishes the current state to the window, as simulation-tick too when events follow it, and returns it
+fn publish_state(window: &tauri::Window, sim: &mut SimulationState) -> SimulationState {
+    let publication = sim.publish();
+    if publication.events.is_empty() {
+        return publication.state;
+    }
+    let state = publication.state.clone();
+    emit_events(window, Some(publication));
+    state
 }
 
-#[tauri::command]
-pub fn set_simulation_running(running: bool) {
-    SIMULATION.lock().unwrap().is_running = running;
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    // Angles in radians, measured counter-clockwise from the +x axis
+    pub argument_of_periapsis: f64,
+    pub true_anomaly: f64,
+    pub period: f64,
 }
 
-#[tauri::command]
-pub fn reset_simulation() {
-    let mut sim = SIMULATION.lock().unwrap();
-    *sim = SimulationState::new();
+// Canonical coordinates of a Kepler orbit: the action is the Delaunay L = sqrt(mu * a), its angle the mean anomaly
+#[derive(Clone, Serialize)]
+pub struct ActionAngleVars {
+    pub action: f64,
+    pub angle: f64,
+    pub mean_anomaly: f64,
+    pub eccentric_anomaly: f64,
+    pub true_anomaly: f64,
 }
 
-#[tauri::command]
-pub fn step_simulation() -> SimulationState {
-    let mut sim = SIMULATION.lock().unwrap();
-    sim.step();
-    sim.clone()
+// Eccentric anomaly E from the mean anomaly M for an elliptic orbit, Newton's method on E - e*sin(E) = M
+pub fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let m = mean_anomaly.rem_euclid(2.0 * std::f64::consts::PI);
+    let mut e_anomaly = if eccentricity > 0.8 { std::f64::consts::PI } else { m };
+    for _ in 0..50 {
+        let delta = (e_anomaly - eccentricity * e_anomaly.sin() - m) / (1.0 - eccentricity * e_anomaly.cos());
+        e_anomaly -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+    e_anomaly
 }
 
-#[tauri::command]
-pub fn set_time_multiplier(multiplier: f64) {
-    let mut sim = SIMULATION.lock().unwrap();
-    sim.time_multiplier = multiplier;
+// Two-body orbit of a body around a chosen primary, in the primary's frame
+struct RelativeOrbit {
+    mu: f64,
+    position: Vec2,
+    velocity: Vec2,
 }
 
-#[tauri::command]
-pub fn update_body(id: u32, mass: Option<f64>, position_x: Option<f64>, position_y: Option<f64>, 
-                    velocity_x: Option<f64>, velocity_y: Option<f64>, radius: Option<f64>, color: Option<String>) {
-    let mut sim = SIMULATION.lock().unwrap();
+impl RelativeOrbit {
+    #[must_use]
+    fn radius(&self) -> f64 {
+        (self.position.x * self.position.x + self.position.y * self.position.y).sqrt()
+    }
     
-    if let Some(body) = sim.bodies.iter_mut().find(|b| b.id == id) {
-        if let Some(m) = mass { body.mass = m; }
-        if let Some(px) = position_x { body.position.x = px; }
-        if let Some(py) = position_y { body.position.y = py; }
-        if let Some(vx) = velocity_x { body.velocity.x = vx; }
-        if let Some(vy) = velocity_y { body.velocity.y = vy; }
-        if let Some(r) = radius { body.radius = r; }
-        if let Some(c) = color { body.color = c; }
+    // Velocity change onto a circular orbit at the current distance going the same way round, None at r = 0
+    #[must_use]
+    fn circularizing_delta_v(&self) -> Option<Vec2> {
+        let r = self.radius();
+        if r <= 0.0 {
+            return None;
+        }
+        
+        let direction = if self.angular_momentum() < 0.0 { -1.0 } else { 1.0 };
+        let circular_speed = (self.mu / r).sqrt();
+        let target = Vec2::new(
+            -self.position.y / r * circular_speed * direction,
+            self.position.x / r * circular_speed * direction,
+        );
+        Some(Vec2::new(target.x - self.velocity.x, target.y - self.velocity.y))
     }
-}
\ No newline at end of file
+    
+    #[must_use]
+    fn speed_squared(&self) -> f64 {
+        self.velocity.x * self.velocity.x + self.velocity.y * self.velocity.y
+    }
+    
+    #[must_use]
+    fn specific_energy(&self) -> f64 {
+        0.5 * self.speed_squared() - self.mu / self.radius()
+    }
+    
+    // None for unbound (parabolic or hyperbolic) orbits
+    #[must_use]
+    fn semi_major_axis(&self) -> Option<f64> {
+        let energy = self.specific_energy();
+        if energy < 0.0 {
+            Some(-self.mu / (2.0 * energy))
+        } else {
+            None
+        }
+    }
+    
+    #[must_use]
+    fn eccentricity_vector(&self) -> Vec2 {
+        let r = self.radius();
+        let r_dot_v = self.position.x * self.velocity.x + self.position.y * self.velocity.y;
+        let k = self.speed_squared() - self.mu / r;
+        Vec2::new(
+            (k * self.position.x - r_dot_v * self.velocity.x) / self.mu,
+            (k * self.position.y - r_dot_v * self.velocity.y) / self.mu,
+        )
+    }
+    
+    #[must_use]
+    fn eccentricity(&self) -> f64 {
+        let e = self.eccentricity_vector();
+        (e.x * e.x + e.y * e.y).sqrt()
+    }
+    
+    #[must_use]
+    fn angular_momentum(&self) -> f64 {
+        self.position.x * self.velocity.y - self.position.y * self.velocity.x
+    }
+    
+    #[must_use]
+    fn elements(&self) -> Option<OrbitalElements> {
+        let semi_major_axis = self.semi_major_axis()?;
+        let e = self.eccentricity_vector();
+        let argument_of_periapsis = e.y.atan2(e.x);
+        
+        // Anomalies run the other way around for clockwise orbits
+        let mut true_anomaly = self.position.y.atan2(self.position.x) - argument_of_periapsis;
+        if self.angular_momentum() < 0.0 {
+            true_anomaly = -true_anomaly;
+        }
+        
+        Some(OrbitalElements {
+            semi_major_axis,
+            eccentricity: (e.x * e.x + e.y * e.y).sqrt(),
+            argument_of_periapsis,
+            true_anomaly: true_anomaly.rem_euclid(2.0 * std::f64::consts::PI),
+            period: 2.0 * std::f64::consts::PI * (semi_major_axis.powi(3) / self.mu).sqrt(),
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventFilter {
+    // Any collision, or only collisions involving the given body
+    Collision { body_id: Option<u32> },
+    // Periapsis or apoapsis passage of the body around whatever it currently orbits
+    Apsis { body_id: u32 },
+    // The two bodies line up as seen from the center body, within the tolerance
+    Alignment { body_ids: (u32, u32), center_id: u32, tolerance_degrees: f64 },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DetectedEvent {
+    Collision { body_ids: (u32, u32) },
+    Periapsis { body_id: u32, parent_id: u32, distance: f64 },
+    Apoapsis { body_id: u32, parent_id: u32, distance: f64 },
+    Alignment { body_ids: (u32, u32), center_id: u32, separation_degrees: f64 },
+}
+
+// Keeps what the detection passes need from the previous step, so an event fires on the step it happens
+struct EventDetector {
+    filter: EventFilter,
+    previous_radial_velocity: Option<f64>,
+    previously_aligned: Option<bool>,
+}
+
+impl EventDetector {
+    fn new(filter: EventFilter) -> Self {
+        Self { filter, previous_radial_velocity: None, previously_aligned: None }
+    }
+    
+    fn check(&mut self, sim: &SimulationState) -> Option<DetectedEvent> {
+        match self.filter {
+            EventFilter::Collision { body_id } => sim.last_step_collisions.iter()
+                .find(|c| body_id.is_none_or(|id| id == c.body_ids.0 || id == c.body_ids.1))
+                .map(|c| DetectedEvent::Collision { body_ids: c.body_ids }),
+            EventFilter::Apsis { body_id } => {
+                let index = sim.body_index(body_id)?;
+                let parent_index = sim.parent_of(index)?;
+                let orbit = sim.relative_orbit(&sim.bodies[index], &sim.bodies[parent_index]);
+                let radial_velocity = orbit.position.x * orbit.velocity.x + orbit.position.y * orbit.velocity.y;
+                let previous = self.previous_radial_velocity.replace(radial_velocity)?;
+                
+                let parent_id = sim.bodies[parent_index].id;
+                let distance = orbit.radius();
+                if previous < 0.0 && radial_velocity >= 0.0 {
+                    Some(DetectedEvent::Periapsis { body_id, parent_id, distance })
+                } else if previous > 0.0 && radial_velocity <= 0.0 {
+                    Some(DetectedEvent::Apoapsis { body_id, parent_id, distance })
+                } else {
+                    None
+                }
+            }
+            EventFilter::Alignment { body_ids, center_id, tolerance_degrees } => {
+                let center = &sim.bodies[sim.body_index(center_id)?];
+                let a = &sim.bodies[sim.body_index(body_ids.0)?];
+                let b = &sim.bodies[sim.body_index(body_ids.1)?];
+                
+                let angle_a = (a.position.y - center.position.y).atan2(a.position.x - center.position.x);
+                let angle_b = (b.position.y - center.position.y).atan2(b.position.x - center.position.x);
+                let separation = (angle_a - angle_b).rem_euclid(2.0 * std::f64::consts::PI);
+                let separation_degrees = separation.min(2.0 * std::f64::consts::PI - separation).to_degrees();
+                
+                // Only the step where the bodies enter the tolerance counts
+                let aligned = separation_degrees <= tolerance_degrees;
+                let was_aligned = self.previously_aligned.replace(aligned)?;
+                if aligned && !was_aligned {
+                    Some(DetectedEvent::Alignment { body_ids, center_id, separation_degrees })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+pub const RESTITUTION: f64 = 0.7;
+
+// Impact energy, 1/2 * reduced mass * normal speed^2, below which a collision leaves no scar
+const SCAR_ENERGY_THRESHOLD: f64 = 50.0;
+const MAX_SCARS_PER_BODY: usize = 16;
+const MAX_SCAR_SIZE: f64 = 0.5;
+
+const DEFAULT_TRAIL_MAX_POINTS: usize = 500;
+const MAX_TRAIL_POINTS: usize = 100_000;
+const DEFAULT_IDLE_TICK_RATE: f64 = 4.0;
+// Bump when saved scenarios change in a way serde defaults can't cover, and add the step to migrate_scenario
+const SCENARIO_SCHEMA_VERSION: u32 = 2;
+// 100 times the default, larger time steps are clamped to this. So is the step the time multiplier makes.
+const MAX_TIME_STEP: f64 = 1.0;
+const MAX_TIME_MULTIPLIER: f64 = 1_000.0;
+const MAX_IDLE_TICK_RATE: f64 = 60.0;
+// Steps per second of the physics thread, the pace the frontend used to step at
+const PHYSICS_TICK_RATE: f64 = 60.0;
+const DEFAULT_EMIT_RATE: f64 = 60.0;
+// Plummer softening length. Small next to the closest default orbit (a moon 25 from its planet, whose
+// pull it weakens by 0.2%) but it keeps the force finite when bodies overlap.
+const DEFAULT_SOFTENING: f64 = 1.0;
+pub const DEFAULT_GRAVITY_CONSTANT: f64 = 6.67430e-1;
+// Loaded constants this many times above or below the defaults get a warning
+const CONSTANT_WARNING_FACTOR: f64 = 10.0;
+// Mass ratio above which the heavier body of a bounce is pinned. Past about 1e16 its velocity change is
+// below the precision of its velocity anyway.
+const DEFAULT_PIN_MASS_RATIO: f64 = 1.0e15;
+// Turn per step (radians) above which a trail gets interpolated points between the step's endpoints
+const TRAIL_CURVATURE_THRESHOLD: f64 = 0.1;
+const MAX_TRAIL_SUBDIVISIONS: usize = 8;
+
+const MAX_PREDICTION_STEPS: u32 = 100_000;
+const MAX_FAST_FORWARD_STEPS: u64 = 2_000_000;
+const PROGRESS_EVENT_INTERVAL: u64 = 5_000;
+const MAX_POINCARE_STEPS: u64 = 10_000_000;
+const MAX_POINCARE_CROSSINGS: u32 = 10_000;
+const MAX_LYAPUNOV_STEPS: u32 = 1_000_000;
+const LYAPUNOV_PERTURBATION: f64 = 1e-8;
+
+// Point masses a ring force is sampled with, see RingForce::points
+const RING_CIRCLES: usize = 5;
+const RING_POINTS_PER_CIRCLE: usize = 20;
+const RING_FORCE_POINTS: usize = RING_CIRCLES * RING_POINTS_PER_CIRCLE;
+
+// Coefficient of Wisdom's resonance overlap criterion, delta_a / a < C * mu^(2/7)
+const WISDOM_OVERLAP_COEFFICIENT: f64 = 1.3;
+
+// Bodies this light next to the whole system only mark out the flow, see RegionQueryOptions
+const TRACER_MASS_FRACTION: f64 = 1e-6;
+
+// Steps between time step recomputations when auto_courant is set
+const COURANT_RECHECK_INTERVAL: u64 = 100;
+
+// Long-running commands claim the busy flag so only one runs at a time and cancel_operation can stop it
+static OPERATION_BUSY: AtomicBool = AtomicBool::new(false);
+static OPERATION_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+struct BusyOperation;
+
+impl BusyOperation {
+    fn begin() -> Result<Self, String> {
+        if OPERATION_BUSY.swap(true, Ordering::SeqCst) {
+            return Err(String::from("another operation is already running"));
+        }
+        OPERATION_CANCELLED.store(false, Ordering::SeqCst);
+        Ok(BusyOperation)
+    }
+    
+    fn cancelled(&self) -> bool {
+        OPERATION_CANCELLED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for BusyOperation {
+    fn drop(&mut self) {
+        OPERATION_BUSY.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct OperationProgress {
+    pub operation: &'static str,
+    pub progress: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FastForwardResult {
+    pub event: Option<DetectedEvent>,
+    pub elapsed: f64,
+    pub steps: u64,
+    pub cancelled: bool,
+}
+
+// Progress of a fast-forward over the live simulation, carried from one batch to the next
+#[derive(Default)]
+struct FastForwardRun {
+    steps: u64,
+    elapsed: f64,
+    stopped: bool,
+    cancelled: bool,
+}
+
+impl FastForwardRun {
+    fn finished(&self, max_sim_time: f64) -> bool {
+        self.stopped || self.cancelled || self.elapsed >= max_sim_time || self.steps >= MAX_FAST_FORWARD_STEPS
+    }
+}
+
+// Laplace coefficient b_{3/2}^{(1)}(alpha) = 1/pi * integral over 0..2pi of cos(psi) / (1 - 2 alpha cos(psi) + alpha^2)^(3/2)
+fn laplace_coefficient(alpha: f64) -> f64 {
+    let intervals = 512;
+    let h = 2.0 * std::f64::consts::PI / intervals as f64;
+    let integrand = |psi: f64| psi.cos() / (1.0 - 2.0 * alpha * psi.cos() + alpha * alpha).powf(1.5);
+    
+    // Simpson's rule
+    let mut sum = integrand(0.0) + integrand(2.0 * std::f64::consts::PI);
+    for k in 1..intervals {
+        let weight = if k % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * integrand(k as f64 * h);
+    }
+    sum * h / 3.0 / std::f64::consts::PI
+}
+
+// Every unordered pair of bodies exactly once, in (i, j) order with i < j
+pub struct BodyPairs<'a> {
+    bodies: &'a [Body],
+    i: usize,
+    j: usize,
+}
+
+impl<'a> BodyPairs<'a> {
+    pub fn new(bodies: &'a [Body]) -> Self {
+        Self { bodies, i: 0, j: 1 }
+    }
+    
+    // Same as next() but also yields the indices, for loops that write back into per-body arrays
+    pub fn next_indexed(&mut self) -> Option<(usize, usize, &'a Body, &'a Body)> {
+        if self.j >= self.bodies.len() {
+            self.i += 1;
+            self.j = self.i + 1;
+            if self.j >= self.bodies.len() {
+                return None;
+            }
+        }
+        
+        let pair = (self.i, self.j, &self.bodies[self.i], &self.bodies[self.j]);
+        self.j += 1;
+        Some(pair)
+    }
+}
+
+impl<'a> Iterator for BodyPairs<'a> {
+    type Item = (&'a Body, &'a Body);
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_indexed().map(|(_, _, a, b)| (a, b))
+    }
+}
+
+#[derive(Clone)]
+struct Contact {
+    body_ids: (u32, u32),
+    point: Vec2,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct TrailPoint {
+    pub position: Vec2,
+    pub step: u64,
+}
+
+// Cubic Hermite interpolation between two samples with known velocities, t in [0, 1]
+fn hermite(p0: Vec2, v0: Vec2, p1: Vec2, v1: Vec2, dt: f64, t: f64) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+    let h10 = t3 - 2.0 * t2 + t;
+    let h01 = -2.0 * t3 + 3.0 * t2;
+    let h11 = t3 - t2;
+    Vec2::new(
+        h00 * p0.x + h10 * dt * v0.x + h01 * p1.x + h11 * dt * v1.x,
+        h00 * p0.y + h10 * dt * v0.y + h01 * p1.y + h11 * dt * v1.y,
+    )
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Moderate,
+    High,
+}
+
+#[derive(Clone, Serialize)]
+pub struct BindingReport {
+    pub body_id: u32,
+    pub parent_id: u32,
+    // Two-body orbital energy relative to the parent, negative while bound
+    pub binding_energy: f64,
+    pub perturber_id: Option<u32>,
+    // Tidal acceleration from the strongest third body as a fraction of the parent's pull
+    pub perturbation_ratio: f64,
+    // Distance to the parent over the parent's Hill radius, 0 when the parent orbits nothing
+    pub hill_fill_fraction: f64,
+    pub risk: RiskLevel,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EnergyReport {
+    pub kinetic: f64,
+    pub potential: f64,
+    pub total: f64,
+}
+
+// Angular momentum about the origin, see Diagnostics for the one about the barycenter
+#[derive(Clone, Serialize)]
+pub struct MomentumReport {
+    pub linear: Vec2,
+    pub angular: f64,
+}
+
+// Conserved quantities of the whole scene, energies as in EnergyReport. Angular momentum is orbital only,
+// body spins are left out, and it is the same in the barycentric frame since it is taken about the barycenter.
+#[derive(Clone, Serialize)]
+pub struct Diagnostics {
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub total_energy: f64,
+    pub momentum: Vec2,
+    pub angular_momentum: f64,
+    pub barycenter: Vec2,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EncounterPrediction {
+    pub min_distance: f64,
+    pub elapsed_time_at_minimum: f64,
+}
+
+const MAX_GRID_SIDE: u32 = 1_000;
+
+// 2^16 cells per side already needs billions of bodies to be meaningful
+const MAX_BOX_COUNT_SCALES: u32 = 16;
+
+const MAX_ASCII_WIDTH: u32 = 200;
+const MAX_ASCII_HEIGHT: u32 = 80;
+// Terminal cells are about twice as tall as they are wide
+const ASCII_CELL_ASPECT: f64 = 2.0;
+// Bodies orbiting a star with less than this fraction of its mass are drawn as asteroids
+const ASTEROID_MASS_FRACTION: f64 = 0.01;
+
+const MAX_SCATTERING_TRIALS: u32 = 1_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScatteringOutcome {
+    Merged,
+    Ejected,
+    Bound,
+}
+
+// Outcomes of a scattering survey, merged + ejected + bound is always trials. Speeds are the incoming
+// body's relative to the center of mass of everything else, taken at contact for merged trials.
+#[derive(Clone, Serialize)]
+pub struct ScatteringSummary {
+    pub trials: u32,
+    pub merged: u32,
+    pub ejected: u32,
+    pub bound: u32,
+    pub mean_final_speed: f64,
+}
+
+const TRANSFER_SAMPLES_PER_PERIOD: f64 = 100.0;
+const TRANSFER_WINDOWS: usize = 10;
+
+#[derive(Clone, Serialize)]
+pub struct HohmannTransfer {
+    // Both burns plus the phasing correction
+    pub delta_v: f64,
+    pub transfer_time: f64,
+    // Radians the target is off the arrival point when the craft gets there, positive when it is ahead
+    pub phase_error: f64,
+    pub departure_period: f64,
+}
+
+const DISRUPTION_FRAGMENTS: u32 = 20;
+// Fluid Roche limit d = 2.44 * R_M * (rho_M / rho_m)^(1/3), with densities from mass / radius^3
+const ROCHE_COEFFICIENT: f64 = 2.44;
+
+// A fluid body that got deeper inside the Roche limit of a heavier body than the configured threshold was
+// torn into a stream of fragments
+#[derive(Clone, Serialize)]
+pub struct TidalDisruption {
+    pub body_id: u32,
+    pub primary_id: u32,
+    // Leading fragment first
+    pub fragment_ids: Vec<u32>,
+    pub elapsed_time: f64,
+}
+
+// Constant extra acceleration on a body for a number of steps
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Burn {
+    pub acceleration: Vec2,
+    pub remaining_steps: u32,
+}
+
+// Drag that is strongest at the center of mass and falls off as 1 / (1 + (r / scale_radius)^2)
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RadialDrag {
+    pub central_drag: f64,
+    pub scale_radius: f64,
+}
+
+pub type ConstraintId = u32;
+
+// Soft constraint that nudges a body's tangential speed until its orbit around the primary has the
+// target semi-major axis, a weak spring in orbital-element space rather than an exact circle
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OrbitConstraint {
+    pub id: ConstraintId,
+    pub body_id: u32,
+    pub primary_id: u32,
+    pub target_sma: f64,
+    // Tangential speed change per unit time per unit of semi-major axis error
+    pub strength: f64,
+}
+
+pub type RingId = u32;
+
+// Uniform disk of material between two radii around a body. Its gravity comes from RING_FORCE_POINTS
+// point masses spread over the disk, which move with the center body and pass their reaction on to it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RingForce {
+    pub id: RingId,
+    pub center_id: u32,
+    pub ring_mass: f64,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+}
+
+impl RingForce {
+    // Concentric circles of RING_POINTS_PER_CIRCLE points each, every circle standing for an equal-width
+    // band of the disk with mass in proportion to the band's area
+    fn points(&self, center: Vec2) -> Vec<(Vec2, f64)> {
+        let width = (self.outer_radius - self.inner_radius) / RING_CIRCLES as f64;
+        let total_area = self.outer_radius.powi(2) - self.inner_radius.powi(2);
+        
+        let mut points = Vec::with_capacity(RING_FORCE_POINTS);
+        for circle in 0..RING_CIRCLES {
+            let r0 = self.inner_radius + width * circle as f64;
+            let r1 = r0 + width;
+            let band_mass = self.ring_mass * (r1 * r1 - r0 * r0) / total_area;
+            // Radius that splits the band into two halves of equal area
+            let r = ((r0 * r0 + r1 * r1) / 2.0).sqrt();
+            for k in 0..RING_POINTS_PER_CIRCLE {
+                let angle = 2.0 * std::f64::consts::PI * k as f64 / RING_POINTS_PER_CIRCLE as f64;
+                points.push((center + Vec2::new(r, 0.0).rotate(angle), band_mass / RING_POINTS_PER_CIRCLE as f64));
+            }
+        }
+        points
+    }
+    
+    // Bodies inside the disk would otherwise get huge kicks passing right by a point
+    fn softening(&self) -> f64 {
+        let radial_spacing = (self.outer_radius - self.inner_radius) / RING_CIRCLES as f64;
+        let angular_spacing = 2.0 * std::f64::consts::PI * self.outer_radius / RING_POINTS_PER_CIRCLE as f64;
+        radial_spacing.max(angular_spacing) / 2.0
+    }
+}
+
+// Two bodies became one. The result keeps the id of one of them, usually the heavier.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MergerEvent {
+    pub result_id: u32,
+    pub body1_id: u32,
+    pub body2_id: u32,
+    pub elapsed_time: f64,
+    pub combined_mass: f64,
+}
+
+// Loudness and placement hints for audio feedback, one per collision resolved in the last step
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CollisionSoundHint {
+    pub elapsed_time: f64,
+    pub relative_speed: f64,
+    pub combined_mass: f64,
+    pub distance_from_com: f64,
+    // -1 at the left edge of the bodies' bounding box, 1 at the right edge
+    pub stereo_pan: f64,
+}
+
+// Starting angle of a body around its parent. Relative angles are measured from the parent's direction as
+// seen from its own parent, e.g. from the planet-sun line for a moon. Planets have no grandparent, so for
+// them both kinds are measured from the +x axis.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "frame", content = "degrees", rename_all = "snake_case")]
+pub enum PhaseAngle {
+    Absolute(f64),
+    RelativeToParent(f64),
+}
+
+impl PhaseAngle {
+    fn degrees(&self) -> f64 {
+        match self {
+            PhaseAngle::Absolute(degrees) | PhaseAngle::RelativeToParent(degrees) => *degrees,
+        }
+    }
+    
+    fn radians(&self, parent_direction: f64) -> f64 {
+        match self {
+            PhaseAngle::Absolute(degrees) => degrees.to_radians(),
+            PhaseAngle::RelativeToParent(degrees) => parent_direction + degrees.to_radians(),
+        }
+    }
+}
+
+// Per-body angles for the default system, in preset order. Missing or None entries use the usual spacing.
+#[derive(Clone, Default, Deserialize)]
+pub struct PresetPhases {
+    #[serde(default)]
+    pub planets: Vec<Option<PhaseAngle>>,
+    #[serde(default)]
+    pub moons: Vec<Option<PhaseAngle>>,
+}
+
+impl PresetPhases {
+    const PLANET_COUNT: usize = 4;
+    const MOON_COUNT: usize = 2;
+    
+    pub fn validate(&self) -> Result<(), String> {
+        if self.planets.len() > Self::PLANET_COUNT {
+            return Err(format!("the preset has {} planets, got {} phases", Self::PLANET_COUNT, self.planets.len()));
+        }
+        if self.moons.len() > Self::MOON_COUNT {
+            return Err(format!("the preset has {} moons, got {} phases", Self::MOON_COUNT, self.moons.len()));
+        }
+        if self.planets.iter().chain(&self.moons).flatten().any(|p| !p.degrees().is_finite()) {
+            return Err(String::from("phase angles must be finite"));
+        }
+        Ok(())
+    }
+    
+    fn planet_angle(&self, index: usize, parent_direction: f64) -> Option<f64> {
+        self.planets.get(index).copied().flatten().map(|p| p.radians(parent_direction))
+    }
+    
+    fn moon_angle(&self, index: usize, parent_direction: f64) -> Option<f64> {
+        self.moons.get(index).copied().flatten().map(|p| p.radians(parent_direction))
+    }
+}
+
+// A disk of count light bodies between inner_r and outer_r with no bodies within gap_width / 2 of gap_center
+#[derive(Clone, Copy, Deserialize)]
+pub struct DiskWithGap {
+    pub center_mass: f64,
+    pub inner_r: f64,
+    pub outer_r: f64,
+    pub gap_center: f64,
+    pub gap_width: f64,
+    pub count: u32,
+    pub seed: u64,
+}
+
+// Region in world coordinates, a rectangle given by any two opposite corners or a circle
+#[derive(Clone, Copy, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RegionShape {
+    Rectangle { corner1: Vec2, corner2: Vec2 },
+    Circle { center: Vec2, radius: f64 },
+}
+
+impl RegionShape {
+    // Whether a disc overlaps the region, touching counts
+    fn intersects_disc(&self, center: Vec2, radius: f64) -> bool {
+        match *self {
+            RegionShape::Rectangle { corner1, corner2 } => {
+                let closest = Vec2::new(
+                    center.x.clamp(corner1.x.min(corner2.x), corner1.x.max(corner2.x)),
+                    center.y.clamp(corner1.y.min(corner2.y), corner1.y.max(corner2.y)),
+                );
+                closest.distance(&center) <= radius
+            }
+            RegionShape::Circle { center: region_center, radius: region_radius } => {
+                region_center.distance(&center) <= region_radius + radius
+            }
+        }
+    }
+}
+
+// What query_region leaves out. Tracers, bodies of at most TRACER_MASS_FRACTION of the total mass like the
+// particles of setup_disk_with_gap, are only returned with include_tracers. exclude_groups lists COM track
+// ids whose bodies are skipped.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RegionQueryOptions {
+    pub include_tracers: bool,
+    pub exclude_groups: Vec<TrackId>,
+}
+
+// Bodies in a region, with the step count of the state they were taken from
+#[derive(Clone, Serialize)]
+pub struct RegionQuery {
+    pub step_count: u64,
+    pub body_ids: Vec<u32>,
+}
+
+// States are emitted at most once per physics tick
+fn validate_emit_rate(rate: f64) -> Result<(), String> {
+    if !rate.is_finite() || rate <= 0.0 || rate > PHYSICS_TICK_RATE {
+        return Err(format!("rate must be above 0 and at most {} per second", PHYSICS_TICK_RATE));
+    }
+    Ok(())
+}
+
+// The idle ticker sleeps 1 / rate between ticks
+fn validate_idle_tick_rate(rate: f64) -> Result<(), String> {
+    if !rate.is_finite() || rate <= 0.0 || rate > MAX_IDLE_TICK_RATE {
+        return Err(format!("rate must be above 0 and at most {} ticks per second", MAX_IDLE_TICK_RATE));
+    }
+    Ok(())
+}
+
+// A zero mass turns force / mass into infinities that spread to every body through the force loop, so
+// edits are checked before anything is written. None means the value is not being set.
+fn validate_body_values(mass: Option<f64>, radius: Option<f64>, motion: &[Option<f64>]) -> Result<(), String> {
+    if mass.is_some_and(|m| !m.is_finite() || m <= 0.0) {
+        return Err(String::from("mass must be a positive number"));
+    }
+    if radius.is_some_and(|r| !r.is_finite() || r <= 0.0) {
+        return Err(String::from("radius must be a positive number"));
+    }
+    if motion.iter().flatten().any(|v| !v.is_finite()) {
+        return Err(String::from("position and velocity must be finite"));
+    }
+    Ok(())
+}
+
+// Small seeded generator (SplitMix64) so generated scenes are reproducible from their seed
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+    
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+const MAX_PLUMMER_BODIES: u32 = 2_000;
+const MAX_DISK_BODIES: u32 = 2_000;
+
+// Euler is the old semi-implicit Euler step, kept for comparing runs with scenes tuned for it. Each step
+// costs one O(n^2) force evaluation with Euler, two with Verlet and four with Rk4.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    Euler,
+    #[default]
+    Verlet,
+    Rk4,
+}
+
+impl Integrator {
+    // The names set_integration_method takes, the same ones the state is serialized with
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "euler" => Ok(Integrator::Euler),
+            "verlet" => Ok(Integrator::Verlet),
+            "rk4" => Ok(Integrator::Rk4),
+            _ => Err(format!("unknown integration method {}, expected euler, verlet or rk4", name)),
+        }
+    }
+}
+
+// What happens to overlapping bodies: Bounce resolves them with an impulse, Merge turns them into one body
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionMode {
+    #[default]
+    Bounce,
+    Merge,
+}
+
+// Groups of commands that change the simulation, read-only mode blocks all but the allowed ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandCategory {
+    // Pausing, resuming and fast-forwarding
+    Playback,
+    TimeControl,
+    // Colors, watches, trails and other things that only change what is shown
+    View,
+    BodyEdit,
+    Config,
+    // Resets, presets and loaded scenarios
+    Scenario,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReadOnlyMode {
+    pub allow: Vec<CommandCategory>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReadOnlyError {
+    pub error: &'static str,
+    pub category: CommandCategory,
+}
+
+// Errors returned by commands. Plain failures serialize as a message string like before, a blocked
+// command as {"error": "read_only", "category": ...} so the UI can tell them apart.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum CommandError {
+    Message(String),
+    ReadOnly(ReadOnlyError),
+}
+
+impl CommandError {
+    pub fn message(message: &str) -> Self {
+        CommandError::Message(String::from(message))
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Message(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::message(message)
+    }
+}
+
+fn validate_orbit_constraint(target_sma: f64, strength: f64) -> Result<(), String> {
+    if !target_sma.is_finite() || target_sma <= 0.0 {
+        return Err(String::from("target_sma must be a positive number"));
+    }
+    if !strength.is_finite() || strength < 0.0 {
+        return Err(String::from("strength must be a non-negative number"));
+    }
+    Ok(())
+}
+
+// Ids are only ever handed out above the current maximum, so they stay unique whatever order bodies are added in
+pub fn next_body_id(bodies: &[Body]) -> u32 {
+    bodies.iter().map(|b| b.id).max().unwrap_or(0) + 1
+}
+
+// Annotated checkpoint of a tutorial scenario. The camera and selection hints are for the frontend,
+// the backend only pauses when pause is set.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TutorialStep {
+    pub time: f64,
+    pub message: String,
+    #[serde(default)]
+    pub focus_body_id: Option<u32>,
+    #[serde(default)]
+    pub selected_body_ids: Vec<u32>,
+    #[serde(default)]
+    pub pause: bool,
+}
+
+// The tutorial section of a scenario file, steps in time order. next_step is the first one not reached yet.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tutorial {
+    pub title: String,
+    pub steps: Vec<TutorialStep>,
+    #[serde(default)]
+    pub next_step: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LoadReport {
+    pub warnings: Vec<String>,
+}
+
+// Physical constants of a scene. They are saved and loaded as one block, so a scenario file always
+// brings its own matching set.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Constants {
+    pub gravity_constant: f64,
+    // Plummer softening length of the pair forces
+    #[serde(default = "default_softening")]
+    pub softening: f64,
+}
+
+impl Constants {
+    pub fn new(gravity_constant: f64) -> Self {
+        Constants { gravity_constant, softening: DEFAULT_SOFTENING }
+    }
+    
+    // A gravity constant of 0 is allowed, it switches gravity off like the collision tutorial does
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.gravity_constant.is_finite() || self.gravity_constant < 0.0 {
+            return Err(String::from("gravity constant must be a finite number of at least 0"));
+        }
+        if !self.softening.is_finite() || self.softening < 0.0 {
+            return Err(String::from("softening must be a non-negative number"));
+        }
+        Ok(())
+    }
+    
+    // Differences from the defaults big enough that scenes behave noticeably unlike the built-in ones
+    #[must_use]
+    pub fn warnings(&self) -> Vec<String> {
+        let default = Constants::default();
+        let mut warnings = Vec::new();
+        let g_ratio = self.gravity_constant / default.gravity_constant;
+        if self.gravity_constant == 0.0 {
+            warnings.push(String::from("gravity is switched off, the gravity constant is 0"));
+        } else if !(1.0 / CONSTANT_WARNING_FACTOR..=CONSTANT_WARNING_FACTOR).contains(&g_ratio) {
+            warnings.push(format!("the gravity constant is {} times the default of {}", g_ratio, default.gravity_constant));
+        }
+        if self.softening > default.softening * CONSTANT_WARNING_FACTOR {
+            warnings.push(format!("the softening length of {} weakens gravity well beyond bodies' surfaces", self.softening));
+        }
+        warnings
+    }
+}
+
+impl Default for Constants {
+    fn default() -> Self {
+        Constants::new(DEFAULT_GRAVITY_CONSTANT)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimulationState {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    pub bodies: Vec<Body>,
+    // Lowest id not handed out yet, so removed bodies' ids are never reused. Files without it continue
+    // above their highest id.
+    #[serde(default)]
+    next_body_id: u32,
+    pub time_step: f64,
+    pub time_multiplier: f64,
+    pub constants: Constants,
+    #[serde(default = "default_pin_mass_ratio")]
+    pub pin_mass_ratio: f64,
+    pub is_running: bool,
+    pub elapsed_time: f64,
+    #[serde(default)]
+    pub integrator: Integrator,
+    #[serde(default)]
+    pub collision_mode: CollisionMode,
+    #[serde(default)]
+    pub collision_sound_events: Vec<CollisionSoundHint>,
+    #[serde(default)]
+    pub config_locked: bool,
+    // Set while presenting, mutating commands outside the allowed categories fail
+    #[serde(default)]
+    pub readonly: Option<ReadOnlyMode>,
+    #[serde(default)]
+    pub radial_drag: Option<RadialDrag>,
+    // Extra drag per body id, on top of the radial profile
+    #[serde(default)]
+    pub drag_coefficients: HashMap<u32, f64>,
+    // Toy non-gravitational drift per body id: rate of change of the semi-major axis around its parent
+    #[serde(default)]
+    pub radial_drift_rates: HashMap<u32, f64>,
+    // Bodies in fluid_body_ids are torn into a stream once roche_limit / distance to a heavier body reaches
+    // this factor, None turns tidal disruption off
+    #[serde(default)]
+    pub disruption_roche_excess: Option<f64>,
+    #[serde(default)]
+    pub fluid_body_ids: HashSet<u32>,
+    // Finite burns per body id, removed once they run out
+    #[serde(default)]
+    pub burns: HashMap<u32, Burn>,
+    #[serde(default)]
+    pub orbit_constraints: Vec<OrbitConstraint>,
+    #[serde(default)]
+    pub ring_forces: Vec<RingForce>,
+    // Every merge in this scene, oldest first
+    #[serde(default)]
+    pub merger_tree: Vec<MergerEvent>,
+    // Rate per unit time at which spins relax toward the orbital angular velocity around the parent
+    #[serde(default)]
+    pub tidal_locking_rate: Option<f64>,
+    // Target Courant number, the time step is retuned to it every COURANT_RECHECK_INTERVAL steps
+    #[serde(default)]
+    pub auto_courant: Option<f64>,
+    // Opposite corners of a rectangle, stepping pauses as soon as a body's center leaves it
+    #[serde(default)]
+    pub auto_pause_region: Option<(Vec2, Vec2)>,
+    // Body that left auto_pause_region and paused the simulation
+    #[serde(default)]
+    pub auto_pause_body: Option<u32>,
+    #[serde(default)]
+    pub tutorial: Option<Tutorial>,
+    #[serde(skip)]
+    follow: Option<FollowTarget>,
+    #[serde(skip)]
+    watches: Vec<Watch>,
+    #[serde(skip)]
+    next_watch_id: WatchId,
+    #[serde(skip)]
+    pub pending_events: Vec<EngineEvent>,
+    // Set on published states: how many events are sent right after this one, see Publication
+    #[serde(default, skip_deserializing)]
+    pub event_count: usize,
+    #[serde(skip)]
+    last_step_collisions: Vec<Contact>,
+    #[serde(skip)]
+    trails: HashMap<u32, VecDeque<TrailPoint>>,
+    #[serde(default = "default_trail_max_points")]
+    pub trail_max_points: usize,
+    // Trails get a point every this many steps
+    #[serde(default = "default_trail_interval")]
+    pub trail_interval: u32,
+    // Bodies added with add_body get the next golden angle color instead of the one they were given
+    #[serde(default)]
+    pub use_golden_angle_colors: bool,
+    // simulation-tick events per second from the physics thread, at most one per step
+    #[serde(default = "default_emit_rate")]
+    pub emit_rate_hz: f64,
+    // Ticks per second of the idle ticker, see start_idle_ticker
+    #[serde(default = "default_idle_tick_rate")]
+    pub idle_tick_rate: f64,
+    // Set when the trail spill file could not be written, no trail points are recorded until spilling is re-enabled
+    #[serde(default)]
+    pub trail_recording_paused: bool,
+    #[serde(skip)]
+    trail_spill: Option<Arc<TrailSpill>>,
+    #[serde(skip)]
+    barycenter_trail: VecDeque<TrailPoint>,
+    #[serde(skip)]
+    com_tracks: Vec<ComTrack>,
+    #[serde(skip)]
+    next_track_id: TrackId,
+    #[serde(default)]
+    pub step_count: u64,
+    // Non-finite values that were zeroed in this copy of the state before it was sent to the frontend
+    #[serde(default)]
+    pub corrupt_fields: Vec<CorruptField>,
+    #[serde(skip)]
+    corruption_reported: bool,
+}
+
+fn default_trail_max_points() -> usize {
+    DEFAULT_TRAIL_MAX_POINTS
+}
+
+fn default_trail_interval() -> u32 {
+    1
+}
+
+fn default_softening() -> f64 {
+    DEFAULT_SOFTENING
+}
+
+fn default_pin_mass_ratio() -> f64 {
+    DEFAULT_PIN_MASS_RATIO
+}
+
+// Files saved before the version field existed have the same layout as version 1
+fn default_schema_version() -> u32 {
+    1
+}
+
+fn default_idle_tick_rate() -> f64 {
+    DEFAULT_IDLE_TICK_RATE
+}
+
+fn default_emit_rate() -> f64 {
+    DEFAULT_EMIT_RATE
+}
+
+impl Default for SimulationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulationState {
+    // Alla måste börja med en vel relativt till annat obj. ex. solen för planeter / planet för månar
+    pub fn new() -> Self {
+        Self::with_phases(&PresetPhases::default())
+    }
+    
+    // The default system with chosen starting angles, bodies without one keep their usual place.
+    // Call PresetPhases::validate first.
+    pub fn with_phases(phases: &PresetPhases) -> Self {
+        let g = DEFAULT_GRAVITY_CONSTANT;
+        
+        let mut bodies = Vec::new();
+        
+        // solen
+        bodies.push(Body::new(1, 8.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 25.0, "#ffcc00"));
+        
+        let sun_mass = 8.0e3;
+        
+        let planet_data = [
+            (1.0e3, 120.0, 10.0, "#ff9999"), //planet 1
+            (1.5e3, 200.0, 12.0, "#3366ff"), //planet 2
+            (3.0e3, 350.0, 18.0, "#ff6600"), //planet 3
+            (2.0e3, 450.0, 15.0, "#33ccff"), //planet 4
+        ];
+        
+        for (i, (mass, distance, radius, color)) in planet_data.iter().enumerate() {
+            let orbital_speed = f64::sqrt(g * sun_mass / distance);
+            
+            let angle = phases.planet_angle(i, 0.0)
+                .unwrap_or(std::f64::consts::PI * 2.0 * (i as f64) / planet_data.len() as f64);
+            
+            let pos_x = angle.cos() * distance;
+            let pos_y = angle.sin() * distance;
+            
+            let vel_x = -angle.sin() * orbital_speed;
+            let vel_y = angle.cos() * orbital_speed;
+            
+            bodies.push(Body::new(next_body_id(&bodies), *mass, Vec2::new(pos_x, pos_y), Vec2::new(vel_x, vel_y), *radius, color));
+        }
+        
+        // Skapa månar för planet 3
+        let planet_index = 2;
+        
+        let planet_mass: f64;
+        let planet_pos_x: f64;
+        let planet_pos_y: f64; 
+        let planet_vel_x: f64;
+        let planet_vel_y: f64;
+        
+        {
+            let planet = &bodies[planet_index];
+            planet_mass = planet.mass;
+            planet_pos_x = planet.position.x;
+            planet_pos_y = planet.position.y;
+            planet_vel_x = planet.velocity.x;
+            planet_vel_y = planet.velocity.y;
+        }
+        
+        let moon_data = [
+            (100.0, 35.0, 4.0, "#cccccc"), //Bigger moon
+            (50.0, 25.0, 3.0, "#aaaaaa"), //Smaller moon
+        ];
+        
+        for (i, (mass, distance, radius, color)) in moon_data.iter().enumerate() {
+            let orbital_speed = f64::sqrt(g * planet_mass / distance);
+            
+            let angle = phases.moon_angle(i, planet_pos_y.atan2(planet_pos_x))
+                .unwrap_or(std::f64::consts::PI * (i as f64) / moon_data.len() as f64);
+            
+            let pos_x = planet_pos_x + angle.cos() * distance;
+            let pos_y = planet_pos_y + angle.sin() * distance;
+            
+            let vel_x = planet_vel_x - angle.sin() * orbital_speed;
+            let vel_y = planet_vel_y + angle.cos() * orbital_speed;
+            
+            bodies.push(Body::new(next_body_id(&bodies), *mass, Vec2::new(pos_x, pos_y), Vec2::new(vel_x, vel_y), *radius, color));
+        }
+        
+        Self::from_bodies(bodies, g)
+    }
+    
+    // Plummer sphere sampled in 3D (Aarseth, Henon & Wielen 1974) and projected onto the plane. The
+    // projection changes both energies, so velocities are rescaled to make 2K / |U| = 1 in this 2D force law.
+    pub fn plummer_sphere(n: u32, total_mass: f64, scale_radius: f64, seed: u64, gravity_constant: f64) -> Result<Self, String> {
+        if !(2..=MAX_PLUMMER_BODIES).contains(&n) {
+            return Err(format!("n must be between 2 and {}", MAX_PLUMMER_BODIES));
+        }
+        if !total_mass.is_finite() || total_mass <= 0.0 {
+            return Err(String::from("total_mass must be a positive number"));
+        }
+        if !scale_radius.is_finite() || scale_radius <= 0.0 {
+            return Err(String::from("scale_radius must be a positive number"));
+        }
+        
+        let mut rng = SplitMix64::new(seed);
+        let mass = total_mass / n as f64;
+        let velocity_scale = (gravity_constant * total_mass / scale_radius).sqrt();
+        
+        let mut bodies = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            // Enclosed mass fraction, the outermost 0.1% is cut off to avoid huge radii
+            let m = (rng.next_f64() * 0.999).max(1e-12);
+            let r = scale_radius / (m.powf(-2.0 / 3.0) - 1.0).sqrt();
+            
+            // Speed in units of the local escape speed, by rejection from q^2 (1 - q^2)^(7/2)
+            let q = loop {
+                let q = rng.next_f64();
+                if 0.1 * rng.next_f64() < q * q * (1.0 - q * q).powf(3.5) {
+                    break q;
+                }
+            };
+            let escape_speed = 2.0_f64.sqrt() * velocity_scale * (1.0 + r * r / (scale_radius * scale_radius)).powf(-0.25);
+            
+            // Only the x and y parts of an isotropic 3D direction are kept
+            let mut planar_direction = || {
+                let z = 2.0 * rng.next_f64() - 1.0;
+                let phi = 2.0 * std::f64::consts::PI * rng.next_f64();
+                let s = (1.0 - z * z).sqrt();
+                Vec2::new(s * phi.cos(), s * phi.sin())
+            };
+            let p = planar_direction();
+            let v = planar_direction();
+            
+            let (position, velocity) = (Vec2::new(p.x * r, p.y * r), Vec2::new(v.x * q * escape_speed, v.y * q * escape_speed));
+            bodies.push(Body::new(next_body_id(&bodies), mass, position, velocity, scale_radius * 0.01, "#ffffff"));
+        }
+        
+        let mut sim = SimulationState::from_bodies(bodies, gravity_constant);
+        
+        // Put the center of mass at rest in the origin
+        let com = sim.center_of_mass();
+        let (px, py) = sim.bodies.iter().fold((0.0, 0.0), |(x, y), b| (x + b.mass * b.velocity.x, y + b.mass * b.velocity.y));
+        for body in &mut sim.bodies {
+            body.position -= com;
+            body.velocity -= Vec2::new(px, py) / total_mass;
+        }
+        
+        let kinetic = sim.kinetic_energy();
+        let potential = sim.potential_energy();
+        if kinetic > 0.0 && potential < 0.0 {
+            let factor = (-potential / (2.0 * kinetic)).sqrt();
+            for body in &mut sim.bodies {
+                body.velocity = body.velocity * factor;
+            }
+        }
+        sim.assign_distinct_colors(false);
+        Ok(sim)
+    }
+    
+    // Central body with the disk's bodies on prograde circular orbits, spread evenly over the area outside
+    // the gap. The disk's own gravity is not included in the orbital speeds.
+    pub fn disk_with_gap(disk: &DiskWithGap, gravity_constant: f64) -> Result<Self, String> {
+        let DiskWithGap { center_mass, inner_r, outer_r, gap_center, gap_width, count, seed } = *disk;
+        if !(1..=MAX_DISK_BODIES).contains(&count) {
+            return Err(format!("count must be between 1 and {}", MAX_DISK_BODIES));
+        }
+        if !center_mass.is_finite() || center_mass <= 0.0 {
+            return Err(String::from("center_mass must be a positive number"));
+        }
+        if ![inner_r, outer_r, gap_center, gap_width].iter().all(|v| v.is_finite()) || inner_r <= 0.0 || outer_r <= inner_r {
+            return Err(String::from("the radii must satisfy 0 < inner_r < outer_r"));
+        }
+        let (gap_inner, gap_outer) = (gap_center - gap_width / 2.0, gap_center + gap_width / 2.0);
+        if gap_width <= 0.0 || gap_inner <= inner_r || gap_outer >= outer_r {
+            return Err(String::from("the gap must have a positive width and lie inside the disk"));
+        }
+        
+        let mut bodies = vec![Body::new(1, center_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), inner_r * 0.2, "#ffcc00")];
+        
+        let mut rng = SplitMix64::new(seed);
+        let (inner_squared, outer_squared) = (inner_r * inner_r, outer_r * outer_r);
+        for _ in 0..count {
+            // Uniform in area, by rejection outside the gap
+            let r = loop {
+                let r = (inner_squared + rng.next_f64() * (outer_squared - inner_squared)).sqrt();
+                if r < gap_inner || r > gap_outer {
+                    break r;
+                }
+            };
+            let angle = 2.0 * std::f64::consts::PI * rng.next_f64();
+            let speed = (gravity_constant * center_mass / r).sqrt();
+            
+            let (position, velocity) = (Vec2::new(r * angle.cos(), r * angle.sin()), Vec2::new(-speed * angle.sin(), speed * angle.cos()));
+            bodies.push(Body::new(next_body_id(&bodies), center_mass * 1e-6, position, velocity, (outer_r - inner_r) * 0.002, "#ffffff"));
+        }
+        
+        let mut sim = SimulationState::from_bodies(bodies, gravity_constant);
+        sim.assign_distinct_colors(true);
+        Ok(sim)
+    }
+    
+    // Scene with the given bodies and default settings, paused at t = 0
+    pub fn from_bodies(bodies: Vec<Body>, gravity_constant: f64) -> Self {
+        Self {
+            schema_version: SCENARIO_SCHEMA_VERSION,
+            bodies,
+            next_body_id: 0,
+            time_step: 0.01,
+            time_multiplier: 1.0,
+            constants: Constants::new(gravity_constant),
+            pin_mass_ratio: DEFAULT_PIN_MASS_RATIO,
+            is_running: false,
+            elapsed_time: 0.0,
+            integrator: Integrator::default(),
+            collision_mode: CollisionMode::default(),
+            collision_sound_events: Vec::new(),
+            config_locked: false,
+            readonly: None,
+            radial_drag: None,
+            drag_coefficients: HashMap::new(),
+            radial_drift_rates: HashMap::new(),
+            disruption_roche_excess: None,
+            fluid_body_ids: HashSet::new(),
+            burns: HashMap::new(),
+            orbit_constraints: Vec::new(),
+            ring_forces: Vec::new(),
+            merger_tree: Vec::new(),
+            tidal_locking_rate: None,
+            auto_courant: None,
+            auto_pause_region: None,
+            auto_pause_body: None,
+            tutorial: None,
+            follow: None,
+            watches: Vec::new(),
+            next_watch_id: 1,
+            pending_events: Vec::new(),
+            event_count: 0,
+            last_step_collisions: Vec::new(),
+            trails: HashMap::new(),
+            trail_max_points: DEFAULT_TRAIL_MAX_POINTS,
+            trail_interval: 1,
+            use_golden_angle_colors: false,
+            emit_rate_hz: DEFAULT_EMIT_RATE,
+            idle_tick_rate: DEFAULT_IDLE_TICK_RATE,
+            trail_recording_paused: false,
+            trail_spill: None,
+            barycenter_trail: VecDeque::new(),
+            com_tracks: Vec::new(),
+            next_track_id: 1,
+            step_count: 0,
+            corrupt_fields: Vec::new(),
+            corruption_reported: false,
+        }
+    }
+    
+    pub fn step(&mut self) {
+        if !self.is_running {
+            return;
+        }
+        
+        self.last_step_collisions.clear();
+        self.collision_sound_events.clear();
+        
+        let effective_time_step = self.effective_time_step();
+        
+        let recording_trails = self.trail_max_points > 0 && !self.trail_recording_paused
+            && (self.step_count + 1).is_multiple_of(u64::from(self.trail_interval.max(1)));
+        // Interpolating the last step only fills in the arc when every step gets a point
+        let interpolating = recording_trails && self.trail_interval <= 1;
+        let motion_before: Vec<(u32, Vec2, Vec2)> = if interpolating {
+            self.bodies.iter().map(|b| (b.id, b.position, b.velocity)).collect()
+        } else {
+            Vec::new()
+        };
+        
+        self.integrate(effective_time_step);
+        
+        self.apply_tidal_locking(effective_time_step);
+        for body in self.bodies.iter_mut() {
+            body.orientation = (body.orientation + body.spin * effective_time_step).rem_euclid(2.0 * std::f64::consts::PI);
+        }
+        
+        self.apply_drag(effective_time_step);
+        self.apply_burns(effective_time_step);
+        self.apply_orbit_constraints(effective_time_step);
+        self.apply_radial_drift(effective_time_step);
+        
+        // Merges and disruptions below can change the bodies, so the motion is matched up by id
+        let motion_after: Vec<(u32, Vec2, Vec2)> = if interpolating {
+            self.bodies.iter().map(|b| (b.id, b.position, b.velocity)).collect()
+        } else {
+            Vec::new()
+        };
+        
+        match self.collision_mode {
+            CollisionMode::Bounce => self.handle_collisions(),
+            CollisionMode::Merge => self.merge_overlapping(),
+        }
+        self.apply_tidal_disruption();
+        self.advance_follow_anchor(effective_time_step);
+        
+        self.elapsed_time += effective_time_step;
+        self.step_count += 1;
+        
+        if recording_trails {
+            self.record_trails(&motion_before, &motion_after, effective_time_step);
+            self.check_trail_spill();
+        }
+        
+        self.evaluate_watches();
+        
+        if let Some(courant) = self.auto_courant {
+            if self.step_count.is_multiple_of(COURANT_RECHECK_INTERVAL) {
+                if let Some(dt) = self.courant_time_step(courant) {
+                    self.time_step = dt;
+                }
+            }
+        }
+        
+        if let Some(id) = self.body_outside_pause_region() {
+            self.auto_pause_body = Some(id);
+            self.is_running = false;
+        }
+        self.check_tutorial_checkpoints();
+        
+        // A NaN or infinity spreads to every body through the force loop, so stop right away
+        if self.bodies.iter().any(|b| !b.non_finite_fields().is_empty()) {
+            self.is_running = false;
+        }
+    }
+    
+    // Queues an event for every checkpoint reached by this step and pauses if any of them asks to
+    fn check_tutorial_checkpoints(&mut self) {
+        let Some(tutorial) = self.tutorial.as_mut() else { return };
+        while let Some(step) = tutorial.steps.get(tutorial.next_step).filter(|s| s.time <= self.elapsed_time) {
+            if step.pause {
+                self.is_running = false;
+            }
+            self.pending_events.push(EngineEvent::TutorialCheckpoint(TutorialCheckpoint {
+                index: tutorial.next_step,
+                elapsed_time: self.elapsed_time,
+                step: step.clone(),
+            }));
+            tutorial.next_step += 1;
+        }
+    }
+    
+    // Pretty-printed scenario JSON. Non-finite values have no JSON form, so a corrupt state is refused.
+    pub fn to_scenario_json(&self) -> Result<String, String> {
+        if let Some(field) = self.scan_non_finite().first() {
+            return Err(format!("body {} has a non-finite {}, the state can't be saved", field.body_id, field.field));
+        }
+        serde_json::to_string_pretty(self).map(|json| json + "\n").map_err(|e| e.to_string())
+    }
+    
+    // Reads a saved scenario, migrating older schema versions. Call repair_duplicate_ids and
+    // repair_tutorial on the result.
+    pub fn from_scenario_json(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("invalid scenario file: {}", e))?;
+        let mut loaded: SimulationState = serde_json::from_value(migrate_scenario(value)?)
+            .map_err(|e| format!("invalid scenario file: {}", e))?;
+        
+        for body in &loaded.bodies {
+            if let Some(field) = body.non_finite_fields().first() {
+                return Err(format!("body {} has a non-finite {}", body.id, field));
+            }
+            if body.mass <= 0.0 {
+                return Err(format!("body {} has a mass of {}, masses must be positive", body.id, body.mass));
+            }
+            if body.radius <= 0.0 {
+                return Err(format!("body {} has a radius of {}, radii must be positive", body.id, body.radius));
+            }
+        }
+        for (name, value) in [("time_step", loaded.time_step), ("time_multiplier", loaded.time_multiplier),
+                              ("elapsed_time", loaded.elapsed_time)] {
+            if !value.is_finite() {
+                return Err(format!("{} is not a finite number", name));
+            }
+        }
+        loaded.set_time_step(loaded.time_step).map_err(|e| format!("invalid time_step: {}", e))?;
+        loaded.set_time_multiplier(loaded.time_multiplier).map_err(|e| format!("invalid time_multiplier: {}", e))?;
+        for (body_id, burn) in &loaded.burns {
+            if burn.remaining_steps == 0 {
+                return Err(format!("the burn on body {} has no steps left", body_id));
+            }
+            if !burn.acceleration.x.is_finite() || !burn.acceleration.y.is_finite() {
+                return Err(format!("the burn on body {} has a non-finite acceleration", body_id));
+            }
+        }
+        validate_idle_tick_rate(loaded.idle_tick_rate).map_err(|e| format!("invalid idle_tick_rate: {}", e))?;
+        validate_emit_rate(loaded.emit_rate_hz).map_err(|e| format!("invalid emit_rate_hz: {}", e))?;
+        loaded.constants.validate().map_err(|e| format!("invalid scenario constants: {}", e))?;
+        Ok(loaded)
+    }
+    
+    // Drops checkpoints with a non-finite time and sorts the rest, a loaded file may list them in any order
+    fn repair_tutorial(&mut self) -> Vec<String> {
+        let Some(tutorial) = self.tutorial.as_mut() else { return Vec::new() };
+        let mut warnings = Vec::new();
+        
+        let before = tutorial.steps.len();
+        tutorial.steps.retain(|s| s.time.is_finite());
+        if tutorial.steps.len() != before {
+            warnings.push(format!("{} tutorial steps without a finite time were dropped", before - tutorial.steps.len()));
+        }
+        if !tutorial.steps.is_sorted_by(|a, b| a.time <= b.time) {
+            tutorial.steps.sort_by(|a, b| a.time.total_cmp(&b.time));
+            warnings.push(String::from("tutorial steps were sorted by time"));
+        }
+        tutorial.next_step = tutorial.next_step.min(tutorial.steps.len());
+        warnings
+    }
+    
+    #[must_use]
+    pub fn next_tutorial_step(&self) -> Option<&TutorialStep> {
+        let tutorial = self.tutorial.as_ref()?;
+        tutorial.steps.get(tutorial.next_step)
+    }
+    
+    fn body_outside_pause_region(&self) -> Option<u32> {
+        let (corner1, corner2) = self.auto_pause_region?;
+        let (min_x, max_x) = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let (min_y, max_y) = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+        
+        self.bodies.iter()
+            .find(|b| !(min_x..=max_x).contains(&b.position.x) || !(min_y..=max_y).contains(&b.position.y))
+            .map(|b| b.id)
+    }
+    
+    fn scan_non_finite(&self) -> Vec<CorruptField> {
+        self.bodies.iter()
+            .flat_map(|b| b.non_finite_fields().into_iter().map(move |field| CorruptField {
+                body_id: b.id,
+                field: String::from(field),
+            }))
+            .collect()
+    }
+    
+    // Snapshot for the frontend with the queued events, stamped with the current step
+    pub fn publish(&mut self) -> Publication {
+        let mut state = self.ipc_snapshot();
+        state.pending_events.clear();
+        let step = self.step_count;
+        let events: Vec<StampedEvent> = self.pending_events.drain(..)
+            .map(|event| StampedEvent { step, event })
+            .collect();
+        state.event_count = events.len();
+        Publication { state, events }
+    }
+    
+    // Publication of the queued events, None when nothing is queued
+    pub fn take_events(&mut self) -> Option<Publication> {
+        (!self.pending_events.is_empty()).then(|| self.publish())
+    }
+    
+    // Copy of the state that is safe to serialize: non-finite values become 0 and are listed in
+    // corrupt_fields. The first time corruption shows up a state-corruption event is queued.
+    pub fn ipc_snapshot(&mut self) -> SimulationState {
+        let corrupt = self.scan_non_finite();
+        if corrupt.is_empty() {
+            self.corruption_reported = false;
+            return self.clone();
+        }
+        
+        if !self.corruption_reported {
+            self.corruption_reported = true;
+            self.pending_events.push(EngineEvent::StateCorruption(StateCorruption {
+                elapsed_time: self.elapsed_time,
+                fields: corrupt.clone(),
+            }));
+        }
+        
+        let mut snapshot = self.clone();
+        snapshot.pending_events.clear();
+        for body in &mut snapshot.bodies {
+            for value in [&mut body.mass, &mut body.radius, &mut body.position.x, &mut body.position.y,
+                          &mut body.velocity.x, &mut body.velocity.y, &mut body.spin, &mut body.orientation] {
+                if !value.is_finite() {
+                    *value = 0.0;
+                }
+            }
+        }
+        snapshot.corrupt_fields = corrupt;
+        snapshot
+    }
+    
+    // Damps velocities relative to the COM velocity, so drag doesn't push the system as a whole
+    fn apply_drag(&mut self, dt: f64) {
+        if self.radial_drag.is_none() && self.drag_coefficients.is_empty() {
+            return;
+        }
+        
+        let com = self.center_of_mass();
+        let com_velocity = self.center_of_mass_velocity();
+        
+        for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            let radial = self.radial_drag.map_or(0.0, |drag| {
+                let r = body.position.distance(&com) / drag.scale_radius;
+                drag.central_drag / (1.0 + r * r)
+            });
+            let coefficient = radial + self.drag_coefficients.get(&body.id).copied().unwrap_or(0.0);
+            let factor = (-coefficient * dt).exp();
+            
+            body.velocity = com_velocity + (body.velocity - com_velocity) * factor;
+        }
+    }
+    
+    // Fluid bodies past the disruption threshold, each with the heavier body that tears it apart most
+    fn disrupted_bodies(&self) -> Vec<(usize, usize)> {
+        let Some(threshold) = self.disruption_roche_excess else { return Vec::new() };
+        
+        self.bodies.iter()
+            .enumerate()
+            .filter(|(_, b)| self.fluid_body_ids.contains(&b.id) && !b.fixed)
+            .filter_map(|(i, body)| {
+                self.bodies.iter()
+                    .enumerate()
+                    .filter(|(_, primary)| primary.mass > body.mass)
+                    .map(|(j, primary)| {
+                        let roche_limit = ROCHE_COEFFICIENT * body.radius * (primary.mass / body.mass).cbrt();
+                        (j, roche_limit / body.position.distance(&primary.position).max(1e-9))
+                    })
+                    .filter(|(_, excess)| *excess >= threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(j, _)| (i, j))
+            })
+            .collect()
+    }
+    
+    // Replaces each disrupted body with DISRUPTION_FRAGMENTS equal fragments in a line one body diameter
+    // long along its motion relative to the primary. Along the line velocities grow by sqrt(2*G*M / r^3)
+    // per unit length, the rate tidal stretching pulls neighbours apart at, leading fragments moving
+    // faster. The line is centered on the body and the offsets cancel, so mass and momentum are kept.
+    fn apply_tidal_disruption(&mut self) {
+        let disrupted = self.disrupted_bodies();
+        if disrupted.is_empty() {
+            return;
+        }
+        
+        let mut removed = Vec::new();
+        for (index, primary_index) in disrupted {
+            let (body, primary) = (self.bodies[index].clone(), &self.bodies[primary_index]);
+            let offset = body.position - primary.position;
+            let r = offset.length();
+            let relative_velocity = body.velocity - primary.velocity;
+            let direction = if relative_velocity.length_squared() > 0.0 {
+                relative_velocity.normalized()
+            } else {
+                Vec2::new(-offset.y, offset.x).normalized()
+            };
+            let shear_rate = (2.0 * self.constants.gravity_constant * primary.mass / (r * r * r)).sqrt();
+            let primary_id = primary.id;
+            
+            let n = DISRUPTION_FRAGMENTS;
+            let length = 2.0 * body.radius;
+            let spacing = length / (n - 1) as f64;
+            let mut fragment_ids = Vec::with_capacity(n as usize);
+            for k in (0..n).rev() {
+                let along = (k as f64 / (n - 1) as f64 - 0.5) * length;
+                let id = self.next_id();
+                fragment_ids.push(id);
+                let (position, velocity) = (body.position + direction * along, body.velocity + direction * (along * shear_rate));
+                // Small enough that neighbours don't touch
+                let radius = 0.4 * spacing;
+                self.bodies.push(Body {
+                    orientation: body.orientation,
+                    ..Body::new(id, body.mass / n as f64, position, velocity, radius, &body.color)
+                });
+            }
+            
+            self.pending_events.push(EngineEvent::TidalDisruption(TidalDisruption {
+                body_id: body.id,
+                primary_id,
+                fragment_ids: fragment_ids.clone(),
+                elapsed_time: self.elapsed_time,
+            }));
+            removed.push((body.id, fragment_ids[fragment_ids.len() / 2]));
+        }
+        
+        for (id, middle_fragment) in removed {
+            self.bodies.retain(|b| b.id != id);
+            self.fluid_body_ids.remove(&id);
+            self.forget_body(id, Some(middle_fragment));
+        }
+    }
+    
+    pub fn set_body_fluid(&mut self, body_id: u32, fluid: bool) -> Result<(), String> {
+        self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        if fluid {
+            self.fluid_body_ids.insert(body_id);
+        } else {
+            self.fluid_body_ids.remove(&body_id);
+        }
+        Ok(())
+    }
+    
+    // Each burning body gets dv = acceleration * dt per step, so a burn of n steps adds acceleration * n * dt
+    fn apply_burns(&mut self, dt: f64) {
+        if self.burns.is_empty() {
+            return;
+        }
+        for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            if let Some(burn) = self.burns.get(&body.id) {
+                body.velocity += burn.acceleration * dt;
+            }
+        }
+        self.burns.retain(|_, burn| {
+            burn.remaining_steps = burn.remaining_steps.saturating_sub(1);
+            burn.remaining_steps > 0
+        });
+    }
+    
+    // Replaces any burn the body already has, duration_steps 0 stops it. direction doesn't need to be a unit vector.
+    pub fn start_burn(&mut self, body_id: u32, direction: Vec2, acceleration: f64, duration_steps: u32) -> Result<(), String> {
+        self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        if duration_steps == 0 {
+            self.burns.remove(&body_id);
+            return Ok(());
+        }
+        if !acceleration.is_finite() || acceleration < 0.0 {
+            return Err(String::from("acceleration must be a non-negative number"));
+        }
+        let direction = direction.normalized();
+        if !direction.length_squared().is_finite() || direction.length_squared() == 0.0 {
+            return Err(String::from("direction must be a finite, non-zero vector"));
+        }
+        
+        self.burns.insert(body_id, Burn { acceleration: direction * acceleration, remaining_steps: duration_steps });
+        Ok(())
+    }
+    
+    // Per-body drag in [0, 1] per unit time, 0 removes it
+    pub fn set_drag_coefficient(&mut self, body_id: u32, damping: f64) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&damping) {
+            return Err(String::from("damping must be between 0 and 1"));
+        }
+        self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        
+        if damping > 0.0 {
+            self.drag_coefficients.insert(body_id, damping);
+        } else {
+            self.drag_coefficients.remove(&body_id);
+        }
+        Ok(())
+    }
+    
+    // Each constrained body gets dv = -strength * (a - target) * dt along its direction of motion around
+    // the primary. Fixed bodies and bodies on unbound orbits are left alone.
+    fn apply_orbit_constraints(&mut self, dt: f64) {
+        for i in 0..self.orbit_constraints.len() {
+            let constraint = &self.orbit_constraints[i];
+            let (Some(body_index), Some(primary_index)) =
+                (self.body_index(constraint.body_id), self.body_index(constraint.primary_id)) else { continue };
+            let (body, primary) = (&self.bodies[body_index], &self.bodies[primary_index]);
+            if body.fixed {
+                continue;
+            }
+            
+            let orbit = self.relative_orbit(body, primary);
+            let Some(a) = orbit.semi_major_axis() else { continue };
+            // Unit vector perpendicular to the radius, pointing along the orbit's sense of rotation
+            let sense = if orbit.angular_momentum() < 0.0 { -1.0 } else { 1.0 };
+            let tangent = orbit.position.perp().normalized() * sense;
+            let dv = -constraint.strength * (a - constraint.target_sma) * dt;
+            
+            self.bodies[body_index].velocity += tangent * dv;
+        }
+    }
+    
+    // Along-track acceleration that changes the osculating semi-major axis at the configured rate. From
+    // Gauss's equation da/dt = 2 a^2 v a_t / mu, so a_t = rate * mu / (2 a^2 v). Pushing along the
+    // velocity leaves the eccentricity roughly alone when the rate is small.
+    fn apply_radial_drift(&mut self, dt: f64) {
+        if self.radial_drift_rates.is_empty() {
+            return;
+        }
+        
+        let mut kicks = Vec::new();
+        for (index, body) in self.bodies.iter().enumerate() {
+            let Some(rate) = self.radial_drift_rates.get(&body.id).copied() else { continue };
+            let Some(parent) = self.parent_of(index) else { continue };
+            if body.fixed {
+                continue;
+            }
+            
+            let orbit = self.relative_orbit(body, &self.bodies[parent]);
+            let Some(a) = orbit.semi_major_axis() else { continue };
+            let speed = orbit.speed_squared().sqrt();
+            if speed <= 0.0 {
+                continue;
+            }
+            let along_track = rate * orbit.mu / (2.0 * a * a * speed);
+            let dv = along_track * dt / speed;
+            kicks.push((index, orbit.velocity * dv));
+        }
+        
+        for (index, kick) in kicks {
+            self.bodies[index].velocity += kick;
+        }
+    }
+    
+    // Semi-major axis drift in distance units per unit time, negative moves inward, 0 removes it
+    pub fn set_radial_drift_rate(&mut self, body_id: u32, rate: f64) -> Result<(), String> {
+        if !rate.is_finite() {
+            return Err(String::from("rate must be a finite number"));
+        }
+        self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        
+        if rate != 0.0 {
+            self.radial_drift_rates.insert(body_id, rate);
+        } else {
+            self.radial_drift_rates.remove(&body_id);
+        }
+        Ok(())
+    }
+    
+    // Relaxes each spin exponentially toward the body's current orbital rate around its parent
+    fn apply_tidal_locking(&mut self, dt: f64) {
+        let Some(rate) = self.tidal_locking_rate else { return };
+        let factor = 1.0 - (-rate * dt).exp();
+        
+        let targets: Vec<(usize, f64)> = (0..self.bodies.len())
+            .filter(|i| !self.bodies[*i].fixed)
+            .filter_map(|i| {
+                let parent = self.parent_of(i)?;
+                let orbit = self.relative_orbit(&self.bodies[i], &self.bodies[parent]);
+                let r = orbit.radius();
+                let orbital_rate = orbit.angular_momentum() / (r * r);
+                orbital_rate.is_finite().then_some((i, orbital_rate))
+            })
+            .collect();
+        for (i, orbital_rate) in targets {
+            let body = &mut self.bodies[i];
+            body.spin += (orbital_rate - body.spin) * factor;
+        }
+    }
+    
+    // Spin over the instantaneous orbital angular velocity around the primary, 1 when tidally locked.
+    // None when either body is missing or the body is not moving around the primary.
+    #[must_use]
+    pub fn spin_orbit_ratio(&self, body_id: u32, primary_id: u32) -> Option<f64> {
+        if body_id == primary_id {
+            return None;
+        }
+        let body = &self.bodies[self.body_index(body_id)?];
+        let primary = &self.bodies[self.body_index(primary_id)?];
+        let orbit = self.relative_orbit(body, primary);
+        
+        let r = orbit.radius();
+        let orbital_rate = orbit.angular_momentum() / (r * r);
+        if !orbital_rate.is_finite() || orbital_rate == 0.0 {
+            return None;
+        }
+        Some(body.spin / orbital_rate)
+    }
+    
+    pub fn add_orbit_constraint(&mut self, body_id: u32, primary_id: u32, target_sma: f64, strength: f64) -> Result<ConstraintId, String> {
+        if body_id == primary_id {
+            return Err(String::from("a body cannot be constrained around itself"));
+        }
+        for id in [body_id, primary_id] {
+            self.body_index(id).ok_or(format!("no body with id {}", id))?;
+        }
+        validate_orbit_constraint(target_sma, strength)?;
+        
+        let id = self.orbit_constraints.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        self.orbit_constraints.push(OrbitConstraint { id, body_id, primary_id, target_sma, strength });
+        Ok(id)
+    }
+    
+    pub fn update_orbit_constraint(&mut self, id: ConstraintId, target_sma: Option<f64>, strength: Option<f64>) -> Result<(), String> {
+        let constraint = self.orbit_constraints.iter_mut()
+            .find(|c| c.id == id)
+            .ok_or(format!("no orbit constraint with id {}", id))?;
+        let target_sma = target_sma.unwrap_or(constraint.target_sma);
+        let strength = strength.unwrap_or(constraint.strength);
+        validate_orbit_constraint(target_sma, strength)?;
+        
+        constraint.target_sma = target_sma;
+        constraint.strength = strength;
+        Ok(())
+    }
+    
+    pub fn remove_orbit_constraint(&mut self, id: ConstraintId) -> bool {
+        let before = self.orbit_constraints.len();
+        self.orbit_constraints.retain(|c| c.id != id);
+        self.orbit_constraints.len() != before
+    }
+    
+    // Appends this step's positions to the trails. Bodies that turned sharply get interpolated points in
+    // between so the trail follows the arc, and collisions add the contact point to both bodies' trails.
+    // With a trail interval above 1 the motion is empty and collisions on skipped steps leave no point.
+    fn record_trails(&mut self, motion_before: &[(u32, Vec2, Vec2)], motion_after: &[(u32, Vec2, Vec2)], dt: f64) {
+        let step = self.step_count;
+        let mut new_points: Vec<(u32, TrailPoint)> = Vec::new();
+        let present: HashSet<u32> = self.bodies.iter().map(|b| b.id).collect();
+        
+        for (&(id, p0, v0), &(_, p1, v1)) in motion_before.iter().zip(motion_after) {
+            if !present.contains(&id) {
+                continue;
+            }
+            
+            let speeds = (v0.x * v0.x + v0.y * v0.y).sqrt() * (v1.x * v1.x + v1.y * v1.y).sqrt();
+            if speeds > 0.0 {
+                let cos_turn = ((v0.x * v1.x + v0.y * v1.y) / speeds).clamp(-1.0, 1.0);
+                let turn = cos_turn.acos();
+                
+                if turn > TRAIL_CURVATURE_THRESHOLD {
+                    let subdivisions = ((turn / TRAIL_CURVATURE_THRESHOLD).ceil() as usize).min(MAX_TRAIL_SUBDIVISIONS);
+                    for k in 1..subdivisions {
+                        let t = k as f64 / subdivisions as f64;
+                        new_points.push((id, TrailPoint { position: hermite(p0, v0, p1, v1, dt, t), step }));
+                    }
+                }
+            }
+        }
+        
+        for contact in &self.last_step_collisions {
+            for id in [contact.body_ids.0, contact.body_ids.1].into_iter().filter(|id| present.contains(id)) {
+                new_points.push((id, TrailPoint { position: contact.point, step }));
+            }
+        }
+        
+        for body in &self.bodies {
+            new_points.push((body.id, TrailPoint { position: body.position, step }));
+        }
+        
+        // Oldest points are evicted first so extra points stay within the same budget. With a spill file
+        // a full trail hands its older half to the writer thread instead of dropping it.
+        let max_points = self.trail_max_points;
+        let mut spilled: Vec<(u32, TrailPoint)> = Vec::new();
+        for (id, point) in new_points {
+            let trail = self.trails.entry(id).or_default();
+            trail.push_back(point);
+            if trail.len() > max_points {
+                if self.trail_spill.is_some() {
+                    let keep = max_points / 2;
+                    spilled.extend(trail.drain(..trail.len() - keep).map(|p| (id, p)));
+                } else {
+                    while trail.len() > max_points {
+                        trail.pop_front();
+                    }
+                }
+            }
+        }
+        
+        if let Some(spill) = &self.trail_spill {
+            if !spilled.is_empty() {
+                spill.send(spilled);
+            }
+        }
+        
+        // Center-of-mass trails are only kept in memory
+        let barycenter = self.center_of_mass();
+        let track_points: Vec<Option<Vec2>> = self.com_tracks.iter()
+            .map(|track| self.group_center_of_mass(&track.body_ids))
+            .collect();
+        
+        let trails = std::iter::once((&mut self.barycenter_trail, Some(barycenter)))
+            .chain(self.com_tracks.iter_mut().map(|t| &mut t.trail).zip(track_points));
+        for (trail, position) in trails {
+            if let Some(position) = position {
+                trail.push_back(TrailPoint { position, step });
+                while trail.len() > max_points {
+                    trail.pop_front();
+                }
+            }
+        }
+    }
+    
+    #[must_use]
+    fn group_center_of_mass(&self, ids: &[u32]) -> Option<Vec2> {
+        let (mass, x, y) = self.bodies.iter()
+            .filter(|b| ids.contains(&b.id))
+            .fold((0.0, 0.0, 0.0), |(m, x, y), b| (m + b.mass, x + b.mass * b.position.x, y + b.mass * b.position.y));
+        if mass > 0.0 { Some(Vec2::new(x / mass, y / mass)) } else { None }
+    }
+    
+    pub fn add_com_track(&mut self, body_ids: Vec<u32>) -> Result<TrackId, String> {
+        if body_ids.is_empty() {
+            return Err(String::from("a track needs at least one body"));
+        }
+        for id in &body_ids {
+            self.body_index(*id).ok_or(format!("no body with id {}", id))?;
+        }
+        
+        let id = self.next_track_id;
+        self.next_track_id += 1;
+        self.com_tracks.push(ComTrack { id, body_ids, trail: VecDeque::new() });
+        Ok(id)
+    }
+    
+    pub fn remove_com_track(&mut self, id: TrackId) -> bool {
+        let before = self.com_tracks.len();
+        self.com_tracks.retain(|t| t.id != id);
+        self.com_tracks.len() != before
+    }
+    
+    // A point every interval steps and at most max_points per trail, 0 points turns recording off. Trails
+    // that are already longer lose their oldest points.
+    pub fn set_trail_config(&mut self, interval: u32, max_points: usize) -> Result<(), String> {
+        if interval == 0 {
+            return Err(String::from("trail interval must be at least 1 step"));
+        }
+        if max_points > MAX_TRAIL_POINTS {
+            return Err(format!("trails can keep at most {} points", MAX_TRAIL_POINTS));
+        }
+        self.trail_interval = interval;
+        self.trail_max_points = max_points;
+        
+        // Body trails hand what no longer fits to the spill file like record_trails does, the group trails
+        // are only kept in memory
+        let mut spilled: Vec<(u32, TrailPoint)> = Vec::new();
+        for (id, trail) in &mut self.trails {
+            let excess = trail.len().saturating_sub(max_points);
+            let drained = trail.drain(..excess);
+            if self.trail_spill.is_some() {
+                spilled.extend(drained.map(|p| (*id, p)));
+            }
+        }
+        if let Some(spill) = self.trail_spill.as_ref().filter(|_| !spilled.is_empty()) {
+            spill.send(spilled);
+        }
+        
+        let group_trails = std::iter::once(&mut self.barycenter_trail)
+            .chain(self.com_tracks.iter_mut().map(|t| &mut t.trail));
+        for trail in group_trails {
+            let excess = trail.len().saturating_sub(max_points);
+            trail.drain(..excess);
+        }
+        Ok(())
+    }
+    
+    // Some(dir) starts spilling to a new file there, None stops spilling and deletes the current file
+    pub fn set_trail_spill_dir(&mut self, dir: Option<&std::path::Path>) -> std::io::Result<()> {
+        self.trail_spill = match dir {
+            Some(dir) => Some(Arc::new(TrailSpill::create(dir)?)),
+            None => None,
+        };
+        self.trail_recording_paused = false;
+        Ok(())
+    }
+    
+    // A failed spill write pauses recording, otherwise the trails would silently lose points again
+    fn check_trail_spill(&mut self) {
+        let Some(reason) = self.trail_spill.as_ref().and_then(|spill| spill.take_error()) else {
+            return;
+        };
+        self.trail_recording_paused = true;
+        self.pending_events.push(EngineEvent::RecordingPaused(RecordingPaused {
+            elapsed_time: self.elapsed_time,
+            reason,
+        }));
+    }
+    
+    // Spilled points followed by the ones still in memory, oldest first. Bodies are keyed by id, the
+    // barycenter and COM tracks by BARYCENTER_TRAIL_KEY and COM_TRACK_KEY_PREFIX plus the track id.
+    pub fn trail_positions(&self) -> Result<HashMap<String, Vec<Vec2>>, String> {
+        Ok(self.trail_points()?
+            .into_iter()
+            .map(|(key, points)| (key, points.iter().map(|p| p.position).collect()))
+            .collect())
+    }
+    
+    // Same as trail_positions, with the step each point was recorded at
+    pub fn trail_points(&self) -> Result<HashMap<String, Vec<TrailPoint>>, String> {
+        // The spill file is shared with later copies of the state, so for a pinned snapshot it can hold
+        // points recorded after it or still in its in-memory trail. Only older points are taken.
+        let mut points: HashMap<String, Vec<TrailPoint>> = match &self.trail_spill {
+            Some(spill) => spill.read()
+                .map_err(|e| format!("could not read trail spill file: {}", e))?
+                .into_iter()
+                .map(|(id, spilled)| {
+                    let before = self.trails.get(&id)
+                        .and_then(|trail| trail.front())
+                        .map_or(self.step_count + 1, |p| p.step);
+                    (id.to_string(), spilled.into_iter().filter(|p| p.step < before).collect())
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+        
+        for (id, trail) in &self.trails {
+            points.entry(id.to_string()).or_default().extend(trail.iter().copied());
+        }
+        if !self.barycenter_trail.is_empty() {
+            points.insert(String::from(BARYCENTER_TRAIL_KEY), self.barycenter_trail.iter().copied().collect());
+        }
+        for track in &self.com_tracks {
+            points.insert(format!("{}{}", COM_TRACK_KEY_PREFIX, track.id), track.trail.iter().copied().collect());
+        }
+        Ok(points)
+    }
+    
+    // Every trail for export, bodies first in scene order, then the barycenter and COM tracks. Spilled
+    // points stay in the spill file until the export reads them, cut off like in trail_points.
+    #[must_use]
+    pub fn export_trails(&self) -> Vec<ExportTrail> {
+        let mut ids: HashSet<u32> = self.trails.keys().copied().collect();
+        if let Some(spill) = &self.trail_spill {
+            ids.extend(spill.ids());
+        }
+        let mut body_trails: HashMap<u32, ExportTrail> = ids.into_iter()
+            .map(|id| {
+                let points: Vec<TrailPoint> = self.trails.get(&id).map(|t| t.iter().copied().collect()).unwrap_or_default();
+                let before = points.first().map_or(self.step_count + 1, |p| p.step);
+                let spilled = self.trail_spill.as_ref().map(|spill| SpilledTrail { spill: Arc::clone(spill), id, before });
+                (id, ExportTrail { key: id.to_string(), name: id.to_string(), color: None, spilled, points })
+            })
+            .collect();
+        
+        let mut trails: Vec<ExportTrail> = self.bodies.iter()
+            .filter_map(|body| {
+                let trail = body_trails.remove(&body.id)?;
+                Some(ExportTrail { name: format!("body {}", body.id), color: Some(body.color.clone()), ..trail })
+            })
+            .collect();
+        
+        // Trails of bodies that are gone and the group tracks
+        let memory_trail = |key: String, points: &VecDeque<TrailPoint>| {
+            ExportTrail { name: key.clone(), key, color: None, spilled: None, points: points.iter().copied().collect() }
+        };
+        let mut rest: Vec<ExportTrail> = body_trails.into_values().collect();
+        if !self.barycenter_trail.is_empty() {
+            rest.push(memory_trail(String::from(BARYCENTER_TRAIL_KEY), &self.barycenter_trail));
+        }
+        for track in &self.com_tracks {
+            rest.push(memory_trail(format!("{}{}", COM_TRACK_KEY_PREFIX, track.id), &track.trail));
+        }
+        rest.sort_by(|a, b| a.key.cmp(&b.key));
+        trails.extend(rest);
+        trails
+    }
+    
+    // Guard for every command that changes the simulation, see CommandCategory
+    pub fn check_allowed(&self, category: CommandCategory) -> Result<(), CommandError> {
+        match &self.readonly {
+            Some(mode) if !mode.allow.contains(&category) => {
+                Err(CommandError::ReadOnly(ReadOnlyError { error: "read_only", category }))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    // Positive and finite, anything above MAX_TIME_STEP is clamped to it
+    pub fn set_time_step(&mut self, dt: f64) -> Result<(), String> {
+        if !dt.is_finite() || dt <= 0.0 {
+            return Err(String::from("time step must be a positive number"));
+        }
+        self.time_step = dt.min(MAX_TIME_STEP);
+        Ok(())
+    }
+    
+    // Positive, finite and at most MAX_TIME_MULTIPLIER
+    pub fn set_time_multiplier(&mut self, multiplier: f64) -> Result<(), String> {
+        if !multiplier.is_finite() || multiplier <= 0.0 {
+            return Err(String::from("time multiplier must be a positive number"));
+        }
+        if multiplier > MAX_TIME_MULTIPLIER {
+            return Err(format!("time multiplier must be at most {}", MAX_TIME_MULTIPLIER));
+        }
+        self.time_multiplier = multiplier;
+        Ok(())
+    }
+    
+    // The time one step advances by, time_step * time_multiplier up to MAX_TIME_STEP
+    #[must_use]
+    pub fn effective_time_step(&self) -> f64 {
+        (self.time_step * self.time_multiplier).min(MAX_TIME_STEP)
+    }
+    
+    pub fn set_gravity_constant(&mut self, g: f64) -> Result<(), String> {
+        let constants = Constants { gravity_constant: g, ..self.constants };
+        constants.validate()?;
+        self.constants = constants;
+        Ok(())
+    }
+    
+    pub fn set_pin_mass_ratio(&mut self, ratio: f64) -> Result<(), String> {
+        if !ratio.is_finite() || ratio < 1.0 {
+            return Err(String::from("pin mass ratio must be a number of at least 1"));
+        }
+        self.pin_mass_ratio = ratio;
+        Ok(())
+    }
+    
+    // Zero turns softening off
+    pub fn set_softening(&mut self, epsilon: f64) -> Result<(), String> {
+        if !epsilon.is_finite() || epsilon < 0.0 {
+            return Err(String::from("softening must be a non-negative number"));
+        }
+        self.constants.softening = epsilon;
+        Ok(())
+    }
+    
+    pub fn set_constants(&mut self, constants: Constants) -> Result<(), String> {
+        constants.validate()?;
+        self.constants = constants;
+        Ok(())
+    }
+    
+    // Guard for commands that change physics configuration, body-level edits are not affected
+    pub fn ensure_unlocked(&self) -> Result<(), String> {
+        if self.config_locked {
+            Err(String::from("simulation config is locked"))
+        } else {
+            Ok(())
+        }
+    }
+    
+    // Replaces the scene for a reset or load, keeping what belongs to the session rather than the scene
+    fn start_over(&mut self, mut fresh: SimulationState) {
+        self.carry_locked_config(&mut fresh);
+        fresh.readonly = self.readonly.clone();
+        // Spilling stays on, but with a new file. The old one is removed when the old state is dropped.
+        fresh.trail_spill = self.trail_spill.as_ref()
+            .and_then(|spill| TrailSpill::create(spill.dir()).ok())
+            .map(Arc::new);
+        *self = fresh;
+    }
+    
+    // Makes now t = 0 without touching the bodies. Merger, collision sound and tutorial times shift back by the old
+    // elapsed_time, so earlier entries end up negative. Step numbers cannot go below 0, so resetting the
+    // step counter too drops the recorded trails and restarts the spill file, and scars count as made at step 0.
+    pub fn rezero_time(&mut self, reset_step_count: bool) {
+        let offset = self.elapsed_time;
+        self.elapsed_time = 0.0;
+        for event in &mut self.merger_tree {
+            event.elapsed_time -= offset;
+        }
+        for hint in &mut self.collision_sound_events {
+            hint.elapsed_time -= offset;
+        }
+        for step in self.tutorial.iter_mut().flat_map(|t| t.steps.iter_mut()) {
+            step.time -= offset;
+        }
+        
+        let previous_step_count = reset_step_count.then_some(self.step_count);
+        if reset_step_count {
+            self.step_count = 0;
+            for body in &mut self.bodies {
+                for scar in &mut body.scars {
+                    scar.step = 0;
+                }
+            }
+            self.trails.clear();
+            self.barycenter_trail.clear();
+            for track in &mut self.com_tracks {
+                track.trail.clear();
+            }
+            self.trail_spill = self.trail_spill.as_ref()
+                .and_then(|spill| TrailSpill::create(spill.dir()).ok())
+                .map(Arc::new);
+        }
+        
+        self.pending_events.push(EngineEvent::TimeRezeroed(TimeRezeroed {
+            previous_elapsed_time: offset,
+            previous_step_count,
+        }));
+    }
+    
+    // Gives every body after the first with an already used id a new one. References to a duplicated
+    // id keep pointing at its first body, since there is no way to tell which copy they meant.
+    fn repair_duplicate_ids(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut seen = HashSet::new();
+        
+        for index in 0..self.bodies.len() {
+            let id = self.bodies[index].id;
+            if !seen.insert(id) {
+                let new_id = self.next_id();
+                warnings.push(format!("duplicate body id {} was changed to {}", id, new_id));
+                self.bodies[index].id = new_id;
+                self.copy_body_settings(id, new_id);
+            }
+        }
+        warnings
+    }
+    
+    // Settings looked up by each body's own id applied to every body that shared it, so new_id keeps them.
+    // Everything found through body_index only ever saw the first of them and stays with the original id,
+    // a trail both bodies wrote into can't be told apart and starts over.
+    fn copy_body_settings(&mut self, id: u32, new_id: u32) {
+        if let Some(damping) = self.drag_coefficients.get(&id).copied() {
+            self.drag_coefficients.insert(new_id, damping);
+        }
+        if let Some(rate) = self.radial_drift_rates.get(&id).copied() {
+            self.radial_drift_rates.insert(new_id, rate);
+        }
+        if let Some(burn) = self.burns.get(&id).copied() {
+            self.burns.insert(new_id, burn);
+        }
+        if self.fluid_body_ids.contains(&id) {
+            self.fluid_body_ids.insert(new_id);
+        }
+        for track in &mut self.com_tracks {
+            if track.body_ids.contains(&id) && !track.body_ids.contains(&new_id) {
+                track.body_ids.push(new_id);
+            }
+        }
+        self.trails.remove(&id);
+    }
+    
+    // A reset keeps a locked configuration, only the bodies and time start over
+    fn carry_locked_config(&self, fresh: &mut SimulationState) {
+        if self.config_locked {
+            fresh.config_locked = true;
+            fresh.time_step = self.time_step;
+            fresh.constants = self.constants;
+            fresh.auto_courant = self.auto_courant;
+        }
+    }
+    
+    // dt = courant * min_separation / max_speed up to MAX_TIME_STEP, None when there is no pair or nothing is moving
+    #[must_use]
+    pub fn courant_time_step(&self, courant: f64) -> Option<f64> {
+        let min_separation = self.body_pairs()
+            .map(|(a, b)| a.position.distance(&b.position))
+            .fold(f64::INFINITY, f64::min);
+        let max_speed = self.bodies.iter()
+            .map(|b| (b.velocity.x * b.velocity.x + b.velocity.y * b.velocity.y).sqrt())
+            .fold(0.0, f64::max);
+        
+        if !min_separation.is_finite() || max_speed <= 0.0 {
+            return None;
+        }
+        
+        let dt = courant * min_separation / max_speed;
+        if dt.is_finite() && dt > 0.0 { Some(dt.min(MAX_TIME_STEP)) } else { None }
+    }
+    
+    // Detached copy for look-ahead computations: running, and without watches or queued events
+    #[must_use]
+    pub fn scratch_copy(&self) -> SimulationState {
+        let mut copy = self.clone();
+        copy.is_running = true;
+        copy.watches.clear();
+        copy.pending_events.clear();
+        copy.trails.clear();
+        copy.trail_max_points = 0;
+        copy.trail_spill = None;
+        copy.tutorial = None;
+        copy
+    }
+    
+    // (total mass, reduced mass m1 m2 / (m1 + m2), gravitational parameter G (m1 + m2)) of a pair
+    #[must_use]
+    pub fn two_body_masses(&self, a: u32, b: u32) -> Option<(f64, f64, f64)> {
+        if a == b {
+            return None;
+        }
+        let m1 = self.bodies[self.body_index(a)?].mass;
+        let m2 = self.bodies[self.body_index(b)?].mass;
+        let total = m1 + m2;
+        Some((total, m1 * m2 / total, self.constants.gravity_constant * total))
+    }
+    
+    // Rough outcome of a collision between two bodies from their current relative motion: slower than
+    // the mutual escape speed they stick, a bit faster they bounce, much faster they break apart, and
+    // trajectories that only clip the edge (or miss) are grazes
+    #[must_use]
+    pub fn predicted_collision_outcome(&self, a: u32, b: u32) -> Option<&'static str> {
+        if a == b {
+            return None;
+        }
+        let body1 = &self.bodies[self.body_index(a)?];
+        let body2 = &self.bodies[self.body_index(b)?];
+        
+        let contact_distance = body1.radius + body2.radius;
+        let escape_speed = (2.0 * self.constants.gravity_constant * (body1.mass + body2.mass) / contact_distance).sqrt();
+        
+        let (rx, ry) = (body2.position.x - body1.position.x, body2.position.y - body1.position.y);
+        let (vx, vy) = (body2.velocity.x - body1.velocity.x, body2.velocity.y - body1.velocity.y);
+        let speed = (vx * vx + vy * vy).sqrt();
+        
+        if speed > 1e-9 {
+            // Gravitational focusing widens the cross-section for slow encounters
+            let impact_parameter = (rx * vy - ry * vx).abs() / speed;
+            let capture_parameter = contact_distance * (1.0 + escape_speed * escape_speed / (speed * speed)).sqrt();
+            if impact_parameter > 0.8 * capture_parameter {
+                return Some("graze");
+            }
+        }
+        
+        Some(if speed < escape_speed {
+            "merge"
+        } else if speed < 3.0 * escape_speed {
+            "bounce"
+        } else {
+            "fragment"
+        })
+    }
+    
+    // Closest center-to-center approach of two bodies over the next n_steps, run on a scratch copy
+    pub fn minimum_encounter(&self, body1_id: u32, body2_id: u32, n_steps: u32) -> Result<EncounterPrediction, String> {
+        if body1_id == body2_id {
+            return Err(String::from("the two bodies must be different"));
+        }
+        for id in [body1_id, body2_id] {
+            self.body_index(id).ok_or(format!("no body with id {}", id))?;
+        }
+        
+        let mut sim = self.scratch_copy();
+        let separation = |sim: &SimulationState| {
+            let a = &sim.bodies[sim.body_index(body1_id)?];
+            let b = &sim.bodies[sim.body_index(body2_id)?];
+            Some(a.position.distance(&b.position))
+        };
+        
+        let mut best = EncounterPrediction {
+            min_distance: separation(&sim).unwrap_or(f64::INFINITY),
+            elapsed_time_at_minimum: sim.elapsed_time,
+        };
+        
+        for _ in 0..n_steps.min(MAX_PREDICTION_STEPS) {
+            sim.step();
+            let Some(distance) = separation(&sim) else {
+                break;
+            };
+            if distance < best.min_distance {
+                best = EncounterPrediction { min_distance: distance, elapsed_time_at_minimum: sim.elapsed_time };
+            }
+        }
+        Ok(best)
+    }
+    
+    // Hohmann transfer departing now from one body's orbit to another's around the primary they share, with
+    // both orbits taken as circular at the bodies' current distances. A target that won't be at the arrival
+    // point adds the cost of a one-revolution phasing orbit, v2 |phase error| / (3 pi), so the total is
+    // lowest when the bodies are in the right angular alignment.
+    pub fn minimum_energy_orbit_transfer(&self, from_id: u32, to_id: u32) -> Result<HohmannTransfer, String> {
+        if from_id == to_id {
+            return Err(String::from("the two bodies must be different"));
+        }
+        let from = self.body_index(from_id).ok_or(format!("no body with id {}", from_id))?;
+        let to = self.body_index(to_id).ok_or(format!("no body with id {}", to_id))?;
+        let primary = self.parent_of(from)
+            .filter(|p| Some(*p) == self.parent_of(to))
+            .ok_or("both bodies must orbit the same primary")?;
+        
+        let p = &self.bodies[primary];
+        let mu = self.constants.gravity_constant * p.mass;
+        let (r1, v1) = (self.bodies[from].position - p.position, self.bodies[from].velocity - p.velocity);
+        let (r2, v2) = (self.bodies[to].position - p.position, self.bodies[to].velocity - p.velocity);
+        let (d1, d2) = (r1.length(), r2.length());
+        if d1 <= 0.0 || d2 <= 0.0 {
+            return Err(String::from("neither body may sit on the primary"));
+        }
+        
+        let a = 0.5 * (d1 + d2);
+        let departure = (mu / d1).sqrt() * ((d2 / a).sqrt() - 1.0);
+        let arrival = (mu / d2).sqrt() * (1.0 - (d1 / a).sqrt());
+        let transfer_time = std::f64::consts::PI * (a.powi(3) / mu).sqrt();
+        
+        let cross = |r: Vec2, v: Vec2| r.x * v.y - r.y * v.x;
+        let direction = if cross(r1, v1) < 0.0 { -1.0 } else { 1.0 };
+        let arrival_angle = r1.y.atan2(r1.x) + direction * std::f64::consts::PI;
+        let target_angle = r2.y.atan2(r2.x) + cross(r2, v2) / (d2 * d2) * transfer_time;
+        let phase_error = (target_angle - arrival_angle + std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI)
+            - std::f64::consts::PI;
+        let phasing = (mu / d2).sqrt() * phase_error.abs() / (3.0 * std::f64::consts::PI);
+        
+        Ok(HohmannTransfer {
+            delta_v: departure.abs() + arrival.abs() + phasing,
+            transfer_time,
+            phase_error: phase_error * direction,
+            departure_period: 2.0 * std::f64::consts::PI * (d1.powi(3) / mu).sqrt(),
+        })
+    }
+    
+    // Every body's position now and then every `sample_every` steps, computed on a scratch copy
+    #[must_use]
+    pub fn predict_paths(&self, steps: u32, sample_every: u32) -> Vec<(u32, Vec<Vec2>)> {
+        let mut sim = self.scratch_copy();
+        let sample_every = sample_every.max(1);
+        let mut paths: Vec<(u32, Vec<Vec2>)> = self.bodies.iter()
+            .map(|b| (b.id, vec![b.position]))
+            .collect();
+        
+        for step in 1..=steps.min(MAX_PREDICTION_STEPS) {
+            sim.step();
+            
+            if step % sample_every == 0 {
+                for (id, path) in &mut paths {
+                    if let Some(index) = sim.body_index(*id) {
+                        path.push(sim.bodies[index].position);
+                    }
+                }
+            }
+        }
+        paths
+    }
+    
+    // Time for a LYAPUNOV_PERTURBATION nudge of the body to double, from stepping self next to a nudged copy.
+    // Both are stepped, so call it on a scratch copy. progress gets the fraction of steps done and returns
+    // false to cancel.
+    pub fn lyapunov_time(&mut self, body_id: u32, n_steps: u32, mut progress: impl FnMut(f64) -> bool) -> Result<f64, String> {
+        let index = self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        let mut perturbed = self.clone();
+        perturbed.bodies[index].position.x += LYAPUNOV_PERTURBATION;
+        
+        let n_steps = n_steps.min(MAX_LYAPUNOV_STEPS);
+        let start_time = self.elapsed_time;
+        for step in 1..=n_steps as u64 {
+            self.step();
+            perturbed.step();
+            self.pending_events.clear();
+            perturbed.pending_events.clear();
+            
+            // Merges and disruptions shift the bodies after them, and need not happen in both runs alike
+            let (Some(i), Some(j)) = (self.body_index(body_id), perturbed.body_index(body_id)) else {
+                return Err(format!("body {} was removed after {} steps", body_id, step));
+            };
+            let separation = self.bodies[i].position.distance(&perturbed.bodies[j].position);
+            let growth = separation / LYAPUNOV_PERTURBATION;
+            let t = self.elapsed_time - start_time;
+            if growth > 2.0 && t > 0.0 {
+                return Ok(t / growth.ln());
+            }
+            
+            if step.is_multiple_of(PROGRESS_EVENT_INTERVAL) && !progress(step as f64 / n_steps as f64) {
+                return Err(String::from("cancelled"));
+            }
+        }
+        
+        Ok(f64::INFINITY)
+    }
+    
+    // One batch of a fast-forward: up to PROGRESS_EVENT_INTERVAL steps, fewer once the run is finished. stop
+    // sees every step and returns true to end the run, which also ends when a step pauses the simulation.
+    // Time is added up step by step, so a rezero or reset between batches doesn't count toward max_sim_time.
+    // The simulation is left paused, so the physics thread doesn't step it between batches.
+    fn fast_forward_batch(&mut self, run: &mut FastForwardRun, max_sim_time: f64, mut stop: impl FnMut(&SimulationState) -> bool) {
+        self.is_running = true;
+        while !run.finished(max_sim_time) {
+            let before = self.elapsed_time;
+            self.step();
+            self.pending_events.clear();
+            run.steps += 1;
+            run.elapsed += self.elapsed_time - before;
+            run.stopped = stop(self) || !self.is_running;
+            if run.steps.is_multiple_of(PROGRESS_EVENT_INTERVAL) {
+                break;
+            }
+        }
+        self.is_running = false;
+    }
+    
+    // Bit-exact fingerprint of the bodies and elapsed time. DefaultHasher::new() uses fixed keys, so it
+    // matches across runs of the same build but may change between Rust versions.
+    #[must_use]
+    pub fn checksum(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.elapsed_time.to_bits().hash(&mut hasher);
+        for body in &self.bodies {
+            body.id.hash(&mut hasher);
+            for value in [body.position.x, body.position.y, body.velocity.x, body.velocity.y, body.mass, body.radius] {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+    
+    #[must_use]
+    pub fn center_of_mass(&self) -> Vec2 {
+        let total_mass: f64 = self.bodies.iter().map(|b| b.mass).sum();
+        if total_mass <= 0.0 {
+            return Vec2::new(0.0, 0.0);
+        }
+        
+        let (x, y) = self.bodies.iter().fold((0.0, 0.0), |(x, y), b| {
+            (x + b.mass * b.position.x, y + b.mass * b.position.y)
+        });
+        Vec2::new(x / total_mass, y / total_mass)
+    }
+    
+    #[must_use]
+    fn center_of_mass_velocity(&self) -> Vec2 {
+        let total_mass: f64 = self.bodies.iter().map(|b| b.mass).sum();
+        let (px, py) = self.bodies.iter().fold((0.0, 0.0), |(x, y), b| (x + b.mass * b.velocity.x, y + b.mass * b.velocity.y));
+        Vec2::new(px / total_mass, py / total_mass)
+    }
+    
+    // Time until each body passes closest to the center of mass, assuming both keep moving in a straight line
+    // at their current velocities, i.e. ignoring gravity, which only makes infalling bodies arrive sooner.
+    // Bodies that don't head straight at the COM pass beside it at that time. None when moving away from it.
+    #[must_use]
+    pub fn time_to_com(&self) -> Vec<(u32, Option<f64>)> {
+        let com = self.center_of_mass();
+        let com_velocity = self.center_of_mass_velocity();
+        
+        self.bodies.iter()
+            .map(|b| {
+                let offset = b.position - com;
+                let velocity = b.velocity - com_velocity;
+                let closing = -offset.dot(velocity);
+                let time = (closing > 0.0).then(|| closing / velocity.length_squared());
+                (b.id, time)
+            })
+            .collect()
+    }
+    
+    // Distance at which the large body's tidal pull across the small one, 2*G*M*R / d^3, matches the small
+    // body's surface gravity G*m / R^2: d = R * cbrt(2 * M / m). This is the rigid body estimate, fluid
+    // bodies come apart further out (see ROCHE_COEFFICIENT). None for a missing body or the same id twice.
+    #[must_use]
+    pub fn tidal_disruption_radius(&self, small_id: u32, large_id: u32) -> Option<f64> {
+        if small_id == large_id {
+            return None;
+        }
+        let small = &self.bodies[self.body_index(small_id)?];
+        let large = &self.bodies[self.body_index(large_id)?];
+        Some(small.radius * (2.0 * large.mass / small.mass).cbrt())
+    }
+    
+    // Steps at the effective time step needed to cover one two-body period of the satellite around the
+    // primary, rounded up. None for a missing body, an unbound orbit or a time step that doesn't advance.
+    #[must_use]
+    pub fn steps_per_orbit(&self, satellite_id: u32, primary_id: u32) -> Option<u32> {
+        if satellite_id == primary_id {
+            return None;
+        }
+        let satellite = &self.bodies[self.body_index(satellite_id)?];
+        let primary = &self.bodies[self.body_index(primary_id)?];
+        let period = self.relative_orbit(satellite, primary).elements()?.period;
+        
+        let effective_time_step = self.effective_time_step();
+        if effective_time_step <= 0.0 {
+            return None;
+        }
+        Some((period / effective_time_step).ceil().min(u32::MAX as f64) as u32)
+    }
+    
+    pub fn body_pairs(&self) -> BodyPairs<'_> {
+        BodyPairs::new(&self.bodies)
+    }
+    
+    fn body_index(&self, id: u32) -> Option<usize> {
+        self.bodies.iter().position(|b| b.id == id)
+    }
+    
+    // The heavier body with the strongest pull on the given body, i.e. what it orbits
+    fn parent_of(&self, index: usize) -> Option<usize> {
+        let body = &self.bodies[index];
+        
+        self.bodies.iter()
+            .enumerate()
+            .filter(|(j, other)| *j != index && other.mass > body.mass)
+            .map(|(j, other)| {
+                let dist = body.position.distance(&other.position).max(1e-9);
+                (j, other.mass / (dist * dist))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(j, _)| j)
+    }
+    
+    #[must_use]
+    fn relative_orbit(&self, body: &Body, primary: &Body) -> RelativeOrbit {
+        RelativeOrbit {
+            mu: self.constants.gravity_constant * (body.mass + primary.mass),
+            position: body.position - primary.position,
+            velocity: body.velocity - primary.velocity,
+        }
+    }
+    
+    // Potential energy per body with zero at infinite separation: each pair's softened
+    // -G*m1*m2 / sqrt(r^2 + epsilon^2) is split evenly between its two bodies, so an isolated body reads 0
+    // and the values sum to the system total
+    #[must_use]
+    pub fn normalized_potentials(&self) -> Vec<(u32, f64)> {
+        let mut potentials: Vec<(u32, f64)> = self.bodies.iter().map(|b| (b.id, 0.0)).collect();
+        
+        let mut pairs = self.body_pairs();
+        while let Some((i, j, a, b)) = pairs.next_indexed() {
+            let d2 = a.position.distance(&b.position).powi(2) + self.constants.softening * self.constants.softening;
+            if d2 <= 0.0 {
+                continue;
+            }
+            let half_pair_energy = 0.5 * -self.constants.gravity_constant * a.mass * b.mass / d2.sqrt();
+            potentials[i].1 += half_pair_energy;
+            potentials[j].1 += half_pair_energy;
+        }
+        potentials
+    }
+    
+    #[must_use]
+    pub fn kinetic_energy(&self) -> f64 {
+        self.bodies.iter()
+            .map(|b| 0.5 * b.mass * (b.velocity.x * b.velocity.x + b.velocity.y * b.velocity.y))
+            .sum()
+    }
+    
+    // Potential of the force law: the pair term is the softened -G*m1*m2 / sqrt(r^2 + epsilon^2), so it stays
+    // finite when bodies overlap, and ring forces add the softened potential of their point masses. With the
+    // kinetic energy it is what stepping conserves, apart from drag, orbit constraints and collisions.
+    #[must_use]
+    pub fn potential_energy(&self) -> f64 {
+        let mut potential = 0.0;
+        let mut pairs = self.body_pairs();
+        while let Some((_, _, body1, body2)) = pairs.next_indexed() {
+            let d2 = body1.position.distance(&body2.position).powi(2) + self.constants.softening * self.constants.softening;
+            if d2 > 0.0 {
+                potential -= self.constants.gravity_constant * body1.mass * body2.mass / d2.sqrt();
+            }
+        }
+        
+        for ring in &self.ring_forces {
+            let Some(center) = self.body_index(ring.center_id) else { continue };
+            let points = ring.points(self.bodies[center].position);
+            let softening_squared = ring.softening().powi(2);
+            for (i, body) in self.bodies.iter().enumerate() {
+                if i == center {
+                    continue;
+                }
+                for (point, mass) in &points {
+                    let d2 = point.distance(&body.position).powi(2) + softening_squared;
+                    potential -= self.constants.gravity_constant * body.mass * mass / d2.sqrt();
+                }
+            }
+        }
+        potential
+    }
+    
+    #[must_use]
+    pub fn energy_report(&self) -> EnergyReport {
+        let potential = self.potential_energy();
+        let kinetic = self.kinetic_energy();
+        EnergyReport { kinetic, potential, total: kinetic + potential }
+    }
+    
+    #[must_use]
+    pub fn momentum_report(&self) -> MomentumReport {
+        MomentumReport {
+            linear: self.bodies.iter().fold(Vec2::new(0.0, 0.0), |p, b| p + b.velocity * b.mass),
+            angular: self.bodies.iter()
+                .map(|b| b.mass * (b.position.x * b.velocity.y - b.position.y * b.velocity.x))
+                .sum(),
+        }
+    }
+    
+    #[must_use]
+    pub fn diagnostics(&self) -> Diagnostics {
+        let energy = self.energy_report();
+        let barycenter = self.center_of_mass();
+        let momentum = self.momentum_report().linear;
+        let angular_momentum = self.bodies.iter()
+            .map(|b| {
+                let r = b.position - barycenter;
+                b.mass * (r.x * b.velocity.y - r.y * b.velocity.x)
+            })
+            .sum();
+        
+        Diagnostics {
+            kinetic_energy: energy.kinetic,
+            potential_energy: energy.potential,
+            total_energy: energy.total,
+            momentum,
+            angular_momentum,
+            barycenter,
+        }
+    }
+    
+    // Scales velocities about the COM velocity so kinetic plus potential energy equals the target, positions
+    // stay put. The COM's own motion is left alone, so the lowest reachable energy is the potential plus it.
+    pub fn set_total_energy(&mut self, target: f64) -> Result<(), String> {
+        if !target.is_finite() {
+            return Err(String::from("target energy must be finite"));
+        }
+        if self.bodies.is_empty() {
+            return Err(String::from("there are no bodies"));
+        }
+        
+        let total_mass: f64 = self.bodies.iter().map(|b| b.mass).sum();
+        let com_velocity = self.center_of_mass_velocity();
+        let bulk_kinetic = 0.5 * total_mass * com_velocity.length_squared();
+        let internal_kinetic = self.kinetic_energy() - bulk_kinetic;
+        
+        let minimum = self.potential_energy() + bulk_kinetic;
+        let needed = target - minimum;
+        if needed < 0.0 {
+            return Err(format!("target energy is below the minimum of {} for these positions", minimum));
+        }
+        if internal_kinetic <= 0.0 {
+            if needed > 0.0 {
+                return Err(String::from("bodies are at rest relative to their center of mass, there is no motion to scale"));
+            }
+            return Ok(());
+        }
+        
+        let factor = (needed / internal_kinetic).sqrt();
+        for body in &mut self.bodies {
+            body.velocity = com_velocity + (body.velocity - com_velocity) * factor;
+        }
+        Ok(())
+    }
+    
+    // CSV of the energy every `sample_every` steps on a scratch copy, drift is relative to the starting total
+    #[must_use]
+    pub fn energy_report_csv(&self, steps: u32, sample_every: u32) -> String {
+        let mut sim = self.scratch_copy();
+        let sample_every = sample_every.max(1);
+        let initial_total = sim.kinetic_energy() + sim.potential_energy();
+        
+        let mut csv = String::from("step,elapsed_time,kinetic,potential,total,drift\n");
+        let mut add_row = |step: u32, sim: &SimulationState| {
+            let kinetic = sim.kinetic_energy();
+            let potential = sim.potential_energy();
+            let total = kinetic + potential;
+            let drift = if initial_total != 0.0 { (total - initial_total) / initial_total.abs() } else { 0.0 };
+            csv.push_str(&format!("{},{},{},{},{},{}\n", step, sim.elapsed_time, kinetic, potential, total, drift));
+        };
+        
+        add_row(0, &sim);
+        for step in 1..=steps.min(MAX_PREDICTION_STEPS) {
+            sim.step();
+            if step % sample_every == 0 {
+                add_row(step, &sim);
+            }
+        }
+        csv
+    }
+    
+    // Pairs (body id, primary id) for every other body inside one of the two primaries' Roche lobes, with
+    // lobe radii from Eggleton's approximation r_L / a = 0.49 q^(2/3) / (0.6 q^(2/3) + ln(1 + q^(1/3))).
+    // Empty when a primary is missing, both ids are the same body or a primary has no positive mass.
+    #[must_use]
+    pub fn roche_lobe_occupants(&self, primary1_id: u32, primary2_id: u32) -> Vec<(u32, u32)> {
+        let (Some(i1), Some(i2)) = (self.body_index(primary1_id), self.body_index(primary2_id)) else {
+            return Vec::new();
+        };
+        let (p1, p2) = (&self.bodies[i1], &self.bodies[i2]);
+        if i1 == i2 || p1.mass <= 0.0 || p2.mass <= 0.0 {
+            return Vec::new();
+        }
+        
+        let separation = p1.position.distance(&p2.position);
+        let lobe_radius = |q: f64| {
+            let q23 = q.powf(2.0 / 3.0);
+            separation * 0.49 * q23 / (0.6 * q23 + (1.0 + q.cbrt()).ln())
+        };
+        let lobe1 = lobe_radius(p1.mass / p2.mass);
+        let lobe2 = lobe_radius(p2.mass / p1.mass);
+        
+        self.bodies.iter()
+            .filter(|b| b.id != primary1_id && b.id != primary2_id)
+            .filter_map(|b| {
+                if b.position.distance(&p1.position) < lobe1 {
+                    Some((b.id, primary1_id))
+                } else if b.position.distance(&p2.position) < lobe2 {
+                    Some((b.id, primary2_id))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+    
+    // Smallest ratio of a neighbouring planet pair's spacing delta_a / a_inner to the width of Wisdom's
+    // resonance overlap zone C * (m / M)^(2/7), m being the heavier planet of the pair. Planets are the
+    // bound bodies orbiting the primary, sorted by semi-major axis. Below 1 the pair is likely chaotic.
+    pub fn chaos_threshold_ratio(&self, primary_id: u32) -> Result<f64, String> {
+        let primary_index = self.body_index(primary_id).ok_or(format!("no body with id {}", primary_id))?;
+        let primary = &self.bodies[primary_index];
+        if primary.mass <= 0.0 {
+            return Err(String::from("the primary needs a positive mass"));
+        }
+        
+        let mut planets: Vec<(f64, f64)> = (0..self.bodies.len())
+            .filter(|&i| self.parent_of(i) == Some(primary_index))
+            .filter_map(|i| {
+                let body = &self.bodies[i];
+                let a = self.relative_orbit(body, primary).semi_major_axis()?;
+                Some((a, body.mass))
+            })
+            .collect();
+        if planets.len() < 2 {
+            return Err(String::from("need at least two bound planets around the primary"));
+        }
+        planets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        
+        Ok(planets.windows(2)
+            .map(|pair| {
+                let ((a_inner, m_inner), (a_outer, m_outer)) = (pair[0], pair[1]);
+                let overlap_width = WISDOM_OVERLAP_COEFFICIENT * (m_inner.max(m_outer) / primary.mass).powf(2.0 / 7.0);
+                ((a_outer - a_inner) / a_inner) / overlap_width
+            })
+            .fold(f64::INFINITY, f64::min))
+    }
+    
+    pub fn query_region(&self, shape: &RegionShape, options: &RegionQueryOptions) -> Result<RegionQuery, String> {
+        let mut excluded = HashSet::new();
+        for group_id in &options.exclude_groups {
+            let track = self.com_tracks.iter().find(|t| t.id == *group_id).ok_or(format!("no group with id {}", group_id))?;
+            excluded.extend(track.body_ids.iter().copied());
+        }
+        let tracer_mass = TRACER_MASS_FRACTION * self.bodies.iter().map(|b| b.mass).sum::<f64>();
+        
+        Ok(RegionQuery {
+            step_count: self.step_count,
+            body_ids: self.bodies.iter()
+                .filter(|b| options.include_tracers || b.mass > tracer_mass)
+                .filter(|b| !excluded.contains(&b.id))
+                .filter(|b| shape.intersects_disc(b.position, b.radius))
+                .map(|b| b.id)
+                .collect(),
+        })
+    }
+    
+    // Mass, center of mass and its velocity of every body but the one at index
+    fn rest_of_system(&self, index: usize) -> (f64, Vec2, Vec2) {
+        let (mass, moment, momentum) = self.bodies.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .fold((0.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)), |(m, r, p), (_, b)| {
+                (m + b.mass, r + b.position * b.mass, p + b.velocity * b.mass)
+            });
+        if mass <= 0.0 {
+            return (0.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0));
+        }
+        (mass, moment * (1.0 / mass), momentum * (1.0 / mass))
+    }
+    
+    // One scattering trial on a scratch copy: the body at incoming_index is shifted by impact_offset across
+    // its motion relative to the rest of the system, then stepped up to steps times. Touching another body
+    // slower than their mutual escape speed counts as a merge, the simulation itself only bounces them.
+    // Otherwise the body ends ejected when its two-body energy against the rest of the system is positive.
+    pub fn scattering_trial(&self, incoming_index: usize, impact_offset: f64, steps: u32) -> (ScatteringOutcome, f64) {
+        let mut sim = self.scratch_copy();
+        let id = sim.bodies[incoming_index].id;
+        let (_, _, rest_velocity) = sim.rest_of_system(incoming_index);
+        let direction = (sim.bodies[incoming_index].velocity - rest_velocity).normalized();
+        sim.bodies[incoming_index].position += Vec2::new(-direction.y, direction.x) * impact_offset;
+        
+        let relative_speed = |sim: &SimulationState, index: usize| {
+            (sim.bodies[index].velocity - sim.rest_of_system(index).2).length()
+        };
+        
+        let mut index = incoming_index;
+        for _ in 0..steps.min(MAX_PREDICTION_STEPS) {
+            sim.step();
+            sim.pending_events.clear();
+            if sim.bodies.get(index).map(|b| b.id) != Some(id) {
+                match sim.body_index(id) {
+                    Some(i) => index = i,
+                    None => return (ScatteringOutcome::Merged, 0.0),
+                }
+            }
+            
+            let merged = sim.last_step_collisions.iter()
+                .filter_map(|c| match c.body_ids {
+                    (a, other) | (other, a) if a == id => Some(other),
+                    _ => None,
+                })
+                .any(|other| sim.predicted_collision_outcome(id, other) == Some("merge"));
+            if merged {
+                return (ScatteringOutcome::Merged, relative_speed(&sim, index));
+            }
+        }
+        
+        let body = &sim.bodies[index];
+        let (rest_mass, rest_position, rest_velocity) = sim.rest_of_system(index);
+        let distance = body.position.distance(&rest_position);
+        let speed = (body.velocity - rest_velocity).length();
+        let reduced_mass = body.mass * rest_mass / (body.mass + rest_mass);
+        let energy = 0.5 * reduced_mass * speed * speed - sim.constants.gravity_constant * body.mass * rest_mass / distance.max(1e-9);
+        let outcome = if energy > 0.0 { ScatteringOutcome::Ejected } else { ScatteringOutcome::Bound };
+        (outcome, speed)
+    }
+    
+    // trials scattering trials (capped at MAX_SCATTERING_TRIALS) of incoming_id against the rest of the scene,
+    // each with the impact parameter shifted by a uniform amount within +-impact_spread from the seed.
+    // progress gets the fraction of trials done and returns false to cancel.
+    pub fn scattering_survey(&self, incoming_id: u32, trials: u32, steps: u32, impact_spread: f64, seed: u64,
+                             mut progress: impl FnMut(f64) -> bool) -> Result<ScatteringSummary, String> {
+        if !impact_spread.is_finite() || impact_spread < 0.0 {
+            return Err(String::from("impact_spread must be a non-negative number"));
+        }
+        let index = self.body_index(incoming_id).ok_or(format!("no body with id {}", incoming_id))?;
+        if self.bodies.len() < 2 {
+            return Err(String::from("the scene needs at least one body besides the incoming one"));
+        }
+        if self.time_step * self.time_multiplier <= 0.0 {
+            return Err(String::from("the effective time step must be positive"));
+        }
+        
+        let trials = trials.min(MAX_SCATTERING_TRIALS);
+        let mut rng = SplitMix64::new(seed);
+        let mut summary = ScatteringSummary { trials, merged: 0, ejected: 0, bound: 0, mean_final_speed: 0.0 };
+        let mut total_speed = 0.0;
+        
+        for trial in 0..trials {
+            let offset = (2.0 * rng.next_f64() - 1.0) * impact_spread;
+            let (outcome, speed) = self.scattering_trial(index, offset, steps);
+            match outcome {
+                ScatteringOutcome::Merged => summary.merged += 1,
+                ScatteringOutcome::Ejected => summary.ejected += 1,
+                ScatteringOutcome::Bound => summary.bound += 1,
+            }
+            total_speed += speed;
+            
+            if !progress((trial + 1) as f64 / trials as f64) {
+                return Err(String::from("cancelled"));
+            }
+        }
+        
+        if trials > 0 {
+            summary.mean_final_speed = total_speed / trials as f64;
+        }
+        Ok(summary)
+    }
+    
+    // Circular orbit speed sqrt(G*M/r) around the central body at the center of each cell of a cols x rows
+    // grid between the corners min and max, row by row from min.y. Cells inside the body read the speed at
+    // its surface. Each side is capped at MAX_GRID_SIDE, None when there is no such body.
+    pub fn circular_speed_grid(&self, central_id: u32, min: Vec2, max: Vec2, cols: u32, rows: u32) -> Option<Vec<f64>> {
+        let central = &self.bodies[self.body_index(central_id)?];
+        let (cols, rows) = (cols.min(MAX_GRID_SIDE), rows.min(MAX_GRID_SIDE));
+        let (low, high) = (Vec2::new(min.x.min(max.x), min.y.min(max.y)), Vec2::new(min.x.max(max.x), min.y.max(max.y)));
+        let cell = Vec2::new((high.x - low.x) / cols.max(1) as f64, (high.y - low.y) / rows.max(1) as f64);
+        let gm = self.constants.gravity_constant * central.mass;
+        
+        let mut speeds = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let point = Vec2::new(low.x + (col as f64 + 0.5) * cell.x, low.y + (row as f64 + 0.5) * cell.y);
+                let r = point.distance(&central.position).max(central.radius);
+                speeds.push(if r > 0.0 { (gm / r).sqrt() } else { 0.0 });
+            }
+        }
+        Some(speeds)
+    }
+    
+    // Box-counting dimension of the body positions: the bounding square is cut into 2^k x 2^k cells for
+    // k = 1..=scales and the slope of log(occupied cells) against log(2^k) is fitted by least squares.
+    // Once cells outnumber the bodies every body gets its own cell and the slope drops towards 0, so
+    // 4^scales should stay well below the body count. The coarsest grids overstate curves instead, a ring
+    // fills all four cells of the 2 x 2 grid, so use as many scales as the body count allows. Capped at
+    // MAX_BOX_COUNT_SCALES, None for fewer than 2 scales or when all bodies sit on one point.
+    pub fn fractal_dimension(&self, scales: u32) -> Option<f64> {
+        let scales = scales.min(MAX_BOX_COUNT_SCALES);
+        if scales < 2 {
+            return None;
+        }
+        let (mut low, mut high) = (Vec2::new(f64::INFINITY, f64::INFINITY), Vec2::new(f64::NEG_INFINITY, f64::NEG_INFINITY));
+        for body in &self.bodies {
+            low = Vec2::new(low.x.min(body.position.x), low.y.min(body.position.y));
+            high = Vec2::new(high.x.max(body.position.x), high.y.max(body.position.y));
+        }
+        let side = (high.x - low.x).max(high.y - low.y);
+        if side <= 0.0 || !side.is_finite() {
+            return None;
+        }
+        
+        let points: Vec<(f64, f64)> = (1..=scales)
+            .map(|k| {
+                let cells_per_side = 1u64 << k;
+                let cell_index = |offset: f64| ((offset / side * cells_per_side as f64) as u64).min(cells_per_side - 1);
+                let occupied: HashSet<(u64, u64)> = self.bodies.iter()
+                    .map(|b| (cell_index(b.position.x - low.x), cell_index(b.position.y - low.y)))
+                    .collect();
+                ((cells_per_side as f64).ln(), (occupied.len() as f64).ln())
+            })
+            .collect();
+        
+        let n = points.len() as f64;
+        let (mean_x, mean_y) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        Some(covariance / variance)
+    }
+    
+    // Bodies have no type, so the glyph comes from the orbit hierarchy: `*` for bodies orbiting nothing,
+    // `O` for planets of those (`.` when lighter than ASTEROID_MASS_FRACTION of their star), `o` for moons
+    fn ascii_glyph(&self, index: usize) -> char {
+        let Some(parent) = self.parent_of(index) else { return '*' };
+        if self.parent_of(parent).is_some() {
+            'o'
+        } else if self.bodies[index].mass < self.bodies[parent].mass * ASTEROID_MASS_FRACTION {
+            '.'
+        } else {
+            'O'
+        }
+    }
+    
+    // The scene as width x height characters (capped at MAX_ASCII_WIDTH x MAX_ASCII_HEIGHT) with +y up, scaled
+    // evenly to fit the bodies' bounding box and centered on it. Where bodies share a cell the heaviest shows.
+    pub fn render_ascii(&self, width: u32, height: u32) -> Result<String, String> {
+        if width == 0 || height == 0 {
+            return Err(String::from("width and height must be at least 1"));
+        }
+        let (width, height) = (width.min(MAX_ASCII_WIDTH) as usize, height.min(MAX_ASCII_HEIGHT) as usize);
+        
+        let (min, max) = self.bodies.iter().fold(
+            (Vec2::new(f64::INFINITY, f64::INFINITY), Vec2::new(f64::NEG_INFINITY, f64::NEG_INFINITY)),
+            |(min, max), b| (Vec2::new(min.x.min(b.position.x), min.y.min(b.position.y)),
+                             Vec2::new(max.x.max(b.position.x), max.y.max(b.position.y))),
+        );
+        let center = (min + max) * 0.5;
+        // Columns per distance unit, the same distance takes half as many rows
+        let span_x = (max.x - min.x) / (width - 1).max(1) as f64;
+        let span_y = (max.y - min.y) / ((height - 1).max(1) as f64 * ASCII_CELL_ASPECT);
+        let units_per_column = span_x.max(span_y);
+        
+        let mut cells: Vec<Option<(f64, char)>> = vec![None; width * height];
+        for (index, body) in self.bodies.iter().enumerate() {
+            let offset = body.position - center;
+            let (dx, dy) = if units_per_column > 0.0 {
+                (offset.x / units_per_column, offset.y / (units_per_column * ASCII_CELL_ASPECT))
+            } else {
+                (0.0, 0.0)
+            };
+            let column = ((width - 1) as f64 / 2.0 + dx).round();
+            let row = ((height - 1) as f64 / 2.0 - dy).round();
+            if !(0.0..width as f64).contains(&column) || !(0.0..height as f64).contains(&row) {
+                continue;
+            }
+            
+            let cell = &mut cells[row as usize * width + column as usize];
+            if cell.is_none_or(|(mass, _)| body.mass > mass) {
+                *cell = Some((body.mass, self.ascii_glyph(index)));
+            }
+        }
+        
+        Ok(cells.chunks(width)
+            .map(|row| row.iter().map(|cell| cell.map_or(' ', |(_, glyph)| glyph)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    
+    // Velocity change that would put each body orbiting the primary on a circular orbit at its current
+    // distance, going the same way round. Its direction is the thrust direction, its length the magnitude.
+    pub fn circularization_guidance(&self, primary_id: u32) -> Result<Vec<(u32, Vec2)>, String> {
+        let primary_index = self.body_index(primary_id).ok_or(format!("no body with id {}", primary_id))?;
+        let primary = &self.bodies[primary_index];
+        
+        Ok((0..self.bodies.len())
+            .filter(|&i| self.parent_of(i) == Some(primary_index))
+            .filter_map(|i| {
+                let body = &self.bodies[i];
+                Some((body.id, self.relative_orbit(body, primary).circularizing_delta_v()?))
+            })
+            .collect())
+    }
+    
+    // Indices of the listed bodies, every id has to exist. Repeated ids count once.
+    fn group_indices(&self, ids: &[u32]) -> Result<Vec<usize>, String> {
+        if ids.is_empty() {
+            return Err(String::from("the group has no bodies"));
+        }
+        let mut indices = Vec::new();
+        for id in ids {
+            let index = self.body_index(*id).ok_or(format!("no body with id {}", id))?;
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+        Ok(indices)
+    }
+    
+    // Same velocity change for every body in the group, so their orbits around each other are untouched
+    pub fn apply_group_impulse(&mut self, ids: &[u32], delta_v: Vec2) -> Result<(), String> {
+        if !delta_v.x.is_finite() || !delta_v.y.is_finite() {
+            return Err(String::from("delta_v must be finite"));
+        }
+        for index in self.group_indices(ids)? {
+            self.bodies[index].velocity += delta_v;
+        }
+        Ok(())
+    }
+    
+    pub fn translate_group(&mut self, ids: &[u32], offset: Vec2) -> Result<(), String> {
+        if !offset.x.is_finite() || !offset.y.is_finite() {
+            return Err(String::from("offset must be finite"));
+        }
+        for index in self.group_indices(ids)? {
+            self.bodies[index].position += offset;
+        }
+        Ok(())
+    }
+    
+    // Puts the anchor on a circular orbit around the parent at its current distance and gives the rest of
+    // the group the same velocity change, so a planet and its moons move onto the new orbit together.
+    // Returns the velocity change.
+    pub fn set_group_velocity_to_orbit(&mut self, ids: &[u32], anchor_id: u32, parent_id: u32) -> Result<Vec2, String> {
+        let indices = self.group_indices(ids)?;
+        if !ids.contains(&anchor_id) {
+            return Err(format!("the anchor {} is not in the group", anchor_id));
+        }
+        if ids.contains(&parent_id) {
+            return Err(format!("the parent {} can't be part of the group", parent_id));
+        }
+        let anchor = &self.bodies[self.body_index(anchor_id).ok_or(format!("no body with id {}", anchor_id))?];
+        let parent = &self.bodies[self.body_index(parent_id).ok_or(format!("no body with id {}", parent_id))?];
+        
+        let delta_v = self.relative_orbit(anchor, parent)
+            .circularizing_delta_v()
+            .ok_or("the anchor sits on its parent")?;
+        for index in indices {
+            self.bodies[index].velocity += delta_v;
+        }
+        Ok(delta_v)
+    }
+    
+    // Tidal force per unit mass stretching a body, the difference in pull between its center and surface:
+    // its radius times the largest eigenvalue of the tidal tensor G M (3 r r^T - I) / d^3 summed over every
+    // other body, 2 G M R / d^3 for a single neighbour. Per unit mass so a heavy primary doesn't always win.
+    // Distances are kept at least the sum of the radii so overlapping bodies don't blow it up.
+    #[must_use]
+    pub fn tidal_force(&self, index: usize) -> f64 {
+        let body = &self.bodies[index];
+        let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+        for (j, other) in self.bodies.iter().enumerate() {
+            if j == index {
+                continue;
+            }
+            let delta = other.position - body.position;
+            let d = delta.length().max(body.radius + other.radius).max(1e-9);
+            let direction = delta.normalized();
+            let k = self.constants.gravity_constant * other.mass / (d * d * d);
+            xx += k * (3.0 * direction.x * direction.x - 1.0);
+            xy += k * 3.0 * direction.x * direction.y;
+            yy += k * (3.0 * direction.y * direction.y - 1.0);
+        }
+        
+        let mean = (xx + yy) / 2.0;
+        let spread = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        let largest = (mean + spread).abs().max((mean - spread).abs());
+        body.radius * largest
+    }
+    
+    #[must_use]
+    pub fn most_tidally_stressed(&self) -> Option<(u32, f64)> {
+        (0..self.bodies.len())
+            .map(|i| (self.bodies[i].id, self.tidal_force(i)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+    
+    // How securely a body is held by the body it orbits, e.g. whether a passing planet could steal a moon
+    pub fn binding_report(&self, body_id: u32) -> Result<BindingReport, String> {
+        let index = self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+        let parent_index = self.parent_of(index).ok_or("body is not orbiting anything")?;
+        let body = &self.bodies[index];
+        let parent = &self.bodies[parent_index];
+        let g = self.constants.gravity_constant;
+        
+        let orbit = self.relative_orbit(body, parent);
+        let r = orbit.radius().max(1e-9);
+        let binding_energy = 0.5 * body.mass * orbit.speed_squared() - g * body.mass * parent.mass / r;
+        let parent_pull = g * parent.mass / (r * r);
+        
+        // Differential acceleration a third body causes between the body and its parent
+        let tidal_acceleration = |other: &Body| {
+            let pull = |at: &Vec2| {
+                let dx = other.position.x - at.x;
+                let dy = other.position.y - at.y;
+                let d = (dx * dx + dy * dy).sqrt().max(1e-9);
+                Vec2::new(g * other.mass * dx / (d * d * d), g * other.mass * dy / (d * d * d))
+            };
+            let on_body = pull(&body.position);
+            let on_parent = pull(&parent.position);
+            let (dx, dy) = (on_body.x - on_parent.x, on_body.y - on_parent.y);
+            (dx * dx + dy * dy).sqrt()
+        };
+        
+        let strongest = self.bodies.iter()
+            .enumerate()
+            .filter(|(k, _)| *k != index && *k != parent_index)
+            .map(|(_, other)| (other.id, tidal_acceleration(other)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let (perturber_id, perturbation_ratio) = match strongest {
+            Some((id, acceleration)) => (Some(id), acceleration / parent_pull),
+            None => (None, 0.0),
+        };
+        
+        let hill_fill_fraction = match self.parent_of(parent_index) {
+            Some(grandparent_index) => {
+                let grandparent = &self.bodies[grandparent_index];
+                let hill_radius = parent.position.distance(&grandparent.position)
+                    * (parent.mass / (3.0 * grandparent.mass)).cbrt();
+                r / hill_radius
+            }
+            None => 0.0,
+        };
+        
+        // Prograde orbits stay stable out to roughly half the Hill radius
+        let risk = if binding_energy >= 0.0 || hill_fill_fraction > 0.5 || perturbation_ratio > 0.25 {
+            RiskLevel::High
+        } else if hill_fill_fraction > 0.3 || perturbation_ratio > 0.1 {
+            RiskLevel::Moderate
+        } else {
+            RiskLevel::Low
+        };
+        
+        Ok(BindingReport {
+            body_id,
+            parent_id: parent.id,
+            binding_energy,
+            perturber_id,
+            perturbation_ratio,
+            hill_fill_fraction,
+            risk,
+        })
+    }
+    
+    pub fn action_angle_variables(&self, body_id: u32, primary_id: u32) -> Result<ActionAngleVars, String> {
+        if body_id == primary_id {
+            return Err(String::from("body and primary must be different"));
+        }
+        let body = &self.bodies[self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?];
+        let primary = &self.bodies[self.body_index(primary_id).ok_or(format!("no body with id {}", primary_id))?];
+        
+        let orbit = self.relative_orbit(body, primary);
+        let elements = orbit.elements().ok_or("orbit is not bound to the primary")?;
+        let a = elements.semi_major_axis;
+        
+        // e cos(E) and e sin(E) come straight from the state vector, so M = E - e sin(E) needs no anomaly
+        // first, and E is then solved for the same way as anywhere else M is known
+        let e_cos = 1.0 - orbit.radius() / a;
+        let e_sin = orbit.position.dot(orbit.velocity) / (orbit.mu * a).sqrt();
+        let mean_anomaly = (e_sin.atan2(e_cos) - e_sin).rem_euclid(2.0 * std::f64::consts::PI);
+        let eccentric_anomaly = solve_kepler(mean_anomaly, elements.eccentricity);
+        
+        Ok(ActionAngleVars {
+            action: (orbit.mu * a).sqrt(),
+            angle: mean_anomaly,
+            mean_anomaly,
+            eccentric_anomaly,
+            true_anomaly: elements.true_anomaly,
+        })
+    }
+    
+    // Orbit of a body around the barycenter of all the other bodies, carrying their combined mass
+    #[must_use]
+    fn barycentric_orbit(&self, index: usize) -> Option<RelativeOrbit> {
+        let body = &self.bodies[index];
+        let rest_mass: f64 = self.bodies.iter().map(|b| b.mass).sum::<f64>() - body.mass;
+        if rest_mass <= 0.0 {
+            return None;
+        }
+        
+        let (mut px, mut py, mut vx, mut vy) = (0.0, 0.0, 0.0, 0.0);
+        for (j, other) in self.bodies.iter().enumerate() {
+            if j != index {
+                px += other.mass * other.position.x;
+                py += other.mass * other.position.y;
+                vx += other.mass * other.velocity.x;
+                vy += other.mass * other.velocity.y;
+            }
+        }
+        
+        Some(RelativeOrbit {
+            mu: self.constants.gravity_constant * (rest_mass + body.mass),
+            position: Vec2::new(body.position.x - px / rest_mass, body.position.y - py / rest_mass),
+            velocity: Vec2::new(body.velocity.x - vx / rest_mass, body.velocity.y - vy / rest_mass),
+        })
+    }
+    
+    // Predicted paths as a FeatureCollection with one LineString per body, its id and color as properties
+    #[must_use]
+    pub fn orbits_geojson(&self, steps: u32, sample_every: u32) -> String {
+        let features: Vec<serde_json::Value> = self.predict_paths(steps, sample_every)
+            .into_iter()
+            .map(|(id, path)| {
+                let color = self.bodies.iter()
+                    .find(|b| b.id == id)
+                    .map(|b| b.color.clone())
+                    .unwrap_or_default();
+                let coordinates: Vec<[f64; 2]> = path.iter().map(|p| [p.x, p.y]).collect();
+                
+                serde_json::json!({
+                    "type": "Feature",
+                    "properties": { "id": id, "color": color },
+                    "geometry": { "type": "LineString", "coordinates": coordinates },
+                })
+            })
+            .collect();
+        
+        serde_json::json!({ "type": "FeatureCollection", "features": features }).to_string()
+    }
+    
+    #[must_use]
+    pub fn all_orbital_elements(&self) -> Vec<(u32, Option<OrbitalElements>)> {
+        (0..self.bodies.len())
+            .map(|i| (self.bodies[i].id, self.barycentric_orbit(i).and_then(|orbit| orbit.elements())))
+            .collect()
+    }
+    
+    // Laplace-Lagrange secular pericenter precession rate (radians per time unit) of the satellite around
+    // the primary caused by the perturber, treating both orbits as coplanar and nearly circular
+    #[must_use]
+    pub fn secular_precession_rate(&self, satellite_id: u32, primary_id: u32, perturber_id: u32) -> Option<f64> {
+        if satellite_id == primary_id || satellite_id == perturber_id || primary_id == perturber_id {
+            return None;
+        }
+        
+        let satellite = &self.bodies[self.body_index(satellite_id)?];
+        let primary = &self.bodies[self.body_index(primary_id)?];
+        let perturber = &self.bodies[self.body_index(perturber_id)?];
+        
+        let satellite_orbit = self.relative_orbit(satellite, primary);
+        let a = satellite_orbit.semi_major_axis()?;
+        let a_perturber = self.relative_orbit(perturber, primary).semi_major_axis()?;
+        
+        let mean_motion = (satellite_orbit.mu / (a * a * a)).sqrt();
+        
+        // alpha is always the inner over the outer semi-major axis, alpha_bar only applies for an outer perturber
+        let (alpha, alpha_bar) = if a_perturber > a {
+            (a / a_perturber, a / a_perturber)
+        } else {
+            (a_perturber / a, 1.0)
+        };
+        if alpha >= 1.0 {
+            return None;
+        }
+        
+        Some(0.25 * mean_motion * perturber.mass / (primary.mass + satellite.mass)
+            * alpha * alpha_bar * laplace_coefficient(alpha))
+    }
+    
+    pub fn add_watch(&mut self, spec: WatchSpec) -> Result<WatchId, String> {
+        if !spec.threshold.is_finite() || spec.threshold < 0.0 {
+            return Err(String::from("watch threshold must be a non-negative number"));
+        }
+        if !self.watch_targets_exist(&spec) {
+            return Err(format!("no body with id {}", spec.body_id));
+        }
+        if self.watches.len() >= MAX_WATCHES {
+            return Err(format!("at most {} watches can be active", MAX_WATCHES));
+        }
+        
+        let id = self.next_watch_id;
+        self.next_watch_id += 1;
+        self.watches.push(Watch { id, spec, last_emitted: None });
+        Ok(id)
+    }
+    
+    pub fn add_body(&mut self, mass: f64, position: Vec2, velocity: Vec2, radius: f64, color: String) -> Result<u32, String> {
+        validate_body_values(Some(mass), Some(radius), &[position.x, position.y, velocity.x, velocity.y].map(Some))?;
+        
+        let id = self.next_id();
+        let color = if self.use_golden_angle_colors { golden_angle_color(self.bodies.len(), 0.0) } else { color };
+        self.bodies.push(Body::new(id, mass, position, velocity, radius, &color));
+        Ok(id)
+    }
+    
+    // Sets the given fields of a body. Nothing is changed unless every value is valid, an unknown id is ignored.
+    pub fn update_body(&mut self, id: u32, mass: Option<f64>, position: (Option<f64>, Option<f64>), velocity: (Option<f64>, Option<f64>),
+                       radius: Option<f64>, color: Option<String>) -> Result<(), String> {
+        validate_body_values(mass, radius, &[position.0, position.1, velocity.0, velocity.1])?;
+        
+        if let Some(body) = self.bodies.iter_mut().find(|b| b.id == id) {
+            if let Some(m) = mass { body.mass = m; }
+            if let Some(px) = position.0 { body.position.x = px; }
+            if let Some(py) = position.1 { body.position.y = py; }
+            if let Some(vx) = velocity.0 { body.velocity.x = vx; }
+            if let Some(vy) = velocity.1 { body.velocity.y = vy; }
+            if let Some(r) = radius { body.radius = r; }
+            if let Some(c) = color { body.color = c; }
+        }
+        Ok(())
+    }
+    
+    // Whether a body with that id was there to remove
+    pub fn remove_body(&mut self, id: u32) -> bool {
+        let Some(index) = self.body_index(id) else { return false };
+        self.bodies.remove(index);
+        self.forget_body(id, None);
+        true
+    }
+    
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_body_id.max(next_body_id(&self.bodies));
+        self.next_body_id = id + 1;
+        id
+    }
+    
+    // Drops everything that refers to a body that was removed from the scene. When it merged into another
+    // body, groups it belonged to continue with the survivor instead.
+    fn forget_body(&mut self, id: u32, survivor: Option<u32>) {
+        self.watches.retain(|w| {
+            let other_id = match w.spec.quantity {
+                WatchQuantity::DistanceTo { other_id } => Some(other_id),
+                _ => None,
+            };
+            w.spec.body_id != id && other_id != Some(id)
+        });
+        self.trails.remove(&id);
+        self.drag_coefficients.remove(&id);
+        self.radial_drift_rates.remove(&id);
+        self.burns.remove(&id);
+        self.fluid_body_ids.remove(&id);
+        self.orbit_constraints.retain(|c| c.body_id != id && c.primary_id != id);
+        self.ring_forces.retain(|r| r.center_id != id);
+        
+        for track in &mut self.com_tracks {
+            if track.body_ids.contains(&id) {
+                track.body_ids.retain(|b| *b != id);
+                if let Some(survivor) = survivor.filter(|s| !track.body_ids.contains(s)) {
+                    track.body_ids.push(survivor);
+                }
+            }
+        }
+        self.com_tracks.retain(|t| !t.body_ids.is_empty());
+        
+        if let Some(follow) = self.follow.as_mut().filter(|f| f.body_id == id) {
+            // The anchor keeps its state so the camera glides over to the survivor
+            match survivor {
+                Some(survivor) => follow.body_id = survivor,
+                None => self.follow = None,
+            }
+            self.pending_events.push(EngineEvent::FollowTargetChanged(FollowTargetChanged {
+                elapsed_time: self.elapsed_time,
+                previous_body_id: id,
+                body_id: survivor,
+            }));
+        }
+    }
+    
+    // None stops following. Picking a target, even the same one again, restarts the smoothing at the body.
+    pub fn set_follow_target(&mut self, body_id: Option<u32>, lookahead: Option<f64>, smoothing_time: Option<f64>) -> Result<(), String> {
+        let Some(body_id) = body_id else {
+            self.follow = None;
+            return Ok(());
+        };
+        let lookahead = lookahead.unwrap_or(DEFAULT_FOLLOW_LOOKAHEAD);
+        let smoothing_time = smoothing_time.unwrap_or(DEFAULT_FOLLOW_SMOOTHING_TIME);
+        if !lookahead.is_finite() || lookahead < 0.0 {
+            return Err(String::from("lookahead must be a non-negative number"));
+        }
+        if !smoothing_time.is_finite() || smoothing_time < 0.0 {
+            return Err(String::from("smoothing_time must be a non-negative number"));
+        }
+        let body = &self.bodies[self.body_index(body_id).ok_or(format!("no body with id {}", body_id))?];
+        
+        self.follow = Some(FollowTarget {
+            body_id,
+            lookahead,
+            smoothing_time,
+            anchor: body.position,
+            anchor_velocity: body.velocity,
+        });
+        Ok(())
+    }
+    
+    fn advance_follow_anchor(&mut self, dt: f64) {
+        let Some(follow) = self.follow.as_mut() else { return };
+        if let Some(body) = self.bodies.iter().find(|b| b.id == follow.body_id) {
+            follow.advance(body.position, dt);
+        }
+    }
+    
+    #[must_use]
+    pub fn follow_anchor(&self) -> Option<FollowAnchor> {
+        let follow = self.follow.as_ref()?;
+        let index = self.body_index(follow.body_id)?;
+        let body = &self.bodies[index];
+        
+        let acceleration = if body.fixed || body.mass <= 0.0 {
+            Vec2::new(0.0, 0.0)
+        } else {
+            self.accelerations()[index]
+        };
+        let t = follow.lookahead;
+        
+        Some(FollowAnchor {
+            body_id: body.id,
+            position: body.position,
+            velocity: body.velocity,
+            predicted_position: body.position + body.velocity * t + acceleration * (0.5 * t * t),
+            smoothed_position: follow.anchor,
+        })
+    }
+    
+    // Moves bodies a fraction t of the way to their counterparts with the same id in the target. At t = 1
+    // they take the target's bodies as they are, bodies missing from the target are removed and new ones added.
+    pub fn morph_toward(&mut self, target: &SimulationState, t: f64) {
+        let t = t.clamp(0.0, 1.0);
+        
+        if t < 1.0 {
+            for body in &mut self.bodies {
+                if let Some(goal) = target.bodies.iter().find(|b| b.id == body.id) {
+                    body.position = body.position.lerp(goal.position, t);
+                    body.velocity = body.velocity.lerp(goal.velocity, t);
+                }
+            }
+            return;
+        }
+        
+        let removed: Vec<u32> = self.bodies.iter()
+            .map(|b| b.id)
+            .filter(|id| target.body_index(*id).is_none())
+            .collect();
+        for id in removed {
+            self.forget_body(id, None);
+        }
+        self.bodies = target.bodies.clone();
+    }
+    
+    // Merges bodies closer than tolerance until no such pair is left and returns the removed ids. Pairs
+    // merge the same way as colliding bodies in Merge mode, see merge_pair.
+    pub fn dedupe_coincident(&mut self, tolerance: f64) -> Result<Vec<u32>, String> {
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(String::from("tolerance must be a non-negative number"));
+        }
+        
+        let mut removed = Vec::new();
+        loop {
+            let mut pairs = self.body_pairs();
+            let mut coincident = None;
+            while let Some((i, j, a, b)) = pairs.next_indexed() {
+                if a.position.distance(&b.position) < tolerance {
+                    coincident = Some((i, j));
+                    break;
+                }
+            }
+            let Some((i, j)) = coincident else { break };
+            
+            let (_, absorbed_id) = self.merge_pair(i, j, self.step_count);
+            removed.push(absorbed_id);
+        }
+        Ok(removed)
+    }
+    
+    // Ids of every body merged into this one, directly or through earlier merges of those bodies. Only
+    // merges that happened before a body was absorbed count toward it, in case its id was reused later.
+    #[must_use]
+    pub fn body_ancestors(&self, id: u32) -> Vec<u32> {
+        let mut found = std::collections::BTreeSet::new();
+        let mut pending = vec![(id, self.merger_tree.len())];
+        while let Some((id, before)) = pending.pop() {
+            for (index, event) in self.merger_tree[..before].iter().enumerate() {
+                if event.result_id != id {
+                    continue;
+                }
+                for other in [event.body1_id, event.body2_id] {
+                    if other != id && found.insert(other) {
+                        pending.push((other, index));
+                    }
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+    
+    pub fn remove_watch(&mut self, id: WatchId) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|w| w.id != id);
+        self.watches.len() != before
+    }
+    
+    fn watch_targets_exist(&self, spec: &WatchSpec) -> bool {
+        let other_exists = match spec.quantity {
+            WatchQuantity::DistanceTo { other_id } => self.body_index(other_id).is_some(),
+            _ => true,
+        };
+        self.body_index(spec.body_id).is_some() && other_exists
+    }
+    
+    fn watch_value(&self, spec: &WatchSpec) -> Option<f64> {
+        let index = self.body_index(spec.body_id)?;
+        let body = &self.bodies[index];
+        
+        match spec.quantity {
+            WatchQuantity::Speed => {
+                Some((body.velocity.x * body.velocity.x + body.velocity.y * body.velocity.y).sqrt())
+            }
+            WatchQuantity::Altitude => {
+                let parent = &self.bodies[self.parent_of(index)?];
+                Some(body.position.distance(&parent.position) - parent.radius)
+            }
+            WatchQuantity::Eccentricity => {
+                let parent = &self.bodies[self.parent_of(index)?];
+                Some(self.relative_orbit(body, parent).eccentricity())
+            }
+            WatchQuantity::DistanceTo { other_id } => {
+                let other = &self.bodies[self.body_index(other_id)?];
+                Some(body.position.distance(&other.position))
+            }
+            WatchQuantity::HillFill => {
+                self.binding_report(spec.body_id).ok().map(|report| report.hill_fill_fraction)
+            }
+        }
+    }
+    
+    // Forgets what was last emitted so every watch reports its current value on the next evaluation
+    fn refresh_watches(&mut self) {
+        for watch in &mut self.watches {
+            watch.last_emitted = None;
+        }
+        self.evaluate_watches();
+    }
+    
+    // A single step whether or not the simulation is running, for stepping by hand while paused
+    pub fn step_once(&mut self) {
+        let was_running = self.is_running;
+        self.is_running = true;
+        self.step();
+        // Stepping may have paused it, e.g. at the auto-pause region
+        if !was_running {
+            self.is_running = false;
+        }
+    }
+    
+    // One tick of the idle loop: watches are evaluated against the paused state, which is never stepped,
+    // and a heartbeat is queued either way
+    fn idle_tick(&mut self) {
+        if !self.is_running {
+            self.evaluate_watches();
+        }
+        self.pending_events.push(EngineEvent::Heartbeat(Heartbeat {
+            elapsed_time: self.elapsed_time,
+            step_count: self.step_count,
+            is_running: self.is_running,
+        }));
+    }
+    
+    // Queues a value-changed event for every watch whose value moved more than its threshold since the last emission
+    fn evaluate_watches(&mut self) {
+        let watches = std::mem::take(&mut self.watches);
+        let mut kept = Vec::with_capacity(watches.len());
+        
+        for mut watch in watches {
+            // Drop watches whose bodies no longer exist
+            if !self.watch_targets_exist(&watch.spec) {
+                continue;
+            }
+            
+            if let Some(value) = self.watch_value(&watch.spec) {
+                let changed = match watch.last_emitted {
+                    Some(last) => (value - last).abs() > watch.spec.threshold,
+                    None => true,
+                };
+                
+                if changed {
+                    watch.last_emitted = Some(value);
+                    self.pending_events.push(EngineEvent::ValueChanged(ValueChanged {
+                        watch_id: watch.id,
+                        body_id: watch.spec.body_id,
+                        value,
+                    }));
+                }
+            }
+            
+            kept.push(watch);
+        }
+        
+        self.watches = kept;
+    }
+    
+    // Recolors bodies in id order from an evenly spaced HSV palette, optionally keeping the heaviest body's color
+    pub fn assign_distinct_colors(&mut self, preserve_central: bool) {
+        let central_id = if preserve_central {
+            self.bodies.iter()
+                .max_by(|a, b| a.mass.total_cmp(&b.mass))
+                .map(|b| b.id)
+        } else {
+            None
+        };
+        
+        let mut ids: Vec<u32> = self.bodies.iter()
+            .map(|b| b.id)
+            .filter(|id| Some(*id) != central_id)
+            .collect();
+        ids.sort_unstable();
+        
+        for (slot, id) in ids.iter().enumerate() {
+            let palette_index = (slot * DISTINCT_PALETTE_STRIDE) % DISTINCT_PALETTE_SIZE;
+            let hue = 360.0 * palette_index as f64 / DISTINCT_PALETTE_SIZE as f64;
+            
+            if let Some(body) = self.bodies.iter_mut().find(|b| b.id == *id) {
+                body.color = hsv_to_hex(hue, 0.75, 0.95);
+            }
+        }
+    }
+    
+    // Colors bodies in order along the golden angle, starting from a hue picked by the seed. Fixed bodies and
+    // the heaviest body, the scene's star, get STAR_HUE.
+    pub fn assign_golden_angle_colors(&mut self, seed: Option<u64>) {
+        let hue_offset = seed.map(|seed| SplitMix64::new(seed).next_f64() * 360.0).unwrap_or(0.0);
+        let star_id = self.bodies.iter()
+            .max_by(|a, b| a.mass.total_cmp(&b.mass))
+            .map(|b| b.id);
+        
+        for (index, body) in self.bodies.iter_mut().enumerate() {
+            body.color = if body.fixed || Some(body.id) == star_id {
+                hsv_to_hex(STAR_HUE, 0.8, 0.9)
+            } else {
+                golden_angle_color(index, hue_offset)
+            };
+        }
+    }
+    
+    // Merges the bodies at indices i and j and returns the survivor's index after the removal and the
+    // absorbed id. The smaller id survives with the summed mass and area, the pair's momentum, its center of
+    // mass and the heavier body's color. A fixed body stays fixed where it is. A hard enough impact leaves a
+    // scar marked with step on the survivor where the absorbed body came in.
+    fn merge_pair(&mut self, i: usize, j: usize, step: u64) -> (usize, u32) {
+        let (keep, gone) = if self.bodies[i].id < self.bodies[j].id { (i, j) } else { (j, i) };
+        let absorbed = self.bodies.remove(gone);
+        let keep = if gone < keep { keep - 1 } else { keep };
+        let survivor = &mut self.bodies[keep];
+        
+        let mass = survivor.mass + absorbed.mass;
+        let delta = absorbed.position - survivor.position;
+        let normal = delta * (1.0 / delta.length().max(0.001));
+        let normal_speed = (absorbed.velocity - survivor.velocity).dot(normal);
+        let impact_energy = 0.5 * survivor.mass * (absorbed.mass / mass) * normal_speed * normal_speed;
+        let scar = Scar::from_impact(impact_energy, normal.y.atan2(normal.x), survivor.orientation, step);
+        
+        let weighted = |s: Vec2, o: Vec2| (s * survivor.mass + o * absorbed.mass) * (1.0 / mass);
+        if absorbed.fixed {
+            survivor.position = absorbed.position;
+            survivor.velocity = absorbed.velocity;
+            survivor.fixed = true;
+        } else if !survivor.fixed {
+            survivor.position = weighted(survivor.position, absorbed.position);
+            survivor.velocity = weighted(survivor.velocity, absorbed.velocity);
+        }
+        if absorbed.mass > survivor.mass {
+            survivor.color = absorbed.color.clone();
+        }
+        survivor.radius = (survivor.radius.powi(2) + absorbed.radius.powi(2)).sqrt();
+        survivor.mass = mass;
+        if let Some(scar) = scar {
+            survivor.add_scar(scar);
+        }
+        
+        let survivor_id = survivor.id;
+        let merger = MergerEvent {
+            result_id: survivor_id,
+            body1_id: survivor_id,
+            body2_id: absorbed.id,
+            elapsed_time: self.elapsed_time,
+            combined_mass: mass,
+        };
+        self.merger_tree.push(merger.clone());
+        self.pending_events.push(EngineEvent::BodiesMerged(merger));
+        self.forget_body(absorbed.id, Some(survivor_id));
+        (keep, absorbed.id)
+    }
+    
+    // Merges overlapping pairs one at a time until none is left, so three bodies touching in the same step
+    // end up as one. Two fixed bodies never merge.
+    fn merge_overlapping(&mut self) {
+        loop {
+            let mut pairs = self.body_pairs();
+            let mut overlapping = None;
+            while let Some((i, j, a, b)) = pairs.next_indexed() {
+                if a.position.distance(&b.position) < a.radius + b.radius && !(a.fixed && b.fixed) {
+                    overlapping = Some((i, j));
+                    break;
+                }
+            }
+            let Some((i, j)) = overlapping else { break };
+            
+            // Merges happen during the step, like bounces, so their scars belong to the step being taken
+            let (keep, absorbed_id) = self.merge_pair(i, j, self.step_count + 1);
+            let survivor = &self.bodies[keep];
+            self.last_step_collisions.push(Contact { body_ids: (survivor.id, absorbed_id), point: survivor.position });
+        }
+    }
+    
+    // Shares of a bounce taken by each body, m2 / M and m1 / M, and the reduced mass m1 * m2 / M. A fixed
+    // body, or one more than pin_mass_ratio times heavier than the other, is pinned: it takes no share and
+    // the other body bounces off it as off a wall.
+    fn contact_shares(&self, body1: &Body, body2: &Body) -> (f64, f64, f64) {
+        let pinned1 = body1.fixed || body1.mass > body2.mass * self.pin_mass_ratio;
+        let pinned2 = body2.fixed || body2.mass > body1.mass * self.pin_mass_ratio;
+        match (pinned1, pinned2) {
+            (true, _) => (0.0, 1.0, body2.mass),
+            (_, true) => (1.0, 0.0, body1.mass),
+            _ => {
+                let total = body1.mass + body2.mass;
+                (body2.mass / total, body1.mass / total, body1.mass * (body2.mass / total))
+            }
+        }
+    }
+    
+    fn handle_collisions(&mut self) {
+        let mut collision_data = Vec::new();
+        let mut sound_hints = Vec::new();
+        let mut contacts = Vec::new();
+        let mut scars = Vec::new();
+        
+        let com = self.center_of_mass();
+        let (min_x, max_x) = self.bodies.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), b| {
+            (lo.min(b.position.x), hi.max(b.position.x))
+        });
+        
+        let mut pairs = self.body_pairs();
+        while let Some((i, j, body1, body2)) = pairs.next_indexed() {
+            let delta = body2.position - body1.position;
+            let distance = delta.length();
+            
+            if distance < body1.radius + body2.radius && !(body1.fixed && body2.fixed) {
+                let normal = delta * (1.0 / distance.max(0.001));
+                let relative_velocity = body2.velocity - body1.velocity;
+                let relative_vel_dot_normal = relative_velocity.dot(normal);
+                
+                if relative_vel_dot_normal < 0.0 {
+                    // The impulse is (1 + e) * mu * v_n, so each body's velocity changes by (1 + e) * v_n times the
+                    // other body's share of the mass. Unlike 1/m1 + 1/m2 the shares don't lose the heavy body's
+                    // part when the masses are far apart.
+                    let (share1, share2, reduced_mass) = self.contact_shares(body1, body2);
+                    let velocity_change = normal * ((1.0 + RESTITUTION) * relative_vel_dot_normal);
+                    let vel_change_i = velocity_change * share1;
+                    let vel_change_j = -velocity_change * share2;
+                    
+                    let penetration = (body1.radius + body2.radius) - distance;
+                    let percent = 0.4; 
+                    let correction = normal * penetration * percent;
+                    let pos_corr_i = -correction * share1;
+                    let pos_corr_j = correction * share2;
+                    
+                    collision_data.push((i, j, vel_change_i, vel_change_j, pos_corr_i, pos_corr_j));
+                    
+                    let impact_energy = 0.5 * reduced_mass * relative_vel_dot_normal * relative_vel_dot_normal;
+                    // The normal points from body1 to body2, so body2 is hit from the opposite side
+                    let contact_angle = normal.y.atan2(normal.x);
+                    let step = self.step_count + 1;
+                    for (index, body, angle) in [(i, body1, contact_angle), (j, body2, contact_angle + std::f64::consts::PI)] {
+                        if let Some(scar) = Scar::from_impact(impact_energy, angle, body.orientation, step) {
+                            scars.push((index, scar));
+                        }
+                    }
+                    
+                    let contact = body1.position + normal * body1.radius;
+                    let stereo_pan = if max_x > min_x {
+                        (2.0 * (contact.x - min_x) / (max_x - min_x) - 1.0).clamp(-1.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    
+                    contacts.push(Contact { body_ids: (body1.id, body2.id), point: contact });
+                    sound_hints.push(CollisionSoundHint {
+                        elapsed_time: self.elapsed_time,
+                        relative_speed: relative_velocity.length(),
+                        combined_mass: body1.mass + body2.mass,
+                        distance_from_com: contact.distance(&com),
+                        stereo_pan,
+                    });
+                }
+            }
+        }
+        
+        self.collision_sound_events.extend(sound_hints);
+        self.last_step_collisions.extend(contacts);
+        for (index, scar) in scars {
+            self.bodies[index].add_scar(scar);
+        }
+        
+        for (i, j, vel_i, vel_j, pos_i, pos_j) in collision_data {
+            self.bodies[i].velocity += vel_i;
+            self.bodies[j].velocity += vel_j;
+            self.bodies[i].position += pos_i;
+            self.bodies[j].position += pos_j;
+        }
+    }
+    
+    // Moves the bodies by gravity alone, the other forces are applied after this
+    fn integrate(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        
+        match self.integrator {
+            Integrator::Euler => {
+                for (i, body) in self.bodies.iter_mut().enumerate() {
+                    if body.fixed {
+                        continue;
+                    }
+                    body.velocity += accelerations[i] * dt;
+                    body.position += body.velocity * dt;
+                }
+            }
+            // Move with the old acceleration, then kick with the average of old and new
+            Integrator::Verlet => {
+                for (i, body) in self.bodies.iter_mut().enumerate() {
+                    if body.fixed {
+                        continue;
+                    }
+                    body.position += body.velocity * dt + accelerations[i] * (0.5 * dt * dt);
+                }
+                
+                let new_accelerations = self.accelerations();
+                for (i, body) in self.bodies.iter_mut().enumerate() {
+                    if body.fixed {
+                        continue;
+                    }
+                    body.velocity += (accelerations[i] + new_accelerations[i]) * (0.5 * dt);
+                }
+            }
+            // Classic fourth order Runge-Kutta, each stage evaluates the forces at trial positions
+            Integrator::Rk4 => {
+                let positions: Vec<Vec2> = self.bodies.iter().map(|b| b.position).collect();
+                let velocities: Vec<Vec2> = self.bodies.iter()
+                    .map(|b| if b.fixed { Vec2::new(0.0, 0.0) } else { b.velocity })
+                    .collect();
+                let offset = |base: &[Vec2], rates: &[Vec2], h: f64| -> Vec<Vec2> {
+                    base.iter().zip(rates).map(|(x, rate)| *x + *rate * h).collect()
+                };
+                
+                let (v1, a1) = (velocities.clone(), accelerations);
+                let v2 = offset(&velocities, &a1, 0.5 * dt);
+                let a2 = self.accelerations_at(&offset(&positions, &v1, 0.5 * dt));
+                let v3 = offset(&velocities, &a2, 0.5 * dt);
+                let a3 = self.accelerations_at(&offset(&positions, &v2, 0.5 * dt));
+                let v4 = offset(&velocities, &a3, dt);
+                let a4 = self.accelerations_at(&offset(&positions, &v3, dt));
+                
+                for (i, body) in self.bodies.iter_mut().enumerate() {
+                    if body.fixed {
+                        continue;
+                    }
+                    body.position += (v1[i] + v2[i] * 2.0 + v3[i] * 2.0 + v4[i]) * (dt / 6.0);
+                    body.velocity += (a1[i] + a2[i] * 2.0 + a3[i] * 2.0 + a4[i]) * (dt / 6.0);
+                }
+            }
+        }
+    }
+    
+    #[must_use]
+    fn accelerations(&self) -> Vec<Vec2> {
+        self.accelerations_at(&self.bodies.iter().map(|b| b.position).collect::<Vec<_>>())
+    }
+    
+    #[must_use]
+    fn accelerations_at(&self, positions: &[Vec2]) -> Vec<Vec2> {
+        self.calculate_forces(positions).iter()
+            .zip(&self.bodies)
+            .map(|(force, body)| *force / body.mass)
+            .collect()
+    }
+    
+    // Forces with every body moved to the matching entry of positions, masses and radii stay the bodies' own
+    #[must_use]
+    fn calculate_forces(&self, positions: &[Vec2]) -> Vec<Vec2> {
+        let mut forces = vec![Vec2::new(0.0, 0.0); self.bodies.len()];
+        
+        let mut pairs = self.body_pairs();
+        while let Some((i, j, body1, body2)) = pairs.next_indexed() {
+            let delta = positions[j] - positions[i];
+            // Plummer softening, G*m1*m2 * delta / (r^2 + epsilon^2)^(3/2). Without softening two bodies on the
+            // same point have no direction to pull in.
+            let d2 = delta.length_squared() + self.constants.softening * self.constants.softening;
+            if d2 <= 0.0 {
+                continue;
+            }
+            let force = delta * (self.constants.gravity_constant * body1.mass * body2.mass / (d2 * d2.sqrt()));
+            
+            forces[i] += force;
+            forces[j] -= force;
+        }
+        
+        self.add_ring_forces(positions, &mut forces);
+        forces
+    }
+    
+    fn add_ring_forces(&self, positions: &[Vec2], forces: &mut [Vec2]) {
+        for ring in &self.ring_forces {
+            let Some(center) = self.body_index(ring.center_id) else { continue };
+            let points = ring.points(positions[center]);
+            let softening_squared = ring.softening().powi(2);
+            
+            let mut reaction = Vec2::new(0.0, 0.0);
+            for (i, body) in self.bodies.iter().enumerate() {
+                if i == center {
+                    continue;
+                }
+                let mut force = Vec2::new(0.0, 0.0);
+                for (point, mass) in &points {
+                    let delta = *point - positions[i];
+                    let d2 = delta.length_squared() + softening_squared;
+                    let magnitude = self.constants.gravity_constant * body.mass * mass / (d2 * d2.sqrt());
+                    force += delta * magnitude;
+                }
+                forces[i] += force;
+                reaction -= force;
+            }
+            forces[center] += reaction;
+        }
+    }
+    
+    pub fn add_ring_force(&mut self, center_id: u32, ring_mass: f64, inner_radius: f64, outer_radius: f64) -> Result<RingId, String> {
+        self.body_index(center_id).ok_or(format!("no body with id {}", center_id))?;
+        if !ring_mass.is_finite() || ring_mass <= 0.0 {
+            return Err(String::from("ring_mass must be a positive number"));
+        }
+        if !inner_radius.is_finite() || !outer_radius.is_finite() || inner_radius < 0.0 || outer_radius <= inner_radius {
+            return Err(String::from("the radii must satisfy 0 <= inner_radius < outer_radius"));
+        }
+        
+        let id = self.ring_forces.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        self.ring_forces.push(RingForce { id, center_id, ring_mass, inner_radius, outer_radius });
+        Ok(id)
+    }
+    
+    pub fn remove_ring_force(&mut self, id: RingId) -> bool {
+        let before = self.ring_forces.len();
+        self.ring_forces.retain(|r| r.id != id);
+        self.ring_forces.len() != before
+    }
+}
+
+
+pub type SnapshotToken = u64;
+
+// A pinned snapshot is dropped once it goes this long without being read
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct PinnedSnapshot {
+    state: SimulationState,
+    expires: Instant,
+}
+
+// States pinned with acquire_snapshot, so every read for one rendered frame sees the same step. The time
+// comes from the caller, so the timeout can be tested without waiting for it.
+#[derive(Default)]
+struct SnapshotStore {
+    next_token: SnapshotToken,
+    pinned: HashMap<SnapshotToken, PinnedSnapshot>,
+}
+
+impl SnapshotStore {
+    // Expired snapshots are swept here too, so leaked tokens go away even if nothing reads any more
+    fn pin(&mut self, state: SimulationState, now: Instant) -> SnapshotToken {
+        self.drop_expired(now);
+        self.next_token += 1;
+        self.pinned.insert(self.next_token, PinnedSnapshot { state, expires: now + SNAPSHOT_TIMEOUT });
+        self.next_token
+    }
+    
+    // Reading a snapshot extends its lifetime
+    fn get(&mut self, token: SnapshotToken, now: Instant) -> Result<&SimulationState, String> {
+        self.drop_expired(now);
+        let pinned = self.pinned.get_mut(&token).ok_or(format!("snapshot {} was released or has expired", token))?;
+        pinned.expires = now + SNAPSHOT_TIMEOUT;
+        Ok(&pinned.state)
+    }
+    
+    fn drop_expired(&mut self, now: Instant) {
+        self.pinned.retain(|_, p| p.expires > now);
+    }
+}
+
+// Everything the commands share, registered with Builder::manage so each app owns its own simulation
+pub struct AppState {
+    simulation: Mutex<SimulationState>,
+    snapshots: Mutex<SnapshotStore>,
+    physics_thread_started: AtomicBool,
+    // Asks the physics thread to exit. Both flags only change under the simulation lock, so a stop and a
+    // start right after it can't leave zero or two threads.
+    physics_thread_stop: AtomicBool,
+    // Set by start_simulation_loop, the physics thread also sends each state it publishes there as simulation-frame
+    frame_window: Mutex<Option<tauri::Window>>,
+}
+
+impl AppState {
+    pub fn new(simulation: SimulationState) -> Self {
+        AppState {
+            simulation: Mutex::new(simulation),
+            snapshots: Mutex::new(SnapshotStore::default()),
+            physics_thread_started: AtomicBool::new(false),
+            physics_thread_stop: AtomicBool::new(false),
+            frame_window: Mutex::new(None),
+        }
+    }
+    
+    // Every access to the simulation goes through here so commands report how long they waited for it.
+    // A command that panicked while holding the lock doesn't lock everyone out: the state is taken as it
+    // was left, a half-finished step at worst, and ipc_snapshot zeroes anything non-finite it left behind.
+    pub fn simulation(&self) -> MutexGuard<'_, SimulationState> {
+        let start = Instant::now();
+        let sim = self.simulation.lock().unwrap_or_else(PoisonError::into_inner);
+        crate::metrics::add_lock_wait(start.elapsed());
+        sim
+    }
+    
+    fn snapshots(&self) -> MutexGuard<'_, SnapshotStore> {
+        self.snapshots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    // Trails of the live state, or of a pinned snapshot when a token is given
+    fn trails(&self, snapshot: Option<SnapshotToken>) -> Result<HashMap<String, Vec<Vec2>>, String> {
+        match snapshot {
+            Some(token) => self.snapshots().get(token, Instant::now())?.trail_positions(),
+            None => self.simulation().trail_positions(),
+        }
+    }
+    
+    fn frame_window(&self) -> MutexGuard<'_, Option<tauri::Window>> {
+        self.frame_window.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+//Tauri commands
+#[tauri::command]
+pub fn get_simulation_state(window: tauri::Window, state: tauri::State<'_, AppState>) -> SimulationState {
+    let mut sim = state.simulation();
+    publish_state(&window, &mut sim)
+}
+
+// Partial view with only the listed bodies, everything global is kept. Only for display: stepping it
+// would give different results, since the gravity of the left out bodies is missing.
+#[tauri::command]
+pub fn get_simulation_state_subset(window: tauri::Window, state: tauri::State<'_, AppState>, body_ids: Vec<u32>) -> Result<SimulationState, String> {
+    let mut sim = state.simulation();
+    if let Some(id) = body_ids.iter().find(|id| sim.body_index(**id).is_none()) {
+        return Err(format!("no body with id {}", id));
+    }
+    
+    let mut subset = sim.ipc_snapshot();
+    subset.bodies.retain(|b| body_ids.contains(&b.id));
+    subset.corrupt_fields.retain(|f| body_ids.contains(&f.body_id));
+    let events = sim.take_events();
+    emit_events(&window, events);
+    Ok(subset)
+}
+
+#[tauri::command]
+pub fn set_simulation_running(app: tauri::AppHandle, state: tauri::State<'_, AppState>, running: bool) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Playback)?;
+    sim.is_running = running;
+    if running {
+        ensure_physics_thread(app, &state, &sim);
+    }
+    Ok(())
+}
+
+// Starts running with the physics thread doing the stepping, like set_simulation_running(true), and has
+// it send every state it publishes to window as simulation-frame as well as simulation-tick
+#[tauri::command]
+pub fn start_simulation_loop(app: tauri::AppHandle, window: tauri::Window, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Playback)?;
+    sim.is_running = true;
+    *state.frame_window() = Some(window);
+    ensure_physics_thread(app, &state, &sim);
+    Ok(())
+}
+
+// Pauses the simulation and makes the physics thread exit after its current step
+#[tauri::command]
+pub fn stop_simulation_loop(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Playback)?;
+    sim.is_running = false;
+    *state.frame_window() = None;
+    if state.physics_thread_started.load(Ordering::SeqCst) {
+        state.physics_thread_stop.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+// Takes the simulation guard so the flags only change under the lock, see AppState
+fn ensure_physics_thread(app: tauri::AppHandle, state: &AppState, _sim: &MutexGuard<'_, SimulationState>) {
+    state.physics_thread_stop.store(false, Ordering::SeqCst);
+    if !state.physics_thread_started.swap(true, Ordering::SeqCst) {
+        start_physics_thread(app);
+    }
+}
+
+// Steps the simulation PHYSICS_TICK_RATE times a second while it is running and sends the state to all
+// windows as simulation-tick at emit_rate_hz, and ahead of the events of every step that queued some.
+// Pausing only stops the stepping, the thread stays around until stop_simulation_loop. Each step holds
+// the lock, so resets and edits land between steps.
+fn start_physics_thread(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let tick = Duration::from_secs_f64(1.0 / PHYSICS_TICK_RATE);
+        let mut last_emit: Option<Instant> = None;
+        loop {
+            let started = Instant::now();
+            {
+                let state = app.state::<AppState>();
+                let mut sim = state.simulation();
+                if state.physics_thread_stop.swap(false, Ordering::SeqCst) {
+                    state.physics_thread_started.store(false, Ordering::SeqCst);
+                    return;
+                }
+                if sim.is_running {
+                    sim.step();
+                    // Events never wait for the next due tick, their state goes out with them
+                    let emit_due = last_emit.is_none_or(|t| t.elapsed().as_secs_f64() >= 1.0 / sim.emit_rate_hz);
+                    if emit_due || !sim.pending_events.is_empty() {
+                        // Sent under the lock, so states from here and the idle ticker go out in step order
+                        let publication = sim.publish();
+                        if let Some(window) = state.frame_window().as_ref() {
+                            let _ = window.emit("simulation-frame", &publication.state);
+                        }
+                        emit_publication(&app, publication);
+                        last_emit = Some(started);
+                    }
+                }
+            }
+            std::thread::sleep(tick.saturating_sub(started.elapsed()));
+        }
+    });
+}
+
+#[tauri::command]
+pub fn set_emit_rate_hz(state: tauri::State<'_, AppState>, rate: f64) -> Result<(), CommandError> {
+    validate_emit_rate(rate)?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    sim.emit_rate_hz = rate;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn rezero_time(window: tauri::Window, state: tauri::State<'_, AppState>, reset_step_count: bool) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::TimeControl)?;
+    sim.rezero_time(reset_step_count);
+    let events = sim.take_events();
+    emit_events(&window, events);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn reset_simulation(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    sim.start_over(SimulationState::new());
+    Ok(())
+}
+
+// Reset to the default system with some bodies placed at chosen angles
+#[tauri::command]
+pub fn reset_simulation_with_phases(state: tauri::State<'_, AppState>, phases: PresetPhases) -> Result<(), CommandError> {
+    phases.validate()?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    sim.start_over(SimulationState::with_phases(&phases));
+    Ok(())
+}
+
+// Replaces the scene with a Plummer cluster in approximate virial equilibrium, using the current G
+#[tauri::command]
+pub fn setup_plummer(state: tauri::State<'_, AppState>, n: u32, total_mass: f64, scale_radius: f64, seed: u64) -> Result<SimulationState, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    let cluster = SimulationState::plummer_sphere(n, total_mass, scale_radius, seed, sim.constants.gravity_constant)?;
+    sim.start_over(cluster);
+    Ok(sim.ipc_snapshot())
+}
+
+// Replaces the scene with a disk around a central body that has an empty ring at gap_center, using the current G
+#[tauri::command]
+pub fn setup_disk_with_gap(state: tauri::State<'_, AppState>, disk: DiskWithGap) -> Result<SimulationState, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    let disk = SimulationState::disk_with_gap(&disk, sim.constants.gravity_constant)?;
+    sim.start_over(disk);
+    Ok(sim.ipc_snapshot())
+}
+
+// Read-only mode for presenting, allow lists the categories that keep working. The mode itself can
+// always be switched, the presenter controls it.
+#[tauri::command]
+pub fn set_readonly(state: tauri::State<'_, AppState>, enabled: bool, allow: Vec<CommandCategory>) {
+    state.simulation().readonly = if enabled { Some(ReadOnlyMode { allow }) } else { None };
+}
+
+// Writes the whole simulation state as scenario JSON that load_simulation reads back
+#[tauri::command]
+pub fn save_simulation(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let json = state.simulation().to_scenario_json()?;
+    std::fs::write(&path, json).map_err(|e| format!("could not write {}: {}", path, e))
+}
+
+// Loads a scenario saved as simulation state JSON. It starts paused, problems that could be repaired are
+// reported as warnings.
+#[tauri::command]
+pub fn load_simulation(state: tauri::State<'_, AppState>, path: String) -> Result<LoadReport, CommandError> {
+    state.simulation().check_allowed(CommandCategory::Scenario)?;
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let mut loaded = SimulationState::from_scenario_json(&json)?;
+    
+    let mut warnings = loaded.repair_duplicate_ids();
+    warnings.extend(loaded.repair_tutorial());
+    warnings.extend(loaded.constants.warnings());
+    loaded.is_running = false;
+    
+    state.simulation().start_over(loaded);
+    Ok(LoadReport { warnings })
+}
+
+#[tauri::command]
+pub fn list_tutorials() -> Vec<&'static str> {
+    crate::presets::PRESETS.iter().filter(|p| p.tutorial).map(|p| p.name).collect()
+}
+
+// Replaces the scene with a built-in tutorial, paused before its first checkpoint
+#[tauri::command]
+pub fn load_tutorial(state: tauri::State<'_, AppState>, name: String) -> Result<SimulationState, CommandError> {
+    let scene = crate::presets::scene(&name)
+        .filter(|scene| scene.tutorial.is_some())
+        .ok_or(format!("no tutorial named {}", name))?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    sim.start_over(scene);
+    Ok(sim.ipc_snapshot())
+}
+
+#[tauri::command]
+pub fn get_next_tutorial_step(state: tauri::State<'_, AppState>) -> Option<TutorialStep> {
+    state.simulation().next_tutorial_step().cloned()
+}
+
+#[tauri::command]
+pub fn get_presets() -> Vec<crate::presets::PresetInfo> {
+    crate::presets::PRESETS.to_vec()
+}
+
+// Replaces the scene with a built-in preset, paused
+#[tauri::command]
+pub fn load_preset(state: tauri::State<'_, AppState>, name: String) -> Result<SimulationState, CommandError> {
+    let scene = crate::presets::scene(&name).ok_or(format!("no preset named {}", name))?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    sim.start_over(scene);
+    Ok(sim.ipc_snapshot())
+}
+
+// Builds a scene from a NASA Horizons osculating elements CSV at the given Julian Date and loads it paused
+#[tauri::command]
+pub fn load_nasa_horizons_csv(state: tauri::State<'_, AppState>, path: String, epoch_jd: f64) -> Result<SimulationState, CommandError> {
+    let csv = std::fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Scenario)?;
+    let scene = crate::horizons::scene_from_csv(&csv, epoch_jd, sim.constants.gravity_constant)?;
+    sim.start_over(scene);
+    Ok(sim.ipc_snapshot())
+}
+
+// Steps once by hand while paused. While running the physics thread does the stepping once it has been
+// started, and this only returns the current state.
+#[tauri::command]
+pub fn step_simulation(window: tauri::Window, state: tauri::State<'_, AppState>) -> SimulationState {
+    let mut sim = state.simulation();
+    if !sim.is_running {
+        sim.step_once();
+    } else if !state.physics_thread_started.load(Ordering::SeqCst) {
+        sim.step();
+    }
+    publish_state(&window, &mut sim)
+}
+
+#[tauri::command]
+pub fn set_body_fixed(state: tauri::State<'_, AppState>, id: u32, fixed: bool) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    let index = sim.body_index(id).ok_or(format!("no body with id {}", id))?;
+    sim.bodies[index].fixed = fixed;
+    Ok(())
+}
+
+// Id and tidal force per unit mass of the body under the greatest tidal stress, None for an empty scene
+#[tauri::command]
+pub fn most_tidally_stressed(state: tauri::State<'_, AppState>) -> Option<(u32, f64)> {
+    state.simulation().most_tidally_stressed()
+}
+
+#[tauri::command]
+pub fn get_merger_tree(state: tauri::State<'_, AppState>) -> Vec<MergerEvent> {
+    state.simulation().merger_tree.clone()
+}
+
+#[tauri::command]
+pub fn get_body_ancestors(state: tauri::State<'_, AppState>, id: u32) -> Vec<u32> {
+    state.simulation().body_ancestors(id)
+}
+
+// Cleanup for bodies that ended up on top of each other, separate from collision handling
+#[tauri::command]
+pub fn dedupe_coincident(state: tauri::State<'_, AppState>, tolerance: f64) -> Result<Vec<u32>, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.dedupe_coincident(tolerance)?)
+}
+
+// Advances only the listed bodies on a scratch copy, everything else is held fixed as a gravity source
+#[tauri::command]
+pub fn step_bodies_independently(state: tauri::State<'_, AppState>, body_ids: Vec<u32>, n_steps: u32) -> Result<HashMap<u32, Vec2>, String> {
+    let mut sim = state.simulation().scratch_copy();
+    for id in &body_ids {
+        sim.body_index(*id).ok_or(format!("no body with id {}", id))?;
+    }
+    
+    for body in &mut sim.bodies {
+        body.fixed = !body_ids.contains(&body.id);
+    }
+    for _ in 0..n_steps.min(MAX_PREDICTION_STEPS) {
+        sim.step();
+    }
+    
+    Ok(sim.bodies.iter()
+        .filter(|b| body_ids.contains(&b.id))
+        .map(|b| (b.id, b.position))
+        .collect())
+}
+
+// Plans a burn sequence: on a scratch copy with every other body fixed, the body gets each delta_v once
+// trigger_time (from now) has passed. Returns the body's position at each kick.
+#[tauri::command]
+pub fn simulate_impulse_sequence(state: tauri::State<'_, AppState>, body_id: u32, kicks: Vec<(f64, Vec2)>) -> Result<Vec<Vec2>, String> {
+    let mut previous = f64::NEG_INFINITY;
+    for (time, delta_v) in &kicks {
+        if !time.is_finite() || *time < 0.0 || !delta_v.x.is_finite() || !delta_v.y.is_finite() {
+            return Err(String::from("kick times must be non-negative and delta_v finite"));
+        }
+        if *time <= previous {
+            return Err(String::from("kick times must be strictly increasing"));
+        }
+        previous = *time;
+    }
+    
+    let mut sim = state.simulation().scratch_copy();
+    sim.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+    if sim.time_step * sim.time_multiplier <= 0.0 {
+        return Err(String::from("the effective time step must be positive"));
+    }
+    for body in &mut sim.bodies {
+        body.fixed = body.id != body_id;
+    }
+    
+    let start_time = sim.elapsed_time;
+    let mut steps = 0;
+    let mut positions = Vec::with_capacity(kicks.len());
+    for (time, delta_v) in kicks {
+        while sim.elapsed_time - start_time < time {
+            if steps == MAX_PREDICTION_STEPS {
+                return Err(format!("the kick at t = {} is more than {} steps away", time, MAX_PREDICTION_STEPS));
+            }
+            sim.step();
+            steps += 1;
+        }
+        // Merging into a fixed body removes it, and any merge shifts the bodies after the absorbed one
+        let index = sim.body_index(body_id)
+            .ok_or(format!("body {} merged into another body before the kick at t = {}", body_id, time))?;
+        let body = &mut sim.bodies[index];
+        body.velocity.x += delta_v.x;
+        body.velocity.y += delta_v.y;
+        positions.push(body.position);
+    }
+    Ok(positions)
+}
+
+#[tauri::command]
+pub fn morph_to(state: tauri::State<'_, AppState>, target: SimulationState, t: f64) -> Result<(), CommandError> {
+    if !t.is_finite() {
+        return Err(CommandError::message("t must be a finite number"));
+    }
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    sim.morph_toward(&target, t);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_time_multiplier(state: tauri::State<'_, AppState>, multiplier: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::TimeControl)?;
+    sim.set_time_multiplier(multiplier)?;
+    Ok(())
+}
+
+// The frontend sends every field as its own argument
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn update_body(state: tauri::State<'_, AppState>, id: u32, mass: Option<f64>, position_x: Option<f64>, position_y: Option<f64>, 
+                    velocity_x: Option<f64>, velocity_y: Option<f64>, radius: Option<f64>, color: Option<String>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.update_body(id, mass, (position_x, position_y), (velocity_x, velocity_y), radius, color)?)
+}
+
+// Returns the new body's id. It is picked while the lock is held, so concurrent calls never share one.
+// The frontend sends every field as its own argument, as it does for update_body.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn add_body(state: tauri::State<'_, AppState>, mass: f64, position_x: f64, position_y: f64, velocity_x: f64, velocity_y: f64,
+                radius: f64, color: String) -> Result<u32, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.add_body(mass, Vec2::new(position_x, position_y), Vec2::new(velocity_x, velocity_y), radius, color)?)
+}
+
+#[tauri::command]
+pub fn remove_body(state: tauri::State<'_, AppState>, id: u32) -> Result<bool, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.remove_body(id))
+}
+
+#[tauri::command]
+pub fn auto_distinct_colors(state: tauri::State<'_, AppState>, preserve_central: Option<bool>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    sim.assign_distinct_colors(preserve_central.unwrap_or(true));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_follow_target(state: tauri::State<'_, AppState>, body_id: Option<u32>, lookahead: Option<f64>,
+                         smoothing_time: Option<f64>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.set_follow_target(body_id, lookahead, smoothing_time)?)
+}
+
+#[tauri::command]
+pub fn get_follow_anchor(window: tauri::Window, state: tauri::State<'_, AppState>) -> Option<FollowAnchor> {
+    let mut sim = state.simulation();
+    let anchor = sim.follow_anchor();
+    let events = sim.take_events();
+    emit_events(&window, events);
+    anchor
+}
+
+#[tauri::command]
+pub fn set_body_fluid(state: tauri::State<'_, AppState>, id: u32, fluid: bool) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.set_body_fluid(id, fluid)?)
+}
+
+// None turns tidal disruption off, otherwise fluid bodies are torn apart at roche_excess times inside the Roche limit
+#[tauri::command]
+pub fn set_tidal_disruption(state: tauri::State<'_, AppState>, roche_excess: Option<f64>) -> Result<(), CommandError> {
+    if roche_excess.is_some_and(|excess| !excess.is_finite() || excess < 1.0) {
+        return Err(CommandError::message("roche_excess must be at least 1"));
+    }
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.disruption_roche_excess = roche_excess;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn start_burn(state: tauri::State<'_, AppState>, id: u32, direction_x: f64, direction_y: f64, acceleration: f64,
+                  duration_steps: u32) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.start_burn(id, Vec2::new(direction_x, direction_y), acceleration, duration_steps)?)
+}
+
+#[tauri::command]
+pub fn apply_group_impulse(state: tauri::State<'_, AppState>, ids: Vec<u32>, delta_v: Vec2) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.apply_group_impulse(&ids, delta_v)?)
+}
+
+#[tauri::command]
+pub fn translate_group(state: tauri::State<'_, AppState>, ids: Vec<u32>, offset: Vec2) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.translate_group(&ids, offset)?)
+}
+
+#[tauri::command]
+pub fn set_group_velocity_to_orbit(state: tauri::State<'_, AppState>, ids: Vec<u32>, anchor_id: u32, parent_id: u32) -> Result<Vec2, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.set_group_velocity_to_orbit(&ids, anchor_id, parent_id)?)
+}
+
+#[tauri::command]
+pub fn circular_speed_grid(state: tauri::State<'_, AppState>, central_id: u32, min: Vec2, max: Vec2, cols: u32, rows: u32) -> Option<Vec<f64>> {
+    state.simulation().circular_speed_grid(central_id, min, max, cols, rows)
+}
+
+#[tauri::command]
+pub fn render_ascii_art(state: tauri::State<'_, AppState>, width: u32, height: u32) -> Result<String, String> {
+    state.simulation().render_ascii(width, height)
+}
+
+#[tauri::command]
+pub fn fractal_dimension(state: tauri::State<'_, AppState>, scales: u32) -> Option<f64> {
+    state.simulation().fractal_dimension(scales)
+}
+
+#[tauri::command]
+pub fn tidal_disruption_radius(state: tauri::State<'_, AppState>, small_id: u32, large_id: u32) -> Option<f64> {
+    state.simulation().tidal_disruption_radius(small_id, large_id)
+}
+
+#[tauri::command]
+pub fn steps_per_orbit(state: tauri::State<'_, AppState>, satellite_id: u32, primary_id: u32) -> Option<u32> {
+    state.simulation().steps_per_orbit(satellite_id, primary_id)
+}
+
+#[tauri::command]
+pub fn time_to_com(state: tauri::State<'_, AppState>) -> Vec<(u32, Option<f64>)> {
+    state.simulation().time_to_com()
+}
+
+#[tauri::command]
+pub fn set_total_energy(state: tauri::State<'_, AppState>, target: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::BodyEdit)?;
+    Ok(sim.set_total_energy(target)?)
+}
+
+#[tauri::command]
+pub fn randomize_body_colors(state: tauri::State<'_, AppState>, seed: Option<u64>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    sim.assign_golden_angle_colors(seed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_golden_angle_colors(state: tauri::State<'_, AppState>, enabled: bool) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    sim.use_golden_angle_colors = enabled;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn watch_value(state: tauri::State<'_, AppState>, spec: WatchSpec) -> Result<WatchId, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.add_watch(spec)?)
+}
+
+#[tauri::command]
+pub fn unwatch(state: tauri::State<'_, AppState>, id: WatchId) -> Result<bool, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.remove_watch(id))
+}
+
+#[tauri::command]
+pub fn secular_precession(state: tauri::State<'_, AppState>, satellite_id: u32, primary_id: u32, perturber_id: u32) -> Option<f64> {
+    let sim = state.simulation();
+    sim.secular_precession_rate(satellite_id, primary_id, perturber_id)
+}
+
+#[tauri::command]
+pub fn cancel_operation() -> bool {
+    let busy = OPERATION_BUSY.load(Ordering::SeqCst);
+    if busy {
+        OPERATION_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    busy
+}
+
+// Steps the live simulation until the filter matches or max_sim_time runs out and leaves it paused. Runs off
+// the main thread and only holds the lock for one batch of steps at a time, so the physics thread, edits and
+// reads get in between. Edits are kept and the run carries on from them.
+#[tauri::command(async)]
+pub fn run_until_event(window: tauri::Window, state: tauri::State<'_, AppState>, event_filter: EventFilter, max_sim_time: f64) -> Result<FastForwardResult, CommandError> {
+    crate::metrics::begin("run_until_event");
+    state.simulation().check_allowed(CommandCategory::Playback)?;
+    if !max_sim_time.is_finite() || max_sim_time <= 0.0 {
+        return Err(CommandError::message("max_sim_time must be a positive number"));
+    }
+    
+    let operation = BusyOperation::begin()?;
+    let mut detector = EventDetector::new(event_filter);
+    {
+        let sim = state.simulation();
+        if sim.time_step * sim.time_multiplier <= 0.0 {
+            return Err(CommandError::message("the effective time step must be positive"));
+        }
+        detector.check(&sim);
+    }
+    
+    let mut run = FastForwardRun::default();
+    let mut event = None;
+    while !run.finished(max_sim_time) {
+        state.simulation().fast_forward_batch(&mut run, max_sim_time, |sim| {
+            event = detector.check(sim);
+            event.is_some()
+        });
+        if run.finished(max_sim_time) {
+            break;
+        }
+        if operation.cancelled() {
+            run.cancelled = true;
+            break;
+        }
+        let _ = window.emit("operation-progress", OperationProgress {
+            operation: "run_until_event",
+            progress: (run.elapsed / max_sim_time).min(1.0),
+        });
+    }
+    
+    let events = {
+        let mut sim = state.simulation();
+        sim.refresh_watches();
+        sim.take_events()
+    };
+    emit_events(&window, events);
+    
+    Ok(FastForwardResult { event, elapsed: run.elapsed, steps: run.steps, cancelled: run.cancelled })
+}
+
+// Steps the live simulation for the given simulated time in batches like run_until_event, and returns the
+// change in potential energy over the steps. It is measured under the lock of each batch, so edits made
+// between batches don't count toward it. progress gets the fraction of the time covered and returns false
+// to stop early, the steps taken so far are kept. Afterwards the simulation runs again if it did before.
+fn release_potential(state: &AppState, seconds: f64, mut progress: impl FnMut(f64) -> bool) -> Result<f64, String> {
+    let was_running = {
+        let sim = state.simulation();
+        if sim.time_step * sim.time_multiplier <= 0.0 {
+            return Err(String::from("the effective time step must be positive"));
+        }
+        sim.is_running
+    };
+    
+    let mut run = FastForwardRun::default();
+    let mut change = 0.0;
+    while !run.finished(seconds) {
+        let mut sim = state.simulation();
+        let start_potential = sim.energy_report().potential;
+        sim.fast_forward_batch(&mut run, seconds, |_| false);
+        change += sim.energy_report().potential - start_potential;
+        drop(sim);
+        if !run.finished(seconds) && !progress((run.elapsed / seconds).min(1.0)) {
+            break;
+        }
+    }
+    
+    let mut sim = state.simulation();
+    sim.is_running = was_running;
+    sim.refresh_watches();
+    Ok(change)
+}
+
+// Fast-forwards the simulation by the given simulated time and returns the change in total potential
+// energy, negative when the system contracted. A cancelled run keeps the steps taken so far.
+#[tauri::command(async)]
+pub fn potential_released(window: tauri::Window, state: tauri::State<'_, AppState>, seconds: f64) -> Result<f64, CommandError> {
+    crate::metrics::begin("potential_released");
+    state.simulation().check_allowed(CommandCategory::Playback)?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(CommandError::message("seconds must be a non-negative number"));
+    }
+    
+    let operation = BusyOperation::begin()?;
+    let change = release_potential(&state, seconds, |progress| {
+        if operation.cancelled() {
+            return false;
+        }
+        let _ = window.emit("operation-progress", OperationProgress { operation: "potential_released", progress });
+        true
+    })?;
+    let events = state.simulation().take_events();
+    emit_events(&window, events);
+    
+    Ok(change)
+}
+
+// Records (x, vx) each time the body crosses y = section_y moving upwards, stepping a scratch copy.
+// Stops at n_crossings, after MAX_POINCARE_STEPS or on cancel, returning the crossings found so far.
+#[tauri::command(async)]
+pub fn compute_poincare_section(window: tauri::Window, state: tauri::State<'_, AppState>, section_y: f64, body_id: u32, n_crossings: u32) -> Result<Vec<(f64, f64)>, String> {
+    crate::metrics::begin("compute_poincare_section");
+    if !section_y.is_finite() {
+        return Err(String::from("section_y must be a finite number"));
+    }
+    
+    let operation = BusyOperation::begin()?;
+    let mut sim = state.simulation().scratch_copy();
+    let mut index = sim.body_index(body_id).ok_or(format!("no body with id {}", body_id))?;
+    if sim.time_step * sim.time_multiplier <= 0.0 {
+        return Err(String::from("the effective time step must be positive"));
+    }
+    
+    let n_crossings = n_crossings.min(MAX_POINCARE_CROSSINGS) as usize;
+    let mut crossings = Vec::with_capacity(n_crossings);
+    let mut previous = (sim.bodies[index].position, sim.bodies[index].velocity);
+    let mut steps = 0;
+    
+    while crossings.len() < n_crossings && steps < MAX_POINCARE_STEPS {
+        sim.step();
+        sim.pending_events.clear();
+        steps += 1;
+        
+        if sim.bodies.get(index).map(|b| b.id) != Some(body_id) {
+            match sim.body_index(body_id) {
+                Some(i) => index = i,
+                None => break,
+            }
+        }
+        
+        let (p0, v0) = previous;
+        let (p1, v1) = (sim.bodies[index].position, sim.bodies[index].velocity);
+        if p0.y < section_y && p1.y >= section_y && v1.y > 0.0 {
+            // Interpolate to the section instead of taking the first point past it
+            let t = (section_y - p0.y) / (p1.y - p0.y);
+            crossings.push((p0.x + (p1.x - p0.x) * t, v0.x + (v1.x - v0.x) * t));
+        }
+        previous = (p1, v1);
+        
+        if steps % PROGRESS_EVENT_INTERVAL == 0 {
+            if operation.cancelled() {
+                break;
+            }
+            let _ = window.emit("operation-progress", OperationProgress {
+                operation: "compute_poincare_section",
+                progress: crossings.len() as f64 / n_crossings as f64,
+            });
+        }
+    }
+    
+    Ok(crossings)
+}
+
+// Steps a scratch copy next to one where the body starts LYAPUNOV_PERTURBATION further along x, until
+// the body's separation between the two first doubles. lambda = ln(d / d0) / t and the Lyapunov time is
+// 1 / lambda, infinite when the separation never doubles within n_steps.
+#[tauri::command(async)]
+pub fn compute_lyapunov_time(window: tauri::Window, state: tauri::State<'_, AppState>, body_id: u32, n_steps: u32) -> Result<f64, String> {
+    crate::metrics::begin("compute_lyapunov_time");
+    let operation = BusyOperation::begin()?;
+    let mut sim = state.simulation().scratch_copy();
+    sim.lyapunov_time(body_id, n_steps, |progress| {
+        if operation.cancelled() {
+            return false;
+        }
+        let _ = window.emit("operation-progress", OperationProgress { operation: "compute_lyapunov_time", progress });
+        true
+    })
+}
+
+// The TRANSFER_WINDOWS cheapest departure times within window_duration from now as (elapsed time, delta-v),
+// cheapest first. The transfer is sampled every hundredth of the departure orbit's period on a scratch
+// copy, and a window is a sample no more expensive than the ones around it.
+#[tauri::command(async)]
+pub fn compute_transfer_window(window: tauri::Window, state: tauri::State<'_, AppState>, from_body_id: u32, to_body_id: u32,
+                               window_duration: f64) -> Result<Vec<(f64, f64)>, String> {
+    crate::metrics::begin("compute_transfer_window");
+    if !window_duration.is_finite() || window_duration <= 0.0 {
+        return Err(String::from("window_duration must be a positive number"));
+    }
+    let operation = BusyOperation::begin()?;
+    let mut sim = state.simulation().scratch_copy();
+    
+    let first = sim.minimum_energy_orbit_transfer(from_body_id, to_body_id)?;
+    let interval = first.departure_period / TRANSFER_SAMPLES_PER_PERIOD;
+    let end_time = sim.elapsed_time + window_duration;
+    let mut samples = vec![(sim.elapsed_time, first.delta_v)];
+    let mut next_sample = sim.elapsed_time + interval;
+    
+    for step in 1..=MAX_PREDICTION_STEPS as u64 {
+        if sim.elapsed_time >= end_time {
+            break;
+        }
+        sim.step();
+        sim.pending_events.clear();
+        
+        if sim.elapsed_time >= next_sample {
+            // Either body can be lost in a collision along the way
+            let Ok(transfer) = sim.minimum_energy_orbit_transfer(from_body_id, to_body_id) else { break };
+            samples.push((sim.elapsed_time, transfer.delta_v));
+            next_sample = (next_sample + interval).max(sim.elapsed_time);
+        }
+        
+        if step.is_multiple_of(PROGRESS_EVENT_INTERVAL) {
+            if operation.cancelled() {
+                return Err(String::from("cancelled"));
+            }
+            let _ = window.emit("operation-progress", OperationProgress {
+                operation: "compute_transfer_window",
+                progress: (1.0 - (end_time - sim.elapsed_time) / window_duration).clamp(0.0, 1.0),
+            });
+        }
+    }
+    
+    let delta_v_at = |i: Option<usize>| i.and_then(|i| samples.get(i)).map_or(f64::INFINITY, |s| s.1);
+    let mut windows: Vec<(f64, f64)> = samples.iter()
+        .enumerate()
+        .filter(|(i, (_, delta_v))| *delta_v <= delta_v_at(i.checked_sub(1)) && *delta_v < delta_v_at(Some(i + 1)))
+        .map(|(_, sample)| *sample)
+        .collect();
+    windows.sort_by(|a, b| a.1.total_cmp(&b.1));
+    windows.truncate(TRANSFER_WINDOWS);
+    Ok(windows)
+}
+
+// Runs trials scattering trials of incoming_id against the rest of the scene, see
+// SimulationState::scattering_survey. The live simulation is not changed.
+#[tauri::command(async)]
+pub fn scattering_survey(window: tauri::Window, state: tauri::State<'_, AppState>, incoming_id: u32, trials: u32, steps: u32,
+                         impact_spread: f64, seed: u64) -> Result<ScatteringSummary, String> {
+    crate::metrics::begin("scattering_survey");
+    let operation = BusyOperation::begin()?;
+    let scene = state.simulation().scratch_copy();
+    scene.scattering_survey(incoming_id, trials, steps, impact_spread, seed, |progress| {
+        if operation.cancelled() {
+            return false;
+        }
+        let _ = window.emit("operation-progress", OperationProgress { operation: "scattering_survey", progress });
+        true
+    })
+}
+
+// Where a body, or every body for None, goes over the next `steps` steps (at most MAX_PREDICTION_STEPS),
+// sampled every `stride` steps after the current position. Runs on a copy taken under the lock, so the
+// live state is left alone and keeps stepping meanwhile. Collisions follow the current collision mode, a
+// body that merges away stops where it was last sampled.
+#[tauri::command(async)]
+pub fn predict_trajectory(state: tauri::State<'_, AppState>, body_id: Option<u32>, steps: u32, stride: u32) -> Result<HashMap<String, Vec<Vec2>>, String> {
+    crate::metrics::begin("predict_trajectory");
+    let copy = {
+        let sim = state.simulation();
+        if let Some(id) = body_id {
+            sim.body_index(id).ok_or(format!("no body with id {}", id))?;
+        }
+        sim.scratch_copy()
+    };
+    
+    Ok(copy.predict_paths(steps, stride)
+        .into_iter()
+        .filter(|(id, _)| body_id.is_none_or(|wanted| wanted == *id))
+        .map(|(id, path)| (id.to_string(), path))
+        .collect())
+}
+
+#[tauri::command]
+pub fn export_orbits_geojson(state: tauri::State<'_, AppState>, steps: u32, sample_every: u32) -> String {
+    state.simulation().orbits_geojson(steps, sample_every)
+}
+
+#[tauri::command]
+pub fn set_time_step(state: tauri::State<'_, AppState>, dt: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.set_time_step(dt)?;
+    // A manually chosen time step replaces the automatic one
+    sim.auto_courant = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_integrator(state: tauri::State<'_, AppState>, integrator: Integrator) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.integrator = integrator;
+    Ok(())
+}
+
+// Same as set_integrator, with the method given by name
+#[tauri::command]
+pub fn set_integration_method(state: tauri::State<'_, AppState>, method: String) -> Result<(), CommandError> {
+    let integrator = Integrator::from_name(&method)?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.integrator = integrator;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_collision_mode(state: tauri::State<'_, AppState>, mode: CollisionMode) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.collision_mode = mode;
+    Ok(())
+}
+
+// None removes the boundary, setting or clearing it also forgets the body that last triggered it
+#[tauri::command]
+pub fn set_auto_pause_region(state: tauri::State<'_, AppState>, region: Option<(Vec2, Vec2)>) -> Result<(), CommandError> {
+    if let Some((corner1, corner2)) = region {
+        let finite = [corner1.x, corner1.y, corner2.x, corner2.y].iter().all(|v| v.is_finite());
+        if !finite {
+            return Err(CommandError::message("auto pause region corners must be finite"));
+        }
+    }
+    
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.auto_pause_region = region;
+    sim.auto_pause_body = None;
+    Ok(())
+}
+
+fn validate_courant(courant: f64) -> Result<(), String> {
+    if courant.is_finite() && courant > 0.0 {
+        Ok(())
+    } else {
+        Err(String::from("courant number must be a positive number"))
+    }
+}
+
+#[tauri::command]
+pub fn set_drag_coefficient(state: tauri::State<'_, AppState>, body_id: u32, damping: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_drag_coefficient(body_id, damping)?)
+}
+
+// Same drag coefficient on every body except the exempt ones, e.g. everything but the star in a disk
+#[tauri::command]
+pub fn set_velocity_damping_except(state: tauri::State<'_, AppState>, exempt_ids: Vec<u32>, damping: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    
+    let ids: Vec<u32> = sim.bodies.iter()
+        .map(|b| b.id)
+        .filter(|id| !exempt_ids.contains(id))
+        .collect();
+    for id in ids {
+        sim.set_drag_coefficient(id, damping)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_radial_drift_rate(state: tauri::State<'_, AppState>, body_id: u32, rate: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_radial_drift_rate(body_id, rate)?)
+}
+
+// None turns tidal locking off
+#[tauri::command]
+pub fn set_tidal_locking(state: tauri::State<'_, AppState>, rate: Option<f64>) -> Result<(), CommandError> {
+    if rate.is_some_and(|r| !r.is_finite() || r < 0.0) {
+        return Err(CommandError::message("rate must be a non-negative number"));
+    }
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    sim.tidal_locking_rate = rate;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn spin_orbit_ratio(state: tauri::State<'_, AppState>, body_id: u32, primary_id: u32) -> Option<f64> {
+    state.simulation().spin_orbit_ratio(body_id, primary_id)
+}
+
+// Adds a disk of the given mass around the center body, returns its id
+#[tauri::command]
+pub fn set_ring_force(state: tauri::State<'_, AppState>, center_id: u32, ring_mass: f64, inner_radius: f64, outer_radius: f64) -> Result<RingId, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.add_ring_force(center_id, ring_mass, inner_radius, outer_radius)?)
+}
+
+#[tauri::command]
+pub fn remove_ring_force(state: tauri::State<'_, AppState>, id: RingId) -> Result<bool, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.remove_ring_force(id))
+}
+
+#[tauri::command]
+pub fn list_ring_forces(state: tauri::State<'_, AppState>) -> Vec<RingForce> {
+    state.simulation().ring_forces.clone()
+}
+
+#[tauri::command]
+pub fn add_orbit_constraint(state: tauri::State<'_, AppState>, body_id: u32, primary_id: u32, target_sma: f64, strength: f64) -> Result<ConstraintId, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.add_orbit_constraint(body_id, primary_id, target_sma, strength)?)
+}
+
+#[tauri::command]
+pub fn update_orbit_constraint(state: tauri::State<'_, AppState>, id: ConstraintId, target_sma: Option<f64>, strength: Option<f64>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.update_orbit_constraint(id, target_sma, strength)?)
+}
+
+#[tauri::command]
+pub fn remove_orbit_constraint(state: tauri::State<'_, AppState>, id: ConstraintId) -> Result<bool, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.remove_orbit_constraint(id))
+}
+
+#[tauri::command]
+pub fn list_orbit_constraints(state: tauri::State<'_, AppState>) -> Vec<OrbitConstraint> {
+    state.simulation().orbit_constraints.clone()
+}
+
+#[tauri::command]
+pub fn set_time_step_from_courant_number(state: tauri::State<'_, AppState>, courant: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    validate_courant(courant)?;
+    
+    sim.time_step = sim.courant_time_step(courant)
+        .ok_or_else(|| String::from("need at least two bodies and one moving body to compute a Courant time step"))?;
+    Ok(())
+}
+
+// Some(courant) retunes the time step periodically while stepping, None turns it off
+#[tauri::command]
+pub fn set_auto_courant(state: tauri::State<'_, AppState>, courant: Option<f64>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    if let Some(courant) = courant {
+        validate_courant(courant)?;
+        if let Some(dt) = sim.courant_time_step(courant) {
+            sim.time_step = dt;
+        }
+    }
+    sim.auto_courant = courant;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_gravity_constant(state: tauri::State<'_, AppState>, g: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_gravity_constant(g)?)
+}
+
+#[tauri::command]
+pub fn set_softening(state: tauri::State<'_, AppState>, epsilon: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_softening(epsilon)?)
+}
+
+#[tauri::command]
+pub fn get_constants(state: tauri::State<'_, AppState>) -> Constants {
+    state.simulation().constants
+}
+
+// Replaces all physical constants at once, they are checked together before any is applied
+#[tauri::command]
+pub fn set_constants(state: tauri::State<'_, AppState>, constants: Constants) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_constants(constants)?)
+}
+
+#[tauri::command]
+pub fn set_pin_mass_ratio(state: tauri::State<'_, AppState>, ratio: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    Ok(sim.set_pin_mass_ratio(ratio)?)
+}
+
+#[tauri::command]
+pub fn lock_simulation_config(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked().map_err(|_| String::from("simulation config is already locked"))?;
+    sim.config_locked = true;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unlock_simulation_config(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.config_locked = false;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn all_orbital_elements(state: tauri::State<'_, AppState>) -> Vec<(u32, Option<OrbitalElements>)> {
+    let sim = state.simulation();
+    sim.all_orbital_elements()
+}
+
+// Writes every trail, COM tracks included, to a file for external renderers, see trail_export
+#[tauri::command(async)]
+pub fn export_trails(window: tauri::Window, state: tauri::State<'_, AppState>, path: String, format: TrailExportFormat, stride: Option<u32>,
+                     tolerance: Option<f64>) -> Result<TrailExportSummary, String> {
+    crate::metrics::begin("export_trails");
+    let options = TrailExportOptions::new(stride, tolerance)?;
+    let operation = BusyOperation::begin()?;
+    let (trails, step_count) = {
+        let sim = state.simulation();
+        (sim.export_trails(), sim.step_count)
+    };
+    
+    crate::trail_export::write(std::path::Path::new(&path), format, &options, &trails, step_count, |progress| {
+        let _ = window.emit("operation-progress", OperationProgress { operation: "export_trails", progress });
+        !operation.cancelled()
+    })
+}
+
+// Trails of the live state, or of a pinned snapshot when a token is given
+#[tauri::command]
+pub fn get_trails(state: tauri::State<'_, AppState>, snapshot: Option<SnapshotToken>) -> Result<HashMap<String, Vec<Vec2>>, String> {
+    state.trails(snapshot)
+}
+
+// Pins a copy of the current state. Release it when the frame is done, it also expires after
+// SNAPSHOT_TIMEOUT without reads.
+#[tauri::command]
+pub fn acquire_snapshot(window: tauri::Window, state: tauri::State<'_, AppState>) -> SnapshotToken {
+    let snapshot = publish_state(&window, &mut state.simulation());
+    state.snapshots().pin(snapshot, Instant::now())
+}
+
+#[tauri::command]
+pub fn get_snapshot_state(state: tauri::State<'_, AppState>, token: SnapshotToken) -> Result<SimulationState, String> {
+    state.snapshots().get(token, Instant::now()).cloned()
+}
+
+#[tauri::command]
+pub fn release_snapshot(state: tauri::State<'_, AppState>, token: SnapshotToken) -> bool {
+    state.snapshots().pinned.remove(&token).is_some()
+}
+
+// Records the center-of-mass path of a group, e.g. a planet and its moons, next to the body trails
+#[tauri::command]
+pub fn track_com(state: tauri::State<'_, AppState>, ids: Vec<u32>) -> Result<TrackId, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.add_com_track(ids)?)
+}
+
+#[tauri::command]
+pub fn untrack_com(state: tauri::State<'_, AppState>, track_id: TrackId) -> Result<bool, CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.remove_com_track(track_id))
+}
+
+#[tauri::command]
+pub fn set_trail_config(state: tauri::State<'_, AppState>, interval: u32, max_points: usize) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    Ok(sim.set_trail_config(interval, max_points)?)
+}
+
+// With spilling on, points evicted from the in-memory trails go to a temp file in the app data dir.
+// Turning it off deletes the file and the points in it. Enabling it again also resumes paused recording.
+#[tauri::command]
+pub fn set_trail_spill(window: tauri::Window, state: tauri::State<'_, AppState>, enabled: bool) -> Result<(), CommandError> {
+    let dir = if enabled {
+        let dir = window.app_handle().path_resolver().app_data_dir()
+            .ok_or_else(|| String::from("no app data directory available"))?;
+        Some(dir)
+    } else {
+        None
+    };
+    
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::View)?;
+    sim.set_trail_spill_dir(dir.as_deref())
+        .map_err(|e| CommandError::Message(format!("could not create trail spill file: {}", e)))
+}
+
+// Keeps the engine ticking while physics is paused: no steps happen here, but this thread evaluates watches
+// and sends heartbeats at idle_tick_rate, delivering queued events to all windows.
+pub fn start_idle_ticker(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let rate = {
+            let state = app.state::<AppState>();
+            let mut sim = state.simulation();
+            sim.idle_tick();
+            if let Some(publication) = sim.take_events() {
+                emit_publication(&app, publication);
+            }
+            sim.idle_tick_rate
+        };
+        std::thread::sleep(Duration::from_secs_f64(1.0 / rate));
+    });
+}
+
+#[tauri::command]
+pub fn set_idle_tick_rate(state: tauri::State<'_, AppState>, rate: f64) -> Result<(), CommandError> {
+    validate_idle_tick_rate(rate)?;
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.idle_tick_rate = rate;
+    Ok(())
+}
+
+// Called when the app exits so the spill file doesn't outlive it
+pub fn shutdown_trail_spill(app: &tauri::AppHandle) {
+    app.state::<AppState>().simulation().trail_spill = None;
+}
+
+#[tauri::command]
+pub fn compute_action_angle_variables(state: tauri::State<'_, AppState>, body_id: u32, primary_id: u32) -> Result<ActionAngleVars, String> {
+    let sim = state.simulation();
+    sim.action_angle_variables(body_id, primary_id)
+}
+
+// A central_drag of 0 turns the drag off
+#[tauri::command]
+pub fn set_radial_drag(state: tauri::State<'_, AppState>, central_drag: f64, scale_radius: f64) -> Result<(), CommandError> {
+    let mut sim = state.simulation();
+    sim.check_allowed(CommandCategory::Config)?;
+    sim.ensure_unlocked()?;
+    
+    if !central_drag.is_finite() || central_drag < 0.0 {
+        return Err(CommandError::message("central_drag must be a non-negative number"));
+    }
+    if !scale_radius.is_finite() || scale_radius <= 0.0 {
+        return Err(CommandError::message("scale_radius must be a positive number"));
+    }
+    
+    sim.radial_drag = if central_drag > 0.0 {
+        Some(RadialDrag { central_drag, scale_radius })
+    } else {
+        None
+    };
+    Ok(())
+}
+
+#[tauri::command]
+pub fn normalized_potentials(state: tauri::State<'_, AppState>) -> Vec<(u32, f64)> {
+    state.simulation().normalized_potentials()
+}
+
+#[tauri::command]
+pub fn get_system_energy(state: tauri::State<'_, AppState>) -> EnergyReport {
+    state.simulation().energy_report()
+}
+
+#[tauri::command]
+pub fn get_momentum(state: tauri::State<'_, AppState>) -> MomentumReport {
+    state.simulation().momentum_report()
+}
+
+#[tauri::command]
+pub fn get_diagnostics(state: tauri::State<'_, AppState>) -> Diagnostics {
+    state.simulation().diagnostics()
+}
+
+#[tauri::command]
+pub fn energy_report_csv(state: tauri::State<'_, AppState>, steps: u32, sample_every: u32) -> String {
+    state.simulation().energy_report_csv(steps, sample_every)
+}
+
+#[tauri::command]
+pub fn roche_lobe_occupants(state: tauri::State<'_, AppState>, primary1_id: u32, primary2_id: u32) -> Vec<(u32, u32)> {
+    state.simulation().roche_lobe_occupants(primary1_id, primary2_id)
+}
+
+#[tauri::command]
+pub fn estimate_n_body_chaos_threshold(state: tauri::State<'_, AppState>, primary_id: u32) -> Result<f64, String> {
+    state.simulation().chaos_threshold_ratio(primary_id)
+}
+
+#[tauri::command]
+pub fn query_region(state: tauri::State<'_, AppState>, shape: RegionShape, options: Option<RegionQueryOptions>) -> Result<RegionQuery, String> {
+    let values = match shape {
+        RegionShape::Rectangle { corner1, corner2 } => vec![corner1.x, corner1.y, corner2.x, corner2.y],
+        RegionShape::Circle { center, radius } => {
+            if radius < 0.0 {
+                return Err(String::from("circle radius must not be negative"));
+            }
+            vec![center.x, center.y, radius]
+        }
+    };
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(String::from("region coordinates must be finite"));
+    }
+    
+    state.simulation().query_region(&shape, &options.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_simulation_checksum(state: tauri::State<'_, AppState>) -> u64 {
+    state.simulation().checksum()
+}
+
+#[tauri::command]
+pub fn circularization_guidance(state: tauri::State<'_, AppState>, primary_id: u32) -> Result<Vec<(u32, Vec2)>, String> {
+    state.simulation().circularization_guidance(primary_id)
+}
+
+#[tauri::command]
+pub fn get_binding_report(state: tauri::State<'_, AppState>, body_id: u32) -> Result<BindingReport, String> {
+    let sim = state.simulation();
+    sim.binding_report(body_id)
+}
+
+#[tauri::command]
+pub fn compute_minimum_encounter_distance(state: tauri::State<'_, AppState>, body1_id: u32, body2_id: u32, n_steps: u32) -> Result<EncounterPrediction, String> {
+    let sim = state.simulation();
+    sim.minimum_encounter(body1_id, body2_id, n_steps)
+}
+
+#[tauri::command]
+pub fn two_body_masses(state: tauri::State<'_, AppState>, a: u32, b: u32) -> Option<(f64, f64, f64)> {
+    state.simulation().two_body_masses(a, b)
+}
+
+#[tauri::command]
+pub fn predict_collision_outcome(state: tauri::State<'_, AppState>, a: u32, b: u32) -> Option<String> {
+    let sim = state.simulation();
+    sim.predicted_collision_outcome(a, b).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn disk_with_gap_leaves_the_gap_empty_and_orbits_prograde() {
+        let disk = DiskWithGap { center_mass: 1.0e4, inner_r: 100.0, outer_r: 500.0, gap_center: 300.0, gap_width: 60.0, count: 400, seed: 7 };
+        let sim = SimulationState::disk_with_gap(&disk, DEFAULT_GRAVITY_CONSTANT).unwrap();
+        
+        assert_eq!(sim.bodies.len(), 401);
+        for body in &sim.bodies[1..] {
+            let r = body.position.length();
+            assert!((100.0..=500.0).contains(&r));
+            assert!((r - 300.0).abs() >= 30.0, "body {} at r = {} is inside the gap", body.id, r);
+            let angular_momentum = body.position.x * body.velocity.y - body.position.y * body.velocity.x;
+            assert!(angular_momentum > 0.0, "body {} orbits retrograde", body.id);
+        }
+    }
+    
+    #[test]
+    fn removed_body_ids_are_not_handed_out_again() {
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        let first = sim.add_body(1.0, Vec2::new(4.0e3, 0.0), Vec2::new(0.0, 0.0), 1.0, String::from("#ffffff")).unwrap();
+        sim.step();
+        assert!(sim.remove_body(first));
+        assert!(!sim.remove_body(first));
+        
+        let second = sim.add_body(1.0, Vec2::new(4.0e3, 0.0), Vec2::new(0.0, 0.0), 1.0, String::from("#ffffff")).unwrap();
+        assert_ne!(first, second);
+        sim.step();
+        
+        sim.remove_body(second);
+        let mut loaded = SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).unwrap();
+        let third = loaded.add_body(1.0, Vec2::new(4.0e3, 0.0), Vec2::new(0.0, 0.0), 1.0, String::from("#ffffff")).unwrap();
+        assert!(third > second);
+    }
+    
+    #[test]
+    fn burn_adds_acceleration_times_steps_times_dt() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.is_running = true;
+        sim.start_burn(1, Vec2::new(0.0, 2.0), 3.0, 40).unwrap();
+        for _ in 0..100 {
+            sim.step();
+        }
+        
+        let expected = 3.0 * 40.0 * sim.time_step;
+        assert!((sim.bodies[0].velocity.y - expected).abs() < 1e-12);
+        assert!(sim.bodies[0].velocity.x.abs() < 1e-12);
+        assert!(sim.burns.is_empty());
+        assert!(sim.start_burn(2, Vec2::new(1.0, 0.0), 1.0, 10).is_err());
+    }
+    
+    #[test]
+    fn scenario_with_a_finished_burn_is_rejected() {
+        let mut sim = SimulationState::new();
+        sim.burns.insert(1, Burn { acceleration: Vec2::new(1.0, 0.0), remaining_steps: 0 });
+        let error = SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).err().unwrap();
+        assert!(error.contains("no steps left"));
+    }
+    
+    #[test]
+    fn scenario_idle_tick_rate_is_range_checked() {
+        let mut sim = SimulationState::new();
+        for rate in [0.0, -1.0, MAX_IDLE_TICK_RATE * 2.0] {
+            sim.idle_tick_rate = rate;
+            assert!(SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).is_err());
+        }
+        sim.idle_tick_rate = MAX_IDLE_TICK_RATE;
+        assert!(SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).is_ok());
+    }
+    
+    #[test]
+    fn scenario_emit_rate_is_range_checked() {
+        let mut sim = SimulationState::new();
+        for rate in [0.0, -30.0, PHYSICS_TICK_RATE + 1.0] {
+            sim.emit_rate_hz = rate;
+            assert!(SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).is_err());
+        }
+        sim.emit_rate_hz = PHYSICS_TICK_RATE;
+        assert!(SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).is_ok());
+    }
+    
+    #[test]
+    fn energy_report_matches_the_softened_force_law() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(300.0, 400.0), Vec2::new(0.0, 0.0), 2.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.constants.softening = 2.0;
+        
+        let report = sim.energy_report();
+        let expected = -DEFAULT_GRAVITY_CONSTANT * 1.0e3 / (500.0_f64.powi(2) + 4.0).sqrt();
+        assert!((report.potential - expected).abs() < 1e-12);
+        assert_eq!(report.kinetic, 0.0);
+        assert_eq!(sim.diagnostics().potential_energy, report.potential);
+        
+        sim.add_ring_force(1, 100.0, 50.0, 80.0).unwrap();
+        assert!(sim.energy_report().potential < report.potential);
+        assert_eq!(sim.diagnostics().potential_energy, sim.energy_report().potential);
+    }
+    
+    #[test]
+    fn time_step_and_gravity_constant_are_validated() {
+        let mut sim = SimulationState::new();
+        for dt in [0.0, -0.01, f64::NAN, f64::INFINITY] {
+            assert!(sim.set_time_step(dt).is_err());
+        }
+        assert_eq!(sim.time_step, 0.01);
+        sim.set_time_step(100.0).unwrap();
+        assert_eq!(sim.time_step, MAX_TIME_STEP);
+        
+        for multiplier in [0.0, -2.0, f64::NAN, f64::INFINITY, 2.0 * MAX_TIME_MULTIPLIER] {
+            assert!(sim.set_time_multiplier(multiplier).is_err());
+        }
+        assert_eq!(sim.time_multiplier, 1.0);
+        // Allowed on its own, but the step it makes with the largest time step is clamped
+        sim.set_time_multiplier(MAX_TIME_MULTIPLIER).unwrap();
+        sim.is_running = true;
+        sim.step();
+        assert_eq!(sim.elapsed_time, MAX_TIME_STEP);
+        
+        for g in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(sim.set_gravity_constant(g).is_err());
+        }
+        sim.set_gravity_constant(2.0).unwrap();
+        assert_eq!(sim.constants.gravity_constant, 2.0);
+        // The same rule as for loaded constants, 0 switches gravity off
+        sim.set_gravity_constant(0.0).unwrap();
+        assert!(sim.constants.validate().is_ok());
+    }
+    
+    #[test]
+    fn every_time_step_source_is_clamped() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+            Body::new(2, 1.0, Vec2::new(1.0e6, 0.0), Vec2::new(1e-3, 0.0), 1.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        assert_eq!(sim.courant_time_step(0.5), Some(MAX_TIME_STEP));
+        
+        sim.time_step = 50.0;
+        let loaded = SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).unwrap();
+        assert_eq!(loaded.time_step, MAX_TIME_STEP);
+        sim.time_step = 0.0;
+        assert!(SimulationState::from_scenario_json(&sim.to_scenario_json().unwrap()).is_err());
+    }
+    
+    #[test]
+    fn collapsing_pair_releases_potential_energy() {
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e3, Vec2::new(-200.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffcc00"),
+            Body::new(2, 1.0e3, Vec2::new(200.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        let before = sim.bodies[0].position.distance(&sim.bodies[1].position);
+        let state = AppState::new(sim);
+        
+        let change = release_potential(&state, 20.0, |_| true).unwrap();
+        let sim = state.simulation();
+        assert!(change < 0.0);
+        assert!(sim.bodies[0].position.distance(&sim.bodies[1].position) < before);
+        assert!(!sim.is_running);
+        assert!((sim.elapsed_time - 20.0).abs() < sim.time_step);
+    }
+    
+    #[test]
+    fn snapshots_expire_after_the_timeout_without_reads() {
+        let mut store = SnapshotStore::default();
+        let start = Instant::now();
+        let first = store.pin(SimulationState::new(), start);
+        let second = store.pin(SimulationState::new(), start);
+        
+        // A read just before the deadline extends it by another timeout
+        assert!(store.get(first, start + SNAPSHOT_TIMEOUT - Duration::from_millis(1)).is_ok());
+        assert!(store.get(second, start + SNAPSHOT_TIMEOUT).is_err());
+        assert!(store.get(first, start + 2 * SNAPSHOT_TIMEOUT - Duration::from_millis(2)).is_ok());
+        
+        // Pinning sweeps the expired ones even if they are never read again
+        let third = store.pin(SimulationState::new(), start + 4 * SNAPSHOT_TIMEOUT);
+        assert_eq!(store.pinned.keys().collect::<Vec<_>>(), [&third]);
+    }
+    
+    #[test]
+    fn pinned_trails_stay_put_while_the_state_advances() {
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        for _ in 0..20 {
+            sim.step();
+        }
+        let state = AppState::new(sim);
+        let token = state.snapshots().pin(state.simulation().clone(), Instant::now());
+        let trails = |snapshot: Option<SnapshotToken>| -> std::collections::BTreeMap<String, Vec<(f64, f64)>> {
+            state.trails(snapshot).unwrap().into_iter()
+                .map(|(key, points)| (key, points.iter().map(|p| (p.x, p.y)).collect()))
+                .collect()
+        };
+        let pinned = trails(None);
+        
+        for _ in 0..20 {
+            state.simulation().step();
+        }
+        assert_eq!(trails(Some(token)), pinned);
+        assert_ne!(trails(None), pinned);
+        assert!(state.snapshots().pinned.remove(&token).is_some());
+        assert!(state.trails(Some(token)).is_err());
+    }
+    
+    #[test]
+    fn set_total_energy_reaches_the_target_with_a_ring() {
+        let mut sim = SimulationState::new();
+        sim.add_ring_force(1, 500.0, 900.0, 1200.0).unwrap();
+        let target = sim.energy_report().total * 0.8;
+        sim.set_total_energy(target).unwrap();
+        
+        let total = sim.diagnostics().total_energy;
+        assert!((total - target).abs() <= 1e-9 * target.abs(), "total {} instead of {}", total, target);
+        assert!(sim.set_total_energy(sim.potential_energy() * 2.0).is_err());
+    }
+    
+    #[test]
+    fn lyapunov_time_follows_the_body_through_merges() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e4, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 20.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(60.0, 0.0), Vec2::new(-40.0, 0.0), 5.0, "#ffffff"),
+            Body::new(3, 1.0, Vec2::new(0.0, 800.0), Vec2::new(-(DEFAULT_GRAVITY_CONSTANT * 1.0e4 / 800.0).sqrt(), 0.0), 5.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.collision_mode = CollisionMode::Merge;
+        
+        let target = sim.scratch_copy().lyapunov_time(3, 2_000, |_| true);
+        assert!(target.is_ok());
+        let absorbed = sim.scratch_copy().lyapunov_time(2, 2_000, |_| true);
+        assert!(absorbed.unwrap_err().contains("removed"));
+    }
+    
+    #[test]
+    fn collisions_and_dedupe_merge_the_same_way() {
+        let bodies = vec![
+            Body::new(3, 1.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 3.0, "#ff0000"),
+            Body::new(1, 2.0, Vec2::new(2.0, 0.0), Vec2::new(-40.0, 0.0), 4.0, "#00ff00"),
+            Body::new(2, 1.0, Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.0), 12.0, "#0000ff"),
+        ];
+        let mut colliding = SimulationState::from_bodies(bodies.clone(), 0.0);
+        colliding.collision_mode = CollisionMode::Merge;
+        colliding.merge_overlapping();
+        let mut deduped = SimulationState::from_bodies(bodies, 0.0);
+        deduped.dedupe_coincident(10.0).unwrap();
+        
+        for sim in [&colliding, &deduped] {
+            assert_eq!(sim.bodies.len(), 1);
+            let merged = &sim.bodies[0];
+            assert_eq!(merged.id, 1);
+            assert_eq!(merged.color, "#00ff00");
+            assert!((merged.mass - 4.0).abs() < 1e-12);
+            assert!((merged.radius - 13.0).abs() < 1e-12);
+            assert!((merged.velocity.x + 20.0).abs() < 1e-12);
+            assert!(!merged.scars.is_empty());
+        }
+    }
+    
+    #[test]
+    fn duplicate_ids_are_renumbered_with_their_settings() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(300.0, 0.0), Vec2::new(0.0, 1.0), 2.0, "#ffffff"),
+            Body::new(3, 1.0, Vec2::new(-300.0, 0.0), Vec2::new(0.0, -1.0), 2.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.start_burn(3, Vec2::new(1.0, 0.0), 0.5, 20).unwrap();
+        sim.set_drag_coefficient(3, 0.1).unwrap();
+        // Body 3 saved under the id of body 2, the way older builds could hand out ids
+        let mut scenario: serde_json::Value = serde_json::from_str(&sim.to_scenario_json().unwrap()).unwrap();
+        scenario["bodies"][2]["id"] = 2.into();
+        for settings in ["burns", "drag_coefficients"] {
+            let setting = scenario[settings].as_object_mut().unwrap().remove("3").unwrap();
+            scenario[settings]["2"] = setting;
+        }
+        
+        let mut loaded = SimulationState::from_scenario_json(&scenario.to_string()).unwrap();
+        let warnings = loaded.repair_duplicate_ids();
+        assert_eq!(warnings.len(), 1);
+        let ids: HashSet<u32> = loaded.bodies.iter().map(|b| b.id).collect();
+        assert_eq!(ids.len(), 3);
+        let new_id = loaded.bodies[2].id;
+        assert!(new_id > 2);
+        assert!(loaded.burns.contains_key(&2) && loaded.burns.contains_key(&new_id));
+        assert_eq!(loaded.drag_coefficients.get(&new_id), Some(&0.1));
+        assert_eq!(loaded.next_id(), new_id + 1);
+    }
+    
+    #[test]
+    fn exported_trails_include_the_spilled_points() {
+        let dir = std::env::temp_dir().join(format!("trail-export-test-{}", std::process::id()));
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        sim.set_trail_config(1, 40).unwrap();
+        sim.set_trail_spill_dir(Some(&dir)).unwrap();
+        for _ in 0..300 {
+            sim.step();
+        }
+        let expected: HashMap<String, usize> = sim.trail_points().unwrap().into_iter().map(|(key, points)| (key, points.len())).collect();
+        // More points than the trails keep in memory, so the body trails went through the spill file
+        let body_counts: Vec<usize> = expected.iter().filter(|(key, _)| key.parse::<u32>().is_ok()).map(|(_, count)| *count).collect();
+        assert_eq!(body_counts.len(), sim.bodies.len());
+        assert!(body_counts.iter().all(|count| *count > 40));
+        
+        let path = dir.join("trails.json");
+        let options = TrailExportOptions::new(None, None).unwrap();
+        let summary = crate::trail_export::write(&path, TrailExportFormat::Json, &options, &sim.export_trails(), sim.step_count, |_| true).unwrap();
+        let exported: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        sim.set_trail_spill_dir(None).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        let trails = exported["trails"].as_array().unwrap();
+        assert_eq!(trails.len(), expected.len());
+        for trail in trails {
+            let key = trail["key"].as_str().unwrap();
+            assert_eq!(trail["points"].as_array().unwrap().len(), expected[key], "trail {}", key);
+        }
+        assert_eq!(summary.points, expected.values().sum::<usize>());
+    }
+    
+    #[test]
+    fn shrinking_the_trails_spills_the_trimmed_points() {
+        let dir = std::env::temp_dir().join(format!("trail-shrink-test-{}", std::process::id()));
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        sim.set_trail_config(1, 200).unwrap();
+        sim.set_trail_spill_dir(Some(&dir)).unwrap();
+        for _ in 0..100 {
+            sim.step();
+        }
+        let before = sim.trail_points().unwrap();
+        
+        sim.set_trail_config(1, 10).unwrap();
+        let after = sim.trail_points().unwrap();
+        sim.set_trail_spill_dir(None).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        for body in &sim.bodies {
+            let key = body.id.to_string();
+            assert_eq!(sim.trails[&body.id].len(), 10);
+            assert_eq!(before[&key].len(), 100);
+            assert_eq!(after[&key].len(), 100);
+        }
+    }
+    
+    #[test]
+    fn tidal_locking_brings_a_fast_spin_down_to_the_orbital_rate() {
+        let (star_mass, distance) = (1.0e4, 400.0);
+        let speed = (DEFAULT_GRAVITY_CONSTANT * star_mass / distance).sqrt();
+        let mut planet = Body::new(2, 1.0, Vec2::new(distance, 0.0), Vec2::new(0.0, speed), 5.0, "#3366ff");
+        planet.spin = 20.0 * speed / distance;
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, star_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 20.0, "#ffcc00"),
+            planet,
+        ], DEFAULT_GRAVITY_CONSTANT);
+        let mut unlocked = sim.clone();
+        sim.tidal_locking_rate = Some(1.0);
+        sim.is_running = true;
+        unlocked.is_running = true;
+        for _ in 0..2000 {
+            sim.step();
+            unlocked.step();
+        }
+        
+        assert!((sim.spin_orbit_ratio(2, 1).unwrap() - 1.0).abs() < 0.01);
+        assert!((unlocked.spin_orbit_ratio(2, 1).unwrap() - 20.0).abs() < 0.5);
+    }
+    
+    #[test]
+    fn ring_forces_cancel_for_a_symmetric_layout() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e4, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(150.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+            Body::new(3, 1.0, Vec2::new(-150.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.add_ring_force(1, 500.0, 100.0, 200.0).unwrap();
+        let positions: Vec<Vec2> = sim.bodies.iter().map(|b| b.position).collect();
+        let mut forces = vec![Vec2::new(0.0, 0.0); 3];
+        sim.add_ring_forces(&positions, &mut forces);
+        
+        assert!(forces[1].length() > 0.0);
+        assert!((forces[1] + forces[2]).length() < 1e-9 * forces[1].length());
+        assert!(forces[1].y.abs() < 1e-9 * forces[1].length());
+        assert!(forces[0].length() < 1e-9 * forces[1].length());
+    }
+    
+    #[test]
+    fn vec2_operators_work_componentwise() {
+        let (a, b) = (Vec2::new(3.0, 4.0), Vec2::new(-1.0, 2.0));
+        let same = |u: Vec2, v: Vec2| (u - v).length() < 1e-12;
+        
+        assert!(same(a + b, Vec2::new(2.0, 6.0)));
+        assert!(same(a - b, Vec2::new(4.0, 2.0)));
+        assert!(same(a * 2.0, Vec2::new(6.0, 8.0)));
+        assert!(same(a / 2.0, Vec2::new(1.5, 2.0)));
+        assert!(same(-a, Vec2::new(-3.0, -4.0)));
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert!(same(c, b));
+        
+        assert_eq!(a.dot(b), 5.0);
+        assert_eq!(a.length_squared(), 25.0);
+        assert_eq!(a.length(), 5.0);
+        assert_eq!(a.distance(&b), 20.0_f64.sqrt());
+        assert!(same(a.normalized(), Vec2::new(0.6, 0.8)));
+        assert!(same(a.perp(), Vec2::new(-4.0, 3.0)));
+        assert_eq!(a.perp().dot(a), 0.0);
+        assert!(same(a.lerp(b, 0.0), a) && same(a.lerp(b, 1.0), b));
+        assert!(same(a.lerp(b, 0.5), Vec2::new(1.0, 3.0)));
+        assert!(same(a.rotate(std::f64::consts::FRAC_PI_2), a.perp()));
+        assert!(same(a.rotate(2.0 * std::f64::consts::PI), a));
+    }
+    
+    #[test]
+    fn action_angle_anomalies_agree_with_each_other() {
+        let star_mass = 1.0e4;
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, star_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 20.0, "#ffcc00"),
+            Body::new(2, 1.0e-6, Vec2::new(300.0, 0.0), Vec2::new(0.0, 4.0), 2.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.is_running = true;
+        
+        for _ in 0..7 {
+            for _ in 0..4000 {
+                sim.step();
+            }
+            let vars = sim.action_angle_variables(2, 1).unwrap();
+            let e = sim.relative_orbit(&sim.bodies[1], &sim.bodies[0]).elements().unwrap().eccentricity;
+            let (big_e, nu) = (vars.eccentric_anomaly, vars.true_anomaly);
+            assert!((big_e - e * big_e.sin() - vars.mean_anomaly).abs() < 1e-9);
+            let nu_from_e = 2.0 * ((1.0 + e).sqrt() * (big_e / 2.0).sin()).atan2((1.0 - e).sqrt() * (big_e / 2.0).cos());
+            let difference = (nu_from_e - nu).rem_euclid(2.0 * std::f64::consts::PI);
+            assert!(difference.min(2.0 * std::f64::consts::PI - difference) < 1e-6, "E = {}, true anomaly = {}", big_e, nu);
+        }
+    }
+    
+    #[test]
+    fn a_body_next_to_a_primary_sits_in_its_roche_lobe() {
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, 4.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1.0e3, Vec2::new(400.0, 0.0), Vec2::new(0.0, 0.0), 8.0, "#ff6633"),
+            Body::new(3, 1.0, Vec2::new(380.0, 10.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+            Body::new(4, 1.0, Vec2::new(-30.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+            Body::new(5, 1.0, Vec2::new(0.0, 2000.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        
+        assert_eq!(sim.roche_lobe_occupants(1, 2), vec![(3, 2), (4, 1)]);
+        assert!(sim.roche_lobe_occupants(1, 1).is_empty());
+        assert!(sim.roche_lobe_occupants(1, 99).is_empty());
+    }
+    
+    #[test]
+    fn chaos_threshold_separates_wide_and_packed_planets() {
+        let planets = |spacing: f64| {
+            let sun_mass = 8.0e3;
+            let mut bodies = vec![Body::new(1, sun_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 25.0, "#ffcc00")];
+            for i in 0..4 {
+                let distance = 120.0 * spacing.powi(i);
+                let speed = (DEFAULT_GRAVITY_CONSTANT * sun_mass / distance).sqrt();
+                bodies.push(Body::new(i as u32 + 2, 1.0, Vec2::new(distance, 0.0), Vec2::new(0.0, speed), 2.0, "#ffffff"));
+            }
+            SimulationState::from_bodies(bodies, DEFAULT_GRAVITY_CONSTANT)
+        };
+        
+        assert!(planets(1.6).chaos_threshold_ratio(1).unwrap() > 1.0);
+        assert!(planets(1.02).chaos_threshold_ratio(1).unwrap() < 1.0);
+        // The default planets weigh up to 3/8 of the sun, far past what the criterion calls stable
+        assert!(SimulationState::new().chaos_threshold_ratio(1).unwrap() < 1.0);
+        assert!(planets(1.6).chaos_threshold_ratio(2).is_err());
+    }
+    
+    #[test]
+    fn region_queries_count_discs_that_straddle_or_touch_the_edge() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e4, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            // Center outside the right edge at x = 100, disc reaching over it
+            Body::new(2, 10.0, Vec2::new(104.0, 50.0), Vec2::new(0.0, 0.0), 5.0, "#ff9999"),
+            // Disc touching the top edge
+            Body::new(3, 10.0, Vec2::new(50.0, 105.0), Vec2::new(0.0, 0.0), 5.0, "#3366ff"),
+            // Within the radius of both edges past the corner, but not of the corner itself
+            Body::new(4, 10.0, Vec2::new(104.0, 104.0), Vec2::new(0.0, 0.0), 5.0, "#33ff66"),
+            Body::new(5, 1e-6, Vec2::new(20.0, 20.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        let rectangle = RegionShape::Rectangle { corner1: Vec2::new(100.0, 100.0), corner2: Vec2::new(-100.0, -100.0) };
+        let ids = |sim: &SimulationState, shape: &RegionShape, options: &RegionQueryOptions| sim.query_region(shape, options).unwrap().body_ids;
+        
+        let query = sim.query_region(&rectangle, &RegionQueryOptions::default()).unwrap();
+        assert_eq!(query.body_ids, [1, 2, 3]);
+        assert_eq!(query.step_count, sim.step_count);
+        let with_tracers = RegionQueryOptions { include_tracers: true, ..RegionQueryOptions::default() };
+        assert_eq!(ids(&sim, &rectangle, &with_tracers), [1, 2, 3, 5]);
+        
+        // Body 2 is 96 from the center, its disc reaches to 91
+        let straddled = RegionShape::Circle { center: Vec2::new(200.0, 50.0), radius: 92.0 };
+        assert_eq!(ids(&sim, &straddled, &with_tracers), [2]);
+        let missed = RegionShape::Circle { center: Vec2::new(200.0, 50.0), radius: 90.5 };
+        assert!(ids(&sim, &missed, &with_tracers).is_empty());
+        
+        let group = sim.add_com_track(vec![1, 2]).unwrap();
+        let without_group = RegionQueryOptions { exclude_groups: vec![group], ..with_tracers };
+        assert_eq!(ids(&sim, &rectangle, &without_group), [3, 5]);
+        assert!(sim.query_region(&rectangle, &RegionQueryOptions { exclude_groups: vec![group + 1], ..RegionQueryOptions::default() }).is_err());
+    }
+    
+    #[test]
+    fn distinct_colors_are_all_different_below_the_palette_size() {
+        let mut sim = SimulationState::new();
+        assert!(sim.bodies.len() < DISTINCT_PALETTE_SIZE);
+        let sun_color = sim.bodies[0].color.clone();
+        for body in &mut sim.bodies[1..] {
+            body.color = String::from("#ffffff");
+        }
+        
+        for preserve_central in [true, false] {
+            sim.assign_distinct_colors(preserve_central);
+            let colors: HashSet<&str> = sim.bodies.iter().map(|b| b.color.as_str()).collect();
+            assert_eq!(colors.len(), sim.bodies.len());
+            assert_eq!(sim.bodies[0].color == sun_color, preserve_central);
+        }
+    }
+    
+    #[test]
+    fn closer_and_heavier_perturbers_precess_the_pericenter_faster() {
+        let rate = |perturber_mass: f64, perturber_distance: f64| {
+            let sun_mass = 1.0e4;
+            let circular = |r: f64| (DEFAULT_GRAVITY_CONSTANT * sun_mass / r).sqrt();
+            SimulationState::from_bodies(vec![
+                Body::new(1, sun_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 20.0, "#ffcc00"),
+                Body::new(2, 1.0, Vec2::new(200.0, 0.0), Vec2::new(0.0, circular(200.0)), 2.0, "#ffffff"),
+                Body::new(3, perturber_mass, Vec2::new(-perturber_distance, 0.0), Vec2::new(0.0, -circular(perturber_distance)), 8.0, "#ff6600"),
+            ], DEFAULT_GRAVITY_CONSTANT).secular_precession_rate(2, 1, 3).unwrap()
+        };
+        
+        assert!(rate(100.0, 600.0) > 0.0);
+        assert!(rate(100.0, 400.0) > rate(100.0, 600.0));
+        assert!(rate(300.0, 600.0) > rate(100.0, 600.0));
+        assert!(SimulationState::new().secular_precession_rate(2, 1, 99).is_none());
+    }
+    
+    #[test]
+    fn fast_forward_batches_cover_the_time_budget_across_a_rezero() {
+        let mut sim = SimulationState::new();
+        let dt = sim.time_step * sim.time_multiplier;
+        let max_sim_time = 1.5 * PROGRESS_EVENT_INTERVAL as f64 * dt;
+        let mut run = FastForwardRun::default();
+        
+        sim.fast_forward_batch(&mut run, max_sim_time, |_| false);
+        assert_eq!(run.steps, PROGRESS_EVENT_INTERVAL);
+        assert!(!sim.is_running && !run.finished(max_sim_time));
+        // Lands between the batches, like an edit from another command would
+        sim.rezero_time(false);
+        sim.fast_forward_batch(&mut run, max_sim_time, |_| false);
+        assert!(run.finished(max_sim_time) && !run.stopped);
+        assert!((run.elapsed - max_sim_time).abs() <= dt);
+        assert!(sim.elapsed_time < run.elapsed);
+        
+        let mut stopped = FastForwardRun::default();
+        sim.fast_forward_batch(&mut stopped, max_sim_time, |_| true);
+        assert_eq!(stopped.steps, 1);
+        assert!(stopped.finished(max_sim_time) && !sim.is_running);
+    }
+    
+    #[test]
+    fn body_pairs_yields_every_pair_once() {
+        for n in [0, 1, 2, 5, 9] {
+            let bodies: Vec<Body> = (0..n)
+                .map(|i| Body::new(i + 1, 1.0, Vec2::new(f64::from(i) * 10.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff"))
+                .collect();
+            let sim = SimulationState::from_bodies(bodies, DEFAULT_GRAVITY_CONSTANT);
+            let pairs: Vec<(u32, u32)> = sim.body_pairs().map(|(a, b)| (a.id, b.id)).collect();
+            let unique: HashSet<(u32, u32)> = pairs.iter().copied().collect();
+            
+            assert_eq!(pairs.len(), (n * n.saturating_sub(1) / 2) as usize);
+            assert_eq!(unique.len(), pairs.len());
+            assert!(pairs.iter().all(|(a, b)| a < b));
+        }
+    }
+    
+    #[test]
+    fn a_collision_at_the_right_edge_pans_fully_right() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0, Vec2::new(-500.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffffff"),
+            Body::new(2, 1.0, Vec2::new(500.0, -4.0), Vec2::new(0.0, 10.0), 5.0, "#ffffff"),
+            Body::new(3, 1.0, Vec2::new(500.0, 4.0), Vec2::new(0.0, -10.0), 5.0, "#ffffff"),
+        ], 0.0);
+        sim.is_running = true;
+        sim.step();
+        
+        assert_eq!(sim.collision_sound_events.len(), 1);
+        let hint = &sim.collision_sound_events[0];
+        assert!((hint.stereo_pan - 1.0).abs() < 1e-9, "pan {}", hint.stereo_pan);
+        assert!((hint.relative_speed - 20.0).abs() < 1e-9);
+        assert_eq!(hint.combined_mass, 2.0);
+    }
+    
+    #[test]
+    fn orbits_geojson_has_one_feature_per_body() {
+        let sim = SimulationState::new();
+        let json: serde_json::Value = serde_json::from_str(&sim.orbits_geojson(200, 10)).unwrap();
+        
+        assert_eq!(json["type"], "FeatureCollection");
+        let features = json["features"].as_array().unwrap();
+        assert_eq!(features.len(), sim.bodies.len());
+        for (feature, body) in features.iter().zip(&sim.bodies) {
+            assert_eq!(feature["properties"]["id"], body.id);
+            assert_eq!(feature["properties"]["color"], body.color.as_str());
+            assert_eq!(feature["geometry"]["type"], "LineString");
+            assert!(!feature["geometry"]["coordinates"].as_array().unwrap().is_empty());
+        }
+    }
+    
+    #[test]
+    fn self_test_reports_every_check_and_leaves_the_live_state_alone() {
+        let state = AppState::new(SimulationState::new());
+        let before = serde_json::to_string(&*state.simulation()).unwrap();
+        
+        let report = crate::selftest::run_all();
+        
+        let names: Vec<_> = report.checks.iter().map(|c| c.name).collect();
+        assert_eq!(names, ["two_body_orbit", "collision_impulse", "serialization_round_trip", "determinism", "step_timing_500_bodies"]);
+        // The timing check depends on the machine, everything else has to pass
+        for check in report.checks.iter().filter(|c| c.name != "step_timing_500_bodies") {
+            assert!(check.passed, "{} failed: {} > {} ({})", check.name, check.value, check.limit, check.detail);
+        }
+        assert_eq!(serde_json::to_string(&*state.simulation()).unwrap(), before);
+    }
+    
+    #[test]
+    fn default_planets_have_bound_barycentric_ellipses() {
+        let sim = SimulationState::new();
+        let elements: HashMap<u32, Option<OrbitalElements>> = sim.all_orbital_elements().into_iter().collect();
+        
+        assert_eq!(elements.len(), sim.bodies.len());
+        for id in 2..=5 {
+            let orbit = elements[&id].as_ref().unwrap_or_else(|| panic!("planet {} is unbound", id));
+            assert!(orbit.semi_major_axis > 0.0 && orbit.period > 0.0);
+            assert!((0.0..1.0).contains(&orbit.eccentricity), "planet {} has e = {}", id, orbit.eccentricity);
+        }
+    }
+    
+    #[test]
+    fn a_right_angle_slingshot_leaves_a_smooth_trail() {
+        // Hyperbola with e = sqrt(2), which turns the light body by 90 degrees, started at true anomaly -2
+        let (mass, periapsis, e) = (1.0e4, 20.0, std::f64::consts::SQRT_2);
+        let mu = DEFAULT_GRAVITY_CONSTANT * mass;
+        let p = periapsis * (1.0 + e);
+        let nu: f64 = -2.0;
+        let r = p / (1.0 + e * nu.cos());
+        let (radial, tangent) = (Vec2::new(nu.cos(), nu.sin()), Vec2::new(-nu.sin(), nu.cos()));
+        let speed_scale = (mu / p).sqrt();
+        let velocity = radial * (speed_scale * e * nu.sin()) + tangent * (speed_scale * (1.0 + e * nu.cos()));
+        
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffcc00"),
+            Body::new(2, 1e-3, radial * r, velocity, 1.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.time_step = 0.2;
+        sim.is_running = true;
+        let steps = 40;
+        for _ in 0..steps {
+            sim.step();
+        }
+        
+        let trail: Vec<Vec2> = sim.trails[&2].iter().map(|point| point.position).collect();
+        let heading = |a: Vec2, b: Vec2| (b.y - a.y).atan2(b.x - a.x);
+        let turns: Vec<f64> = trail.windows(3)
+            .map(|w| (heading(w[1], w[2]) - heading(w[0], w[1]) + std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI) - std::f64::consts::PI)
+            .collect();
+        let total_turn: f64 = turns.iter().sum();
+        let sharpest = turns.iter().fold(0.0_f64, |max, turn| max.max(turn.abs()));
+        
+        assert!(trail.len() > steps, "no interpolated points in {} steps", steps);
+        assert!(total_turn.abs() > 1.2, "the body only turned {} rad", total_turn);
+        assert!(sharpest < 0.2, "the trail has a corner of {} rad", sharpest);
+    }
+    
+    #[test]
+    fn radial_drag_slows_a_body_through_the_center_more_than_one_in_the_outskirts() {
+        // Gravity off and a heavy anchor keeps the center of mass at the origin
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e6, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 0.1, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(-50.0, 1.0), Vec2::new(10.0, 0.0), 0.1, "#ff9999"),
+            Body::new(3, 1.0, Vec2::new(-50.0, 500.0), Vec2::new(10.0, 0.0), 0.1, "#3366ff"),
+        ], 0.0);
+        sim.radial_drag = Some(RadialDrag { central_drag: 0.05, scale_radius: 20.0 });
+        sim.time_step = 0.1;
+        sim.is_running = true;
+        for _ in 0..100 {
+            sim.step();
+        }
+        
+        let speed = |id: u32| sim.bodies[sim.body_index(id).unwrap()].velocity.length();
+        let (central_loss, outer_loss) = (10.0 - speed(2), 10.0 - speed(3));
+        assert!(sim.bodies[sim.body_index(2).unwrap()].position.x > 0.0, "the body never crossed the center");
+        assert!(outer_loss > 0.0);
+        assert!(central_loss > 10.0 * outer_loss, "central loss {} vs outer loss {}", central_loss, outer_loss);
+    }
+    
+    #[test]
+    fn binding_report_warns_before_a_flyby_strips_the_moon() {
+        let g = DEFAULT_GRAVITY_CONSTANT;
+        let (planet_mass, moon_distance) = (100.0, 10.0);
+        let moon_speed = (g * planet_mass / moon_distance).sqrt();
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, planet_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 2.0, "#3366ff"),
+            Body::new(2, 0.01, Vec2::new(moon_distance, 0.0), Vec2::new(0.0, moon_speed), 0.5, "#cccccc"),
+            Body::new(3, 1000.0, Vec2::new(-300.0, 20.0), Vec2::new(5.0, 0.0), 1.0, "#ff6600"),
+        ], g);
+        sim.time_step = 0.05;
+        sim.is_running = true;
+        assert_eq!(sim.binding_report(2).unwrap().risk, RiskLevel::Low);
+        
+        let bound_to_planet = |sim: &SimulationState| {
+            let orbit = sim.relative_orbit(&sim.bodies[1], &sim.bodies[0]);
+            0.5 * orbit.speed_squared() - orbit.mu / orbit.radius() < 0.0
+        };
+        let mut first_high = None;
+        let mut stripped_at = None;
+        for step in 0..3000 {
+            sim.step();
+            if first_high.is_none() && sim.binding_report(2).unwrap().risk == RiskLevel::High {
+                first_high = Some(step);
+            }
+            if !bound_to_planet(&sim) {
+                stripped_at = Some(step);
+                break;
+            }
+        }
+        
+        let stripped_at = stripped_at.expect("the flyby never stripped the moon");
+        let first_high = first_high.expect("the report never flagged high risk");
+        assert!(first_high < stripped_at, "high risk at step {}, stripped at step {}", first_high, stripped_at);
+    }
+    
+    #[test]
+    fn slow_pairs_predict_merge_and_fast_pairs_bounce_or_fragment() {
+        // Head-on along x, the mutual escape speed at contact is about 5.2
+        let pair = |closing_speed: f64| SimulationState::from_bodies(vec![
+            Body::new(1, 100.0, Vec2::new(0.0, 0.0), Vec2::new(closing_speed / 2.0, 0.0), 5.0, "#ff9999"),
+            Body::new(2, 100.0, Vec2::new(50.0, 0.0), Vec2::new(-closing_speed / 2.0, 0.0), 5.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        
+        assert_eq!(pair(1.0).predicted_collision_outcome(1, 2), Some("merge"));
+        assert_eq!(pair(10.0).predicted_collision_outcome(1, 2), Some("bounce"));
+        assert_eq!(pair(30.0).predicted_collision_outcome(1, 2), Some("fragment"));
+        assert_eq!(pair(1.0).predicted_collision_outcome(1, 3), None);
+    }
+    
+    #[test]
+    fn non_finite_values_are_reported_and_serialize_as_zero() {
+        for field in ["position.x", "velocity.y", "mass"] {
+            let mut sim = SimulationState::new();
+            let body = &mut sim.bodies[2];
+            match field {
+                "position.x" => body.position.x = f64::NAN,
+                "velocity.y" => body.velocity.y = f64::INFINITY,
+                _ => body.mass = f64::NAN,
+            }
+            let id = sim.bodies[2].id;
+            
+            let snapshot = sim.ipc_snapshot();
+            let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+            let reported: Vec<(u32, &str)> = snapshot.corrupt_fields.iter().map(|f| (f.body_id, f.field.as_str())).collect();
+            assert_eq!(reported, [(id, field)]);
+            assert!(!json["bodies"][2].to_string().contains("null"), "{} leaked as null", field);
+            
+            // The event goes out once per corruption, not on every snapshot
+            sim.ipc_snapshot();
+            let events = sim.pending_events.iter().filter(|e| matches!(e, EngineEvent::StateCorruption(_))).count();
+            assert_eq!(events, 1);
+        }
+    }
+    
+    #[test]
+    fn a_body_leaving_the_pause_region_pauses_and_is_reported() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 1.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(50.0, 0.0), Vec2::new(100.0, 0.0), 1.0, "#3366ff"),
+        ], 0.0);
+        sim.auto_pause_region = Some((Vec2::new(100.0, 100.0), Vec2::new(-100.0, -100.0)));
+        sim.is_running = true;
+        
+        let mut steps = 0;
+        while sim.is_running && steps < 1000 {
+            sim.step();
+            steps += 1;
+        }
+        
+        assert!(!sim.is_running);
+        assert_eq!(sim.auto_pause_body, Some(2));
+        assert!(sim.bodies[1].position.x > 100.0 && sim.bodies[1].position.x < 102.0);
+    }
+    
+    #[test]
+    fn moving_a_body_far_away_drives_its_potential_to_zero() {
+        let mut sim = SimulationState::new();
+        let potential = |sim: &SimulationState, id: u32| sim.normalized_potentials().into_iter().find(|p| p.0 == id).unwrap().1;
+        let bound = potential(&sim, 2);
+        assert!(bound < 0.0);
+        
+        let mut previous = bound;
+        for distance in [1.0e4, 1.0e6, 1.0e8] {
+            sim.bodies[1].position = Vec2::new(distance, 0.0);
+            let far = potential(&sim, 2);
+            assert!(far < 0.0 && far > previous, "{} at distance {} after {}", far, distance, previous);
+            previous = far;
+        }
+        assert!(previous.abs() < bound.abs() * 1e-5);
+        
+        let total: f64 = sim.normalized_potentials().iter().map(|p| p.1).sum();
+        assert!((total - sim.potential_energy()).abs() < 1e-9 * total.abs());
+    }
+    
+    #[test]
+    fn energy_report_has_a_header_and_one_row_per_sample() {
+        let sim = SimulationState::new();
+        let csv = sim.energy_report_csv(100, 10);
+        let lines: Vec<&str> = csv.lines().collect();
+        
+        assert_eq!(lines[0], "step,elapsed_time,kinetic,potential,total,drift");
+        assert_eq!(lines.len(), 1 + 11);
+        let steps: Vec<&str> = lines[1..].iter().map(|line| line.split(',').next().unwrap()).collect();
+        assert_eq!(steps, ["0", "10", "20", "30", "40", "50", "60", "70", "80", "90", "100"]);
+        assert!(lines[1..].iter().all(|line| line.split(',').count() == 6));
+    }
+    
+    #[test]
+    fn requested_preset_phases_are_honored() {
+        let phases = PresetPhases {
+            planets: vec![None, Some(PhaseAngle::Absolute(90.0)), Some(PhaseAngle::Absolute(250.0))],
+            moons: vec![Some(PhaseAngle::RelativeToParent(0.0)), Some(PhaseAngle::RelativeToParent(180.0))],
+        };
+        phases.validate().unwrap();
+        let sim = SimulationState::with_phases(&phases);
+        
+        let direction = |from: usize, to: usize| {
+            let d = sim.bodies[to].position - sim.bodies[from].position;
+            d.y.atan2(d.x).to_degrees().rem_euclid(360.0)
+        };
+        let close = |a: f64, b: f64| ((a - b + 180.0).rem_euclid(360.0) - 180.0).abs() < 1e-9;
+        
+        // Index 0 is the sun, 1 to 4 the planets and 5 and 6 the moons of the planet at index 2
+        assert!(close(direction(0, 1), 0.0));
+        assert!(close(direction(0, 2), 90.0));
+        assert!(close(direction(0, 3), 250.0));
+        assert!(close(direction(0, 4), 270.0));
+        assert!(close(direction(2, 5), 90.0));
+        assert!(close(direction(2, 6), 270.0));
+        
+        let too_many = PresetPhases { planets: vec![None; 5], moons: Vec::new() };
+        assert!(too_many.validate().is_err());
+    }
+    
+    #[test]
+    fn morphing_with_increasing_t_converges_to_the_target() {
+        let mut sim = SimulationState::new();
+        let target = crate::presets::scene("binary_star").unwrap();
+        let gap = |sim: &SimulationState| target.bodies.iter()
+            .map(|goal| {
+                let body = &sim.bodies[sim.body_index(goal.id).unwrap()];
+                body.position.distance(&goal.position) + (body.velocity - goal.velocity).length()
+            })
+            .sum::<f64>();
+        
+        let mut previous = gap(&sim);
+        for k in 1..10 {
+            sim.morph_toward(&target, k as f64 / 10.0);
+            let current = gap(&sim);
+            assert!(current < previous, "gap grew from {} to {} at t = {}", previous, current, k as f64 / 10.0);
+            previous = current;
+        }
+        assert_eq!(sim.bodies.len(), 7);
+        
+        sim.morph_toward(&target, 1.0);
+        let ids: Vec<u32> = sim.bodies.iter().map(|b| b.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert_eq!(gap(&sim), 0.0);
+    }
+    
+    #[test]
+    fn plummer_sphere_starts_virialized_and_stays_bounded() {
+        let mut sim = SimulationState::plummer_sphere(200, 1.0e4, 100.0, 3, DEFAULT_GRAVITY_CONSTANT).unwrap();
+        let virial_ratio = |sim: &SimulationState| 2.0 * sim.kinetic_energy() / sim.potential_energy().abs();
+        let median_radius = |sim: &SimulationState| {
+            let com = sim.center_of_mass();
+            let mut radii: Vec<f64> = sim.bodies.iter().map(|b| b.position.distance(&com)).collect();
+            radii.sort_by(f64::total_cmp);
+            radii[radii.len() / 2]
+        };
+        assert!((virial_ratio(&sim) - 1.0).abs() < 1e-9);
+        let initial_radius = median_radius(&sim);
+        
+        sim.time_step = 0.05;
+        sim.is_running = true;
+        for _ in 0..400 {
+            sim.step();
+        }
+        
+        let (ratio, radius) = (virial_ratio(&sim), median_radius(&sim));
+        assert!((0.5..2.0).contains(&ratio), "virial ratio drifted to {}", ratio);
+        assert!(radius < 2.0 * initial_radius && radius > 0.5 * initial_radius, "median radius went from {} to {}", initial_radius, radius);
+    }
+    
+    #[test]
+    fn identical_runs_produce_identical_checksums() {
+        let run = |seed: u64| {
+            let mut sim = SimulationState::plummer_sphere(50, 1.0e4, 100.0, seed, DEFAULT_GRAVITY_CONSTANT).unwrap();
+            let initial = sim.checksum();
+            sim.is_running = true;
+            for _ in 0..100 {
+                sim.step();
+            }
+            assert_ne!(sim.checksum(), initial);
+            sim.checksum()
+        };
+        
+        assert_eq!(run(11), run(11));
+        assert_ne!(run(11), run(12));
+    }
+    
+    #[test]
+    fn only_an_eccentric_orbit_needs_circularization_guidance() {
+        let (mass, g) = (1.0e4, DEFAULT_GRAVITY_CONSTANT);
+        let circular_speed = |r: f64| (g * (mass + 1e-3) / r).sqrt();
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1e-3, Vec2::new(100.0, 0.0), Vec2::new(0.0, circular_speed(100.0)), 1.0, "#ff9999"),
+            Body::new(3, 1e-3, Vec2::new(0.0, 200.0), Vec2::new(-0.7 * circular_speed(200.0), 0.0), 1.0, "#3366ff"),
+        ], g);
+        
+        let guidance: HashMap<u32, Vec2> = sim.circularization_guidance(1).unwrap().into_iter().collect();
+        assert_eq!(guidance.len(), 2);
+        assert!(guidance[&2].length() < 1e-9, "circular orbit got {}", guidance[&2].length());
+        assert!(guidance[&3].length() > 0.1 * circular_speed(200.0));
+        assert!(sim.circularization_guidance(9).is_err());
+    }
+    
+    #[test]
+    fn nearly_coincident_bodies_are_merged_into_one() {
+        let mut sim = SimulationState::new();
+        let before = sim.bodies.len();
+        let copy = Body { id: 40, velocity: Vec2::new(3.0, -1.0), position: sim.bodies[1].position + Vec2::new(1e-9, 0.0), ..sim.bodies[1].clone() };
+        sim.bodies.push(copy);
+        let (mass, momentum) = (sim.bodies.iter().map(|b| b.mass).sum::<f64>(), sim.momentum_report().linear);
+        
+        assert_eq!(sim.dedupe_coincident(1e-6).unwrap(), [40]);
+        assert_eq!(sim.bodies.len(), before);
+        assert!((sim.bodies.iter().map(|b| b.mass).sum::<f64>() - mass).abs() < 1e-9);
+        assert!((sim.momentum_report().linear - momentum).length() < 1e-9);
+        assert!(sim.dedupe_coincident(1e-6).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn radial_drift_changes_the_semi_major_axis_at_the_configured_rate() {
+        let (mass, g) = (1.0e4, DEFAULT_GRAVITY_CONSTANT);
+        let speed = (g * (mass + 1e-3) / 100.0).sqrt();
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1e-3, Vec2::new(100.0, 0.0), Vec2::new(0.0, speed), 1.0, "#aa9988"),
+        ], g);
+        let rate = 0.05;
+        sim.set_radial_drift_rate(2, rate).unwrap();
+        sim.time_step = 0.05;
+        sim.is_running = true;
+        
+        let elements = |sim: &SimulationState| sim.relative_orbit(&sim.bodies[1], &sim.bodies[0]).elements().unwrap();
+        let start = elements(&sim).semi_major_axis;
+        // About five orbits
+        for _ in 0..8000 {
+            sim.step();
+        }
+        
+        let end = elements(&sim);
+        let expected = rate * sim.elapsed_time;
+        assert!(((end.semi_major_axis - start) - expected).abs() < 0.05 * expected, "a moved by {}, expected {}", end.semi_major_axis - start, expected);
+        assert!(end.eccentricity < 0.02, "eccentricity grew to {}", end.eccentricity);
+    }
+    
+    #[test]
+    fn spin_at_the_orbital_rate_gives_a_ratio_of_one() {
+        let mut sim = SimulationState::new();
+        let (r, speed) = {
+            let (moon, planet) = (&sim.bodies[5], &sim.bodies[2]);
+            (moon.position.distance(&planet.position), (moon.velocity - planet.velocity).length())
+        };
+        sim.bodies[5].spin = speed / r;
+        
+        let ratio = sim.spin_orbit_ratio(sim.bodies[5].id, sim.bodies[2].id).unwrap();
+        assert!((ratio - 1.0).abs() < 1e-9, "ratio {}", ratio);
+        sim.bodies[5].spin = -2.0 * speed / r;
+        assert!((sim.spin_orbit_ratio(sim.bodies[5].id, sim.bodies[2].id).unwrap() + 2.0).abs() < 1e-9);
+        assert_eq!(sim.spin_orbit_ratio(sim.bodies[5].id, 99), None);
+    }
+    
+    #[test]
+    fn two_equal_masses_have_twice_the_total_and_half_the_reduced_mass() {
+        let m = 250.0;
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, m, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ff9999"),
+            Body::new(2, m, Vec2::new(100.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        
+        let (total, reduced, mu) = sim.two_body_masses(1, 2).unwrap();
+        assert_eq!(total, 2.0 * m);
+        assert_eq!(reduced, m / 2.0);
+        assert_eq!(mu, DEFAULT_GRAVITY_CONSTANT * 2.0 * m);
+        assert_eq!(sim.two_body_masses(1, 1), None);
+        assert_eq!(sim.two_body_masses(1, 3), None);
+    }
+    
+    #[test]
+    fn normalizing_below_the_length_cutoff_gives_zero() {
+        let zero = Vec2::new(0.0, 0.0);
+        for tiny in [zero, Vec2::new(1e-12, 0.0), Vec2::new(-5e-10, 5e-10)] {
+            let n = tiny.normalized();
+            assert!(n.x == 0.0 && n.y == 0.0, "{} {} normalized to {} {}", tiny.x, tiny.y, n.x, n.y);
+        }
+        
+        let small = Vec2::new(0.0, -2e-9).normalized();
+        assert!((small.length() - 1.0).abs() < 1e-12);
+        assert!((small.y + 1.0).abs() < 1e-12);
+        assert_eq!(Vec2::new(1.5, -2.0).scale(2.0).length_squared(), 25.0);
+    }
+    
+    #[test]
+    fn a_body_skimming_the_sun_is_the_most_tidally_stressed() {
+        let mut sim = SimulationState::new();
+        let id = sim.add_body(1.0, Vec2::new(0.0, 29.0), Vec2::new(15.0, 0.0), 3.0, String::from("#ffffff")).unwrap();
+        
+        let (stressed, force) = sim.most_tidally_stressed().unwrap();
+        assert_eq!(stressed, id);
+        let index = sim.body_index(id).unwrap();
+        assert!((0..sim.bodies.len()).filter(|&i| i != index).all(|i| sim.tidal_force(i) < force));
+        assert_eq!(SimulationState::from_bodies(Vec::new(), DEFAULT_GRAVITY_CONSTANT).most_tidally_stressed(), None);
+    }
+    
+    #[test]
+    fn removing_the_middle_of_three_new_bodies_reuses_no_id() {
+        let mut sim = SimulationState::new();
+        let add = |sim: &mut SimulationState, x: f64| sim.add_body(1.0, Vec2::new(x, 3.0e3), Vec2::new(0.0, 0.0), 1.0, String::from("#ffffff")).unwrap();
+        let added = [add(&mut sim, 0.0), add(&mut sim, 100.0), add(&mut sim, 200.0)];
+        
+        assert!(sim.remove_body(added[1]));
+        let last = add(&mut sim, 300.0);
+        
+        assert!(!added.contains(&last));
+        let ids: HashSet<u32> = sim.bodies.iter().map(|b| b.id).collect();
+        assert_eq!(ids.len(), sim.bodies.len());
+    }
+    
+    fn circular_pair(integrator: Integrator, time_step: f64) -> SimulationState {
+        let (primary_mass, satellite_mass, distance) = (1000.0, 1.0, 100.0);
+        let total_mass = primary_mass + satellite_mass;
+        let speed = (DEFAULT_GRAVITY_CONSTANT * total_mass / distance).sqrt();
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, primary_mass, Vec2::new(-distance * satellite_mass / total_mass, 0.0),
+                Vec2::new(0.0, -speed * satellite_mass / total_mass), 10.0, "#ffcc00"),
+            Body::new(2, satellite_mass, Vec2::new(distance * primary_mass / total_mass, 0.0),
+                Vec2::new(0.0, speed * primary_mass / total_mass), 2.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        sim.integrator = integrator;
+        sim.time_step = time_step;
+        sim.is_running = true;
+        sim
+    }
+    
+    #[test]
+    fn verlet_keeps_a_circular_orbit_at_its_radius_for_ten_thousand_steps() {
+        // About 80 steps per orbit, coarse enough for the first-order error of Euler to show. Steps above
+        // MAX_TIME_STEP are clamped, so the orbit is sped up instead: 9 times the gravity and 3 times the
+        // speed keep it circular with a third of the period.
+        let max_deviation = |integrator: Integrator| {
+            let mut sim = circular_pair(integrator, 1.0);
+            sim.constants.gravity_constant *= 9.0;
+            for body in &mut sim.bodies {
+                body.velocity = body.velocity * 3.0;
+            }
+            let mut deviation: f64 = 0.0;
+            for _ in 0..10_000 {
+                sim.step();
+                deviation = deviation.max((sim.bodies[0].position.distance(&sim.bodies[1].position) - 100.0).abs() / 100.0);
+            }
+            deviation
+        };
+        
+        let (verlet, euler) = (max_deviation(Integrator::Verlet), max_deviation(Integrator::Euler));
+        assert!(verlet < 0.03, "radius drifted by {}", verlet);
+        assert!(euler > 0.03, "Euler stayed within {}", euler);
+    }
+    
+    #[test]
+    fn verlet_drifts_an_order_of_magnitude_less_energy_than_euler() {
+        let max_drift = |integrator: Integrator| {
+            let mut sim = circular_pair(integrator, 1.0);
+            let initial = sim.kinetic_energy() + sim.potential_energy();
+            let mut drift: f64 = 0.0;
+            for _ in 0..5000 {
+                sim.step();
+                drift = drift.max(((sim.kinetic_energy() + sim.potential_energy() - initial) / initial).abs());
+            }
+            drift
+        };
+        
+        let (verlet, euler) = (max_drift(Integrator::Verlet), max_drift(Integrator::Euler));
+        assert!(verlet * 10.0 < euler, "Verlet drift {} vs Euler drift {}", verlet, euler);
+    }
+    
+    #[test]
+    fn only_bodies_heading_toward_the_com_get_a_crossing_time() {
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e6, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(100.0, 0.0), Vec2::new(-10.0, 0.0), 1.0, "#ff9999"),
+            Body::new(3, 1.0, Vec2::new(-100.0, 0.0), Vec2::new(-10.0, 0.0), 1.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        
+        let times: HashMap<u32, Option<f64>> = sim.time_to_com().into_iter().collect();
+        let arrival = times[&2].unwrap();
+        assert!(arrival.is_finite() && (arrival - 10.0).abs() < 1e-3, "arrives after {}", arrival);
+        assert_eq!(times[&3], None);
+    }
+    
+    #[test]
+    fn rk4_conserves_the_energy_of_a_sun_and_planet_better_than_euler() {
+        let max_drift = |integrator: Integrator| {
+            let mut sim = circular_pair(integrator, 1.0);
+            let initial = sim.kinetic_energy() + sim.potential_energy();
+            let mut drift: f64 = 0.0;
+            for _ in 0..2000 {
+                sim.step();
+                drift = drift.max(((sim.kinetic_energy() + sim.potential_energy() - initial) / initial).abs());
+            }
+            drift
+        };
+        
+        let (rk4, euler) = (max_drift(Integrator::Rk4), max_drift(Integrator::Euler));
+        assert!(rk4 * 100.0 < euler, "RK4 drift {} vs Euler drift {}", rk4, euler);
+    }
+    
+    #[test]
+    fn integration_methods_parse_by_name_and_unknown_ones_are_rejected() {
+        assert_eq!(Integrator::from_name("euler"), Ok(Integrator::Euler));
+        assert_eq!(Integrator::from_name("verlet"), Ok(Integrator::Verlet));
+        assert_eq!(Integrator::from_name("RK4"), Ok(Integrator::Rk4));
+        let error = Integrator::from_name("leapfrog").unwrap_err();
+        assert!(error.contains("leapfrog"), "{}", error);
+    }
+    
+    #[test]
+    fn scattering_tallies_add_up_to_the_trials() {
+        let mut sim = SimulationState::new();
+        let id = sim.add_body(5.0, Vec2::new(-800.0, 100.0), Vec2::new(20.0, 0.0), 3.0, String::from("#ffffff")).unwrap();
+        let mut reports = 0;
+        
+        let summary = sim.scattering_survey(id, 12, 300, 150.0, 5, |_| { reports += 1; true }).unwrap();
+        assert_eq!(summary.trials, 12);
+        assert_eq!(summary.merged + summary.ejected + summary.bound, summary.trials);
+        assert!(summary.mean_final_speed.is_finite());
+        assert_eq!(reports, 12);
+        assert!(sim.scattering_survey(id, 12, 300, 150.0, 5, |_| false).is_err());
+    }
+    
+    #[test]
+    fn circular_speed_grid_is_fastest_near_the_center() {
+        let sim = SimulationState::new();
+        let grid = sim.circular_speed_grid(1, Vec2::new(-550.0, -550.0), Vec2::new(550.0, 550.0), 11, 11).unwrap();
+        assert_eq!(grid.len(), 121);
+        
+        // The middle row and column run through the sun, speeds rise toward it from both sides
+        let row: Vec<f64> = grid[55..66].to_vec();
+        let column: Vec<f64> = (0..11).map(|r| grid[r * 11 + 5]).collect();
+        for line in [row, column] {
+            assert!(line[..6].windows(2).all(|w| w[0] < w[1]));
+            assert!(line[5..].windows(2).all(|w| w[0] > w[1]));
+        }
+        assert!(grid[0] < grid[12] && grid[12] < grid[24]);
+        assert_eq!(sim.circular_speed_grid(99, Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), 2, 2), None);
+    }
+    
+    #[test]
+    fn default_scene_conserves_linear_momentum_over_a_thousand_steps() {
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        let start = sim.momentum_report().linear;
+        
+        for _ in 0..1000 {
+            sim.step();
+        }
+        
+        let end = sim.momentum_report().linear;
+        assert!((end.length() - start.length()).abs() < 1e-6, "momentum went from {} to {}", start.length(), end.length());
+        assert!((end - start).length() < 1e-6);
+    }
+    
+    #[test]
+    fn halving_the_time_step_doubles_the_steps_per_orbit() {
+        let mut sim = SimulationState::new();
+        let coarse = sim.steps_per_orbit(2, 1).unwrap();
+        sim.time_step /= 2.0;
+        let fine = sim.steps_per_orbit(2, 1).unwrap();
+        
+        // Both counts are rounded up, so the finer one can come out one short of twice the coarse one
+        assert!(fine == 2 * coarse || fine + 1 == 2 * coarse, "{} steps, then {}", coarse, fine);
+        assert_eq!(sim.steps_per_orbit(2, 99), None);
+    }
+    
+    #[test]
+    fn no_state_shows_a_body_after_its_merge_event() {
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 10.0, Vec2::new(0.0, 0.0), Vec2::new(5.0, 0.0), 2.0, "#ff9999"),
+            Body::new(2, 5.0, Vec2::new(20.0, 0.0), Vec2::new(-5.0, 0.0), 2.0, "#3366ff"),
+        ], 0.0);
+        sim.collision_mode = CollisionMode::Merge;
+        sim.is_running = true;
+        
+        // Replays what the frontend receives: each state, then the events published with it
+        let mut merged_away: Vec<u32> = Vec::new();
+        for _ in 0..500 {
+            sim.step();
+            let publication = sim.publish();
+            assert_eq!(publication.state.event_count, publication.events.len());
+            for id in &merged_away {
+                assert!(publication.state.body_index(*id).is_none(), "body {} is back after its merge event", id);
+            }
+            for stamped in &publication.events {
+                assert!(stamped.step <= publication.state.step_count);
+                if let EngineEvent::BodiesMerged(merger) = &stamped.event {
+                    let gone = if merger.result_id == merger.body1_id { merger.body2_id } else { merger.body1_id };
+                    assert!(publication.state.body_index(gone).is_none(), "the state before the event still has body {}", gone);
+                    merged_away.push(gone);
+                }
+            }
+        }
+        assert_eq!(merged_away, [2]);
+    }
+    
+    #[test]
+    fn saved_scenarios_load_back_identically_and_bad_ones_are_rejected() {
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        for _ in 0..50 {
+            sim.step();
+        }
+        
+        let path = std::env::temp_dir().join(format!("scenario-round-trip-{}.json", std::process::id()));
+        std::fs::write(&path, sim.to_scenario_json().unwrap()).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let loaded = SimulationState::from_scenario_json(&json).unwrap();
+        
+        assert_eq!(loaded.to_scenario_json().unwrap(), json);
+        assert_eq!(loaded.schema_version, SCENARIO_SCHEMA_VERSION);
+        assert_eq!(loaded.checksum(), sim.checksum());
+        
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["bodies"][1]["mass"] = serde_json::Value::from(-1.0);
+        let error = |json: &str| SimulationState::from_scenario_json(json).err().unwrap_or_default();
+        assert!(error(&value.to_string()).contains("mass"));
+        assert!(error("{ \"bodies\": ").starts_with("invalid scenario file"));
+        
+        sim.bodies[1].mass = f64::NAN;
+        assert!(sim.to_scenario_json().is_err());
+    }
+    
+    #[test]
+    fn a_disruption_stream_keeps_spreading_after_the_breakup() {
+        let g = DEFAULT_GRAVITY_CONSTANT;
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 1.0e4, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 10.0, "#ffcc00"),
+            Body::new(2, 1.0, Vec2::new(60.0, 0.0), Vec2::new(0.0, 1.3 * (g * 1.0e4 / 60.0).sqrt()), 2.0, "#cc6633"),
+        ], g);
+        sim.set_body_fluid(2, true).unwrap();
+        // The Roche limit is about 105, so the body starts 1.75 times inside it
+        sim.disruption_roche_excess = Some(1.5);
+        let (mass, momentum) = (sim.bodies.iter().map(|b| b.mass).sum::<f64>(), sim.momentum_report().linear);
+        sim.is_running = true;
+        sim.step();
+        
+        let fragment_ids = sim.pending_events.iter()
+            .find_map(|e| match e {
+                EngineEvent::TidalDisruption(disruption) => Some(disruption.fragment_ids.clone()),
+                _ => None,
+            })
+            .expect("the body was not disrupted");
+        assert_eq!(fragment_ids.len(), DISRUPTION_FRAGMENTS as usize);
+        assert!((sim.bodies.iter().map(|b| b.mass).sum::<f64>() - mass).abs() < 1e-9);
+        assert!((sim.momentum_report().linear - momentum).length() < 1e-9);
+        
+        let spread = |sim: &SimulationState| {
+            let (first, last) = (sim.body_index(fragment_ids[0]).unwrap(), sim.body_index(fragment_ids[fragment_ids.len() - 1]).unwrap());
+            sim.bodies[first].position.distance(&sim.bodies[last].position)
+        };
+        let mut previous = spread(&sim);
+        for step in 0..100 {
+            sim.step();
+            let current = spread(&sim);
+            assert!(current > previous, "the stream shrank from {} to {} at step {}", previous, current, step);
+            previous = current;
+        }
+    }
+    
+    #[test]
+    fn nan_and_zero_mass_edits_are_rejected_and_leave_the_body_alone() {
+        let mut sim = SimulationState::new();
+        let before = serde_json::to_string(&sim.bodies[1]).unwrap();
+        
+        for mass in [f64::NAN, 0.0, -1.0] {
+            assert!(sim.update_body(2, Some(mass), (None, None), (None, None), None, None).is_err());
+        }
+        assert!(sim.update_body(2, None, (Some(f64::NAN), None), (None, None), None, None).is_err());
+        assert!(sim.update_body(2, None, (None, None), (None, Some(f64::INFINITY)), None, None).is_err());
+        assert!(sim.update_body(2, Some(5.0), (None, None), (None, None), Some(0.0), None).is_err());
+        assert_eq!(serde_json::to_string(&sim.bodies[1]).unwrap(), before);
+        
+        let count = sim.bodies.len();
+        let color = String::from("#ffffff");
+        assert!(sim.add_body(f64::NAN, Vec2::new(0.0, 900.0), Vec2::new(0.0, 0.0), 1.0, color.clone()).is_err());
+        assert!(sim.add_body(0.0, Vec2::new(0.0, 900.0), Vec2::new(0.0, 0.0), 1.0, color.clone()).is_err());
+        assert!(sim.add_body(1.0, Vec2::new(f64::NAN, 900.0), Vec2::new(0.0, 0.0), 1.0, color).is_err());
+        assert_eq!(sim.bodies.len(), count);
+    }
+    
+    #[test]
+    fn a_filled_square_measures_near_two_and_a_ring_near_one() {
+        let mut rng = SplitMix64::new(9);
+        let body = |id: u32, position: Vec2| Body::new(id, 1.0, position, Vec2::new(0.0, 0.0), 0.1, "#ffffff");
+        
+        let square: Vec<Body> = (1..=4000).map(|id| body(id, Vec2::new(rng.next_f64(), rng.next_f64()) * 1000.0)).collect();
+        let ring: Vec<Body> = (1..=4000)
+            .map(|id| {
+                let angle = 2.0 * std::f64::consts::PI * rng.next_f64();
+                body(id, Vec2::new(angle.cos(), angle.sin()) * 500.0)
+            })
+            .collect();
+        
+        // The ring needs finer grids, the coarse ones see it fill every cell
+        let filled = SimulationState::from_bodies(square, 0.0).fractal_dimension(4).unwrap();
+        let thin = SimulationState::from_bodies(ring, 0.0).fractal_dimension(6).unwrap();
+        assert!((filled - 2.0).abs() < 0.15, "filled square measured {}", filled);
+        assert!((thin - 1.0).abs() < 0.25, "ring measured {}", thin);
+    }
+    
+    #[test]
+    fn softened_force_stays_finite_and_smooth_down_to_zero_separation() {
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, 100.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ff9999"),
+            Body::new(2, 100.0, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#3366ff"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        let epsilon = sim.constants.softening;
+        let force_at = |r: f64| sim.calculate_forces(&[Vec2::new(0.0, 0.0), Vec2::new(r, 0.0)])[0].length();
+        
+        // The Plummer force peaks at r = epsilon / sqrt(2) at 2 / (3 sqrt(3)) of G m1 m2 / epsilon^2
+        let peak_r = epsilon / 2.0_f64.sqrt();
+        let peak = DEFAULT_GRAVITY_CONSTANT * 100.0 * 100.0 / (epsilon * epsilon) * 2.0 / (3.0 * 3.0_f64.sqrt());
+        let separations: Vec<f64> = (0..=400).rev().map(|k| k as f64 * 0.025).collect();
+        let forces: Vec<f64> = separations.iter().map(|&r| force_at(r)).collect();
+        
+        assert!(forces.iter().all(|f| f.is_finite() && *f <= peak * (1.0 + 1e-12)));
+        assert_eq!(forces[forces.len() - 1], 0.0);
+        // Rising all the way in to the peak and falling smoothly after it, no jump where a clamp would kick in
+        for (f, r) in forces.windows(2).zip(separations.windows(2)) {
+            if r[1] >= peak_r {
+                assert!(f[1] > f[0], "force fell from {} to {} closing in to {}", f[0], f[1], r[1]);
+            } else if r[0] < peak_r {
+                assert!(f[1] < f[0], "force rose from {} to {} closing in to {}", f[0], f[1], r[1]);
+            }
+        }
+    }
+    
+    #[test]
+    fn bounces_keep_momentum_at_extreme_mass_ratios() {
+        for ratio in [1e6, 1e9, 1e12] {
+            let (heavy, grain) = (8.0e3, 8.0e3 / ratio);
+            let mut sim = SimulationState::from_bodies(vec![
+                Body::new(1, heavy, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffcc00"),
+                Body::new(2, grain, Vec2::new(9.9, 0.0), Vec2::new(-10.0, 0.0), 5.0, "#ffffff"),
+            ], 0.0);
+            sim.time_step = 1e-6;
+            sim.is_running = true;
+            let before = sim.momentum_report().linear;
+            sim.step();
+            
+            assert!(sim.bodies[1].velocity.x > 0.0, "the grain did not bounce at ratio {}", ratio);
+            let error = (sim.momentum_report().linear - before).length();
+            assert!(error < 1e-9 * grain * 10.0, "momentum error {} at ratio {}", error, ratio);
+        }
+        
+        // Past pin_mass_ratio the heavy body is pinned and the grain bounces off it exactly
+        let mut sim = SimulationState::from_bodies(vec![
+            Body::new(1, 8.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 5.0, "#ffcc00"),
+            Body::new(2, 8.0e-13, Vec2::new(9.9, 0.0), Vec2::new(-10.0, 0.0), 5.0, "#ffffff"),
+        ], 0.0);
+        sim.time_step = 1e-6;
+        sim.is_running = true;
+        sim.step();
+        assert_eq!(sim.bodies[0].velocity.x, 0.0);
+        assert!((sim.bodies[1].velocity.x - 10.0 * RESTITUTION).abs() < 1e-12);
+    }
+    
+    #[test]
+    fn a_denser_body_has_a_smaller_disruption_radius() {
+        // Same mass, so the smaller moon is eight times as dense as the fluffy one
+        let sim = SimulationState::from_bodies(vec![
+            Body::new(1, 8.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 25.0, "#ffcc00"),
+            Body::new(2, 10.0, Vec2::new(300.0, 0.0), Vec2::new(0.0, 0.0), 2.0, "#cccccc"),
+            Body::new(3, 10.0, Vec2::new(-300.0, 0.0), Vec2::new(0.0, 0.0), 4.0, "#aaaaaa"),
+        ], DEFAULT_GRAVITY_CONSTANT);
+        
+        let (dense, fluffy) = (sim.tidal_disruption_radius(2, 1).unwrap(), sim.tidal_disruption_radius(3, 1).unwrap());
+        assert!(dense < fluffy, "dense {} vs fluffy {}", dense, fluffy);
+        assert!((fluffy / dense - 2.0).abs() < 1e-12);
+        assert_eq!(sim.tidal_disruption_radius(2, 9), None);
+        assert_eq!(sim.tidal_disruption_radius(2, 2), None);
+    }
+}
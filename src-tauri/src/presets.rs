@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+use crate::physics::{Body, SimulationState, SplitMix64, Vec2, DEFAULT_GRAVITY_CONSTANT};
+
+#[derive(Clone, Serialize)]
+pub struct PresetInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    // Whether the scene comes with tutorial checkpoints
+    pub tutorial: bool,
+}
+
+pub const PRESETS: [PresetInfo; 7] = [
+    PresetInfo { name: "default", description: "The sun with four planets and the moons of the third", tutorial: false },
+    PresetInfo { name: "binary_star", description: "Two equal stars on a circular orbit with a planet circling both", tutorial: false },
+    PresetInfo { name: "figure_eight", description: "Three equal bodies chasing each other along a figure eight", tutorial: false },
+    PresetInfo { name: "asteroid_ring", description: "A star with a ring of 50 small asteroids", tutorial: false },
+    PresetInfo { name: "empty", description: "No bodies at all", tutorial: false },
+    PresetInfo { name: "orbital_speed", description: "Tutorial: how orbital speed changes with distance", tutorial: true },
+    PresetInfo { name: "collision_momentum", description: "Tutorial: momentum is conserved in a collision", tutorial: true },
+];
+
+const G: f64 = DEFAULT_GRAVITY_CONSTANT;
+
+// Each star moves around the common center at m_other * sqrt(G / (M d)), the planet is far enough out
+// to see the pair as a single mass M.
+fn binary_star() -> SimulationState {
+    let (star_mass, separation) = (4.0e3, 200.0);
+    let star_speed = star_mass * (G / (2.0 * star_mass * separation)).sqrt();
+    let planet_distance = 700.0;
+    let planet_speed = (G * 2.0 * star_mass / planet_distance).sqrt();
+    
+    SimulationState::from_bodies(vec![
+        Body::new(1, star_mass, Vec2::new(-separation / 2.0, 0.0), Vec2::new(0.0, -star_speed), 20.0, "#ffcc00"),
+        Body::new(2, star_mass, Vec2::new(separation / 2.0, 0.0), Vec2::new(0.0, star_speed), 20.0, "#ff6633"),
+        Body::new(3, 10.0, Vec2::new(0.0, planet_distance), Vec2::new(-planet_speed, 0.0), 8.0, "#3366ff"),
+    ], G)
+}
+
+// The Chenciner-Montgomery initial conditions for G = m = 1, scaled to length L and mass m. Velocities then
+// scale with sqrt(G m / L).
+fn figure_eight() -> SimulationState {
+    let (mass, length) = (1.0e3, 150.0);
+    let speed_scale = (G * mass / length).sqrt();
+    let position = Vec2::new(0.97000436, -0.24308753) * length;
+    let center_velocity = Vec2::new(-0.93240737, -0.86473146) * speed_scale;
+    let outer_velocity = center_velocity * -0.5;
+    
+    SimulationState::from_bodies(vec![
+        Body::new(1, mass, position, outer_velocity, 10.0, "#ff9999"),
+        Body::new(2, mass, position * -1.0, outer_velocity, 10.0, "#33ccff"),
+        Body::new(3, mass, Vec2::new(0.0, 0.0), center_velocity, 10.0, "#ffcc00"),
+    ], G)
+}
+
+// Asteroids between 260 and 340 on circular orbits, placed from a fixed seed so the ring is always the same.
+// The asteroids are light enough that the star alone sets their speed.
+fn asteroid_ring() -> SimulationState {
+    let star_mass = 8.0e3;
+    let mut rng = SplitMix64::new(50);
+    let mut bodies = vec![Body::new(1, star_mass, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 25.0, "#ffcc00")];
+    
+    for i in 0..50 {
+        let angle = std::f64::consts::PI * 2.0 * rng.next_f64();
+        let distance = 260.0 + 80.0 * rng.next_f64();
+        let (direction, speed) = (Vec2::new(angle.cos(), angle.sin()), (G * star_mass / distance).sqrt());
+        bodies.push(Body::new(i + 2, 0.5, direction * distance, Vec2::new(-direction.y, direction.x) * speed, 2.0, "#aa9988"));
+    }
+    
+    SimulationState::from_bodies(bodies, G)
+}
+
+pub fn scene(name: &str) -> Option<SimulationState> {
+    match name {
+        "default" => Some(SimulationState::new()),
+        "binary_star" => Some(binary_star()),
+        "figure_eight" => Some(figure_eight()),
+        "asteroid_ring" => Some(asteroid_ring()),
+        "empty" => Some(SimulationState::from_bodies(Vec::new(), G)),
+        "orbital_speed" => Some(crate::tutorials::orbital_speed()),
+        "collision_momentum" => Some(crate::tutorials::collision_momentum()),
+        _ => None,
+    }
+}
@@ -0,0 +1,182 @@
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::physics::{Body, SimulationState, Vec2, DEFAULT_GRAVITY_CONSTANT, RESTITUTION};
+
+#[derive(Clone, Serialize)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub value: f64,
+    pub limit: f64,
+    pub detail: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+// Light satellite on a circular orbit around a heavy primary, both in the barycentric frame
+fn two_body_scene(g: f64) -> SimulationState {
+    let (primary_mass, satellite_mass, distance) = (1000.0, 1.0, 100.0);
+    let total_mass = primary_mass + satellite_mass;
+    let speed = (g * total_mass / distance).sqrt();
+    
+    let bodies = vec![
+        Body::new(1, primary_mass, Vec2::new(-distance * satellite_mass / total_mass, 0.0),
+             Vec2::new(0.0, -speed * satellite_mass / total_mass), 10.0, "#ffffff"),
+        Body::new(2, satellite_mass, Vec2::new(distance * primary_mass / total_mass, 0.0),
+             Vec2::new(0.0, speed * primary_mass / total_mass), 2.0, "#ffffff"),
+    ];
+    SimulationState::from_bodies(bodies, g)
+}
+
+fn check_orbit_accuracy() -> SelfTestCheck {
+    let g = DEFAULT_GRAVITY_CONSTANT;
+    let mut sim = two_body_scene(g);
+    sim.is_running = true;
+    
+    let distance = 100.0;
+    let period = 2.0 * std::f64::consts::PI * (distance * distance * distance / (g * 1001.0)).sqrt();
+    let steps = (period / sim.effective_time_step()).ceil() as u32;
+    
+    let mut max_deviation: f64 = 0.0;
+    for _ in 0..steps {
+        sim.step();
+        let separation = sim.bodies[0].position.distance(&sim.bodies[1].position);
+        max_deviation = max_deviation.max((separation - distance).abs() / distance);
+    }
+    
+    let limit = 0.05;
+    SelfTestCheck {
+        name: "two_body_orbit",
+        passed: max_deviation <= limit,
+        value: max_deviation,
+        limit,
+        detail: format!("max relative radius deviation over one orbit ({} steps)", steps),
+    }
+}
+
+// Head-on collisions along x with gravity switched off, compared against the 1D restitution formula
+fn check_collision_impulses() -> SelfTestCheck {
+    let cases = [(1.0, 1.0, 1.0, -1.0), (1.0, 3.0, 2.0, 0.0), (5.0, 0.5, 0.5, -4.0)];
+    let mut max_error: f64 = 0.0;
+    
+    for (m1, m2, v1, v2) in cases {
+        let bodies = vec![
+            Body::new(1, m1, Vec2::new(0.0, 0.0), Vec2::new(v1, 0.0), 5.0, "#ffffff"),
+            Body::new(2, m2, Vec2::new(9.9, 0.0), Vec2::new(v2, 0.0), 5.0, "#ffffff"),
+        ];
+        let mut sim = SimulationState::from_bodies(bodies, 0.0);
+        sim.time_step = 1e-6;
+        sim.is_running = true;
+        sim.step();
+        
+        let expected1 = v1 - (1.0 + RESTITUTION) * m2 * (v1 - v2) / (m1 + m2);
+        let expected2 = v2 + (1.0 + RESTITUTION) * m1 * (v1 - v2) / (m1 + m2);
+        max_error = max_error
+            .max((sim.bodies[0].velocity.x - expected1).abs())
+            .max((sim.bodies[1].velocity.x - expected2).abs());
+    }
+    
+    let limit = 1e-9;
+    SelfTestCheck {
+        name: "collision_impulse",
+        passed: max_error <= limit,
+        value: max_error,
+        limit,
+        detail: format!("max velocity error across {} oracle cases", cases.len()),
+    }
+}
+
+fn check_serialization_round_trip() -> SelfTestCheck {
+    let mut sim = two_body_scene(DEFAULT_GRAVITY_CONSTANT);
+    sim.elapsed_time = 12.5;
+    
+    let result = serde_json::to_string(&sim)
+        .and_then(|json| serde_json::from_str::<SimulationState>(&json).map(|back| (json, back)))
+        .and_then(|(json, back)| serde_json::to_string(&back).map(|again| json == again));
+    
+    let (passed, detail) = match result {
+        Ok(true) => (true, String::from("state serializes back to identical JSON")),
+        Ok(false) => (false, String::from("JSON differs after a round trip")),
+        Err(e) => (false, format!("serialization failed: {}", e)),
+    };
+    SelfTestCheck {
+        name: "serialization_round_trip",
+        passed,
+        value: if passed { 0.0 } else { 1.0 },
+        limit: 0.0,
+        detail,
+    }
+}
+
+fn check_determinism() -> SelfTestCheck {
+    let run = || {
+        let mut sim = SimulationState::new();
+        sim.is_running = true;
+        for _ in 0..1000 {
+            sim.step();
+        }
+        sim.bodies.iter()
+            .map(|b| (b.position.x.to_bits(), b.position.y.to_bits()))
+            .collect::<Vec<_>>()
+    };
+    
+    let mismatches = run().iter().zip(run().iter()).filter(|(a, b)| a != b).count();
+    SelfTestCheck {
+        name: "determinism",
+        passed: mismatches == 0,
+        value: mismatches as f64,
+        limit: 0.0,
+        detail: String::from("bodies whose positions differ bit-wise between two identical 1000-step runs"),
+    }
+}
+
+fn check_step_timing() -> SelfTestCheck {
+    let bodies = (0..500)
+        .map(|i| {
+            let (col, row) = ((i % 25) as f64, (i / 25) as f64);
+            Body::new(i + 1, 1.0, Vec2::new(col * 20.0, row * 20.0), Vec2::new(0.0, 0.0), 1.0, "#ffffff")
+        })
+        .collect();
+    let mut sim = SimulationState::from_bodies(bodies, DEFAULT_GRAVITY_CONSTANT);
+    sim.is_running = true;
+    
+    let start = Instant::now();
+    sim.step();
+    let millis = start.elapsed().as_secs_f64() * 1000.0;
+    
+    let limit = 250.0;
+    SelfTestCheck {
+        name: "step_timing_500_bodies",
+        passed: millis <= limit,
+        value: millis,
+        limit,
+        detail: String::from("milliseconds for one step with 500 bodies"),
+    }
+}
+
+// Runs only on synthetic states, the live simulation is never touched
+pub fn run_all() -> SelfTestReport {
+    let checks = vec![
+        check_orbit_accuracy(),
+        check_collision_impulses(),
+        check_serialization_round_trip(),
+        check_determinism(),
+        check_step_timing(),
+    ];
+    
+    SelfTestReport {
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+    }
+}
+
+#[tauri::command(async)]
+pub fn run_self_test() -> SelfTestReport {
+    crate::metrics::begin("run_self_test");
+    run_all()
+}
@@ -0,0 +1,174 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::physics::{TrailPoint, Vec2};
+
+static NEXT_SPILL_FILE: AtomicU32 = AtomicU32::new(0);
+
+enum SpillMessage {
+    Rows(Vec<(u32, TrailPoint)>),
+    Flush(Sender<()>),
+}
+
+// Trail points evicted from memory, appended as CSV rows (id,step,x,y) to a temp file by a writer
+// thread so the stepping thread never waits on the disk. The file is removed when this is dropped.
+pub struct TrailSpill {
+    sender: Option<Sender<SpillMessage>>,
+    writer: Option<JoinHandle<()>>,
+    path: PathBuf,
+    error: Arc<Mutex<Option<String>>>,
+    // Every body id sent so far, so the file need not be read to find out whose points it holds
+    ids: Mutex<BTreeSet<u32>>,
+}
+
+impl TrailSpill {
+    pub fn create(dir: &Path) -> io::Result<TrailSpill> {
+        fs::create_dir_all(dir)?;
+        let n = NEXT_SPILL_FILE.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("trail-spill-{}-{}.csv", std::process::id(), n));
+        let file = File::create(&path)?;
+        
+        let (sender, receiver) = mpsc::channel();
+        let error = Arc::new(Mutex::new(None));
+        let writer_error = Arc::clone(&error);
+        let writer = thread::spawn(move || write_rows(file, receiver, writer_error));
+        
+        Ok(TrailSpill { sender: Some(sender), writer: Some(writer), path, error, ids: Mutex::new(BTreeSet::new()) })
+    }
+    
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+    
+    pub fn send(&self, rows: Vec<(u32, TrailPoint)>) {
+        self.ids.lock().unwrap().extend(rows.iter().map(|(id, _)| *id));
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(SpillMessage::Rows(rows));
+        }
+    }
+    
+    // First write error since the last call, the writer stops writing once it hits one
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+    
+    pub fn ids(&self) -> Vec<u32> {
+        self.ids.lock().unwrap().iter().copied().collect()
+    }
+    
+    // Waits for queued rows to reach the file, then streams everything spilled so far in the order it was
+    // written, a row at a time
+    pub fn rows(&self) -> io::Result<SpillRows> {
+        if let Some(sender) = &self.sender {
+            let (ack, done) = mpsc::channel();
+            if sender.send(SpillMessage::Flush(ack)).is_ok() {
+                let _ = done.recv();
+            }
+        }
+        let file = OpenOptions::new().read(true).open(&self.path)?;
+        Ok(SpillRows { lines: BufReader::new(file).lines() })
+    }
+    
+    // Everything spilled so far grouped by body
+    pub fn read(&self) -> io::Result<HashMap<u32, Vec<TrailPoint>>> {
+        let mut spilled: HashMap<u32, Vec<TrailPoint>> = HashMap::new();
+        for row in self.rows()? {
+            let (id, point) = row?;
+            spilled.entry(id).or_default().push(point);
+        }
+        Ok(spilled)
+    }
+}
+
+pub struct SpillRows {
+    lines: Lines<BufReader<File>>,
+}
+
+impl Iterator for SpillRows {
+    type Item = io::Result<(u32, TrailPoint)>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let fields: Vec<&str> = line.split(',').collect();
+            // A row cut short by a failed write is skipped
+            let [id, step, x, y] = fields[..] else { continue };
+            let (Ok(id), Ok(step), Ok(x), Ok(y)) = (id.parse(), step.parse(), x.parse(), y.parse()) else { continue };
+            return Some(Ok((id, TrailPoint { position: Vec2::new(x, y), step })));
+        }
+        None
+    }
+}
+
+// The points of one body in a spill file that are older than step before, read back when they are needed
+pub struct SpilledTrail {
+    pub spill: Arc<TrailSpill>,
+    pub id: u32,
+    pub before: u64,
+}
+
+impl SpilledTrail {
+    pub fn read(&self) -> io::Result<Vec<TrailPoint>> {
+        let mut points = Vec::new();
+        for row in self.spill.rows()? {
+            let (id, point) = row?;
+            if id == self.id && point.step < self.before {
+                points.push(point);
+            }
+        }
+        Ok(points)
+    }
+}
+
+impl Drop for TrailSpill {
+    fn drop(&mut self) {
+        // Closing the channel ends the writer loop
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn write_rows(file: File, receiver: Receiver<SpillMessage>, error: Arc<Mutex<Option<String>>>) {
+    let mut out = BufWriter::new(file);
+    let mut failed = false;
+    
+    for message in receiver {
+        match message {
+            SpillMessage::Rows(rows) => {
+                if failed {
+                    continue;
+                }
+                let result = rows.iter().try_for_each(|(id, p)| {
+                    writeln!(out, "{},{},{},{}", id, p.step, p.position.x, p.position.y)
+                });
+                if let Err(e) = result {
+                    failed = true;
+                    *error.lock().unwrap() = Some(e.to_string());
+                }
+            }
+            SpillMessage::Flush(ack) => {
+                if !failed {
+                    if let Err(e) = out.flush() {
+                        failed = true;
+                        *error.lock().unwrap() = Some(e.to_string());
+                    }
+                }
+                let _ = ack.send(());
+            }
+        }
+    }
+    
+    let _ = out.flush();
+}
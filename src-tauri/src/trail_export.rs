@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::physics::{TrailPoint, Vec2};
+use crate::spill::SpilledTrail;
+
+// Bumped whenever a field changes meaning or goes away
+const TRAIL_EXPORT_VERSION: u32 = 1;
+
+pub struct ExportTrail {
+    // get_trails key: the body id, "barycenter" or "track-" plus the track id
+    pub key: String,
+    pub name: String,
+    // None for the barycenter and COM tracks
+    pub color: Option<String>,
+    // Points that went to the spill file, read a trail at a time while writing so a long run never sits in
+    // memory as a whole
+    pub spilled: Option<SpilledTrail>,
+    // Points still in memory, all newer than the spilled ones
+    pub points: Vec<TrailPoint>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailExportFormat {
+    Json,
+    Csv,
+}
+
+pub struct TrailExportOptions {
+    // Keeps every stride-th point, the last point of a trail is always kept
+    pub stride: usize,
+    // Douglas-Peucker tolerance in distance units, 0 keeps every point left after the stride
+    pub tolerance: f64,
+}
+
+impl TrailExportOptions {
+    pub fn new(stride: Option<u32>, tolerance: Option<f64>) -> Result<Self, String> {
+        let stride = stride.unwrap_or(1);
+        let tolerance = tolerance.unwrap_or(0.0);
+        if stride == 0 {
+            return Err(String::from("stride must be at least 1"));
+        }
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(String::from("tolerance must be a non-negative number"));
+        }
+        Ok(TrailExportOptions { stride: stride as usize, tolerance })
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct TrailExportSummary {
+    pub trails: usize,
+    pub points: usize,
+    pub cancelled: bool,
+    // Where the schema for a CSV export went, JSON exports carry it in their meta object
+    pub meta_path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExportMeta {
+    version: u32,
+    format: &'static str,
+    step_count: u64,
+    stride: usize,
+    tolerance: f64,
+    schema: &'static str,
+}
+
+const JSON_SCHEMA: &str = "trails is a list of {key, name, color, points}. key is the body id, \
+\"barycenter\" or \"track-<id>\" for center-of-mass tracks, color is null for those. points is a list of \
+[x, y, step] in simulation distance units, oldest first.";
+
+const CSV_SCHEMA: &str = "One row per point: key,name,color,step,x,y. key is the body id, \"barycenter\" \
+or \"track-<id>\" for center-of-mass tracks, color is empty for those. Rows of a trail are contiguous and \
+oldest first.";
+
+#[derive(Serialize)]
+struct JsonTrail<'a> {
+    key: &'a str,
+    name: &'a str,
+    color: Option<&'a str>,
+    points: Vec<(f64, f64, u64)>,
+}
+
+// Stride first, then Douglas-Peucker on what is left
+fn reduce(points: &[TrailPoint], options: &TrailExportOptions) -> Vec<TrailPoint> {
+    let mut strided: Vec<TrailPoint> = points.iter().step_by(options.stride).copied().collect();
+    if let (Some(last), Some(kept)) = (points.last(), strided.last()) {
+        if kept.step != last.step {
+            strided.push(*last);
+        }
+    }
+    if options.tolerance <= 0.0 || strided.len() < 3 {
+        return strided;
+    }
+    
+    let mut keep = vec![false; strided.len()];
+    keep[0] = true;
+    keep[strided.len() - 1] = true;
+    let mut spans = vec![(0, strided.len() - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let (a, b) = (strided[first].position, strided[last].position);
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_to_segment(strided[i].position, a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((i, distance)) = farthest {
+            if distance > options.tolerance {
+                keep[i] = true;
+                spans.push((first, i));
+                spans.push((i, last));
+            }
+        }
+    }
+    strided.into_iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| p).collect()
+}
+
+fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f64 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0.0 {
+        return p.distance(&a);
+    }
+    let t = ((p - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    p.distance(&(a + ab * t))
+}
+
+// Streams the trails to path one at a time, reading the spilled part of each from the spill file. progress gets the fraction of trails written and returns
+// false to stop early, the file then holds the trails written so far and is still well formed.
+pub fn write(path: &Path, format: TrailExportFormat, options: &TrailExportOptions, trails: &[ExportTrail],
+             step_count: u64, mut progress: impl FnMut(f64) -> bool) -> Result<TrailExportSummary, String> {
+    let io_error = |e: std::io::Error| format!("could not write {}: {}", path.display(), e);
+    let mut out = BufWriter::new(File::create(path).map_err(io_error)?);
+    let meta = ExportMeta {
+        version: TRAIL_EXPORT_VERSION,
+        format: match format { TrailExportFormat::Json => "json", TrailExportFormat::Csv => "csv" },
+        step_count,
+        stride: options.stride,
+        tolerance: options.tolerance,
+        schema: match format { TrailExportFormat::Json => JSON_SCHEMA, TrailExportFormat::Csv => CSV_SCHEMA },
+    };
+    
+    match format {
+        TrailExportFormat::Json => {
+            let meta = serde_json::to_string(&meta).map_err(|e| e.to_string())?;
+            write!(out, "{{\"meta\":{},\"trails\":[", meta).map_err(io_error)?;
+        }
+        TrailExportFormat::Csv => writeln!(out, "key,name,color,step,x,y").map_err(io_error)?,
+    }
+    
+    let mut summary = TrailExportSummary { trails: 0, points: 0, cancelled: false, meta_path: None };
+    for (i, trail) in trails.iter().enumerate() {
+        let mut all = match &trail.spilled {
+            Some(spilled) => spilled.read().map_err(|e| format!("could not read trail spill file: {}", e))?,
+            None => Vec::new(),
+        };
+        all.extend_from_slice(&trail.points);
+        let points = reduce(&all, options);
+        match format {
+            TrailExportFormat::Json => {
+                if i > 0 {
+                    out.write_all(b",").map_err(io_error)?;
+                }
+                let json = JsonTrail {
+                    key: &trail.key,
+                    name: &trail.name,
+                    color: trail.color.as_deref(),
+                    points: points.iter().map(|p| (p.position.x, p.position.y, p.step)).collect(),
+                };
+                serde_json::to_writer(&mut out, &json).map_err(|e| e.to_string())?;
+            }
+            TrailExportFormat::Csv => {
+                let color = trail.color.as_deref().unwrap_or("");
+                for p in &points {
+                    writeln!(out, "{},{},{},{},{},{}", trail.key, trail.name, color, p.step, p.position.x, p.position.y)
+                        .map_err(io_error)?;
+                }
+            }
+        }
+        summary.trails += 1;
+        summary.points += points.len();
+        
+        if !progress((i + 1) as f64 / trails.len() as f64) {
+            summary.cancelled = true;
+            break;
+        }
+    }
+    
+    if let TrailExportFormat::Json = format {
+        out.write_all(b"]}\n").map_err(io_error)?;
+    }
+    out.flush().map_err(io_error)?;
+    
+    // CSV has no room for the meta object, so it goes next to the file
+    if let TrailExportFormat::Csv = format {
+        let mut meta_path = PathBuf::from(path).into_os_string();
+        meta_path.push(".meta.json");
+        let meta_path = PathBuf::from(meta_path);
+        let json = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+        std::fs::write(&meta_path, json + "\n").map_err(|e| format!("could not write {}: {}", meta_path.display(), e))?;
+        summary.meta_path = Some(meta_path.to_string_lossy().into_owned());
+    }
+    Ok(summary)
+}
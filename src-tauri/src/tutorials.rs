@@ -0,0 +1,70 @@
+use crate::physics::{Body, SimulationState, Tutorial, TutorialStep, Vec2, DEFAULT_GRAVITY_CONSTANT};
+
+const G: f64 = DEFAULT_GRAVITY_CONSTANT;
+
+// The intro steps at t = 0 do not pause, they are reached on the first step after pressing play
+fn checkpoint(time: f64, message: &str, focus_body_id: Option<u32>, selected_body_ids: Vec<u32>) -> TutorialStep {
+    TutorialStep { time, message: String::from(message), focus_body_id, selected_body_ids, pause: true }
+}
+
+// A star with two circular orbits and a comet with e = 0.6. The planets and the comet are light enough
+// that the star barely moves, so the times below match the two-body periods (86, 340 and 1109).
+pub fn orbital_speed() -> SimulationState {
+    let gm = G * 8.0e3;
+    let circular = |r: f64| (gm / r).sqrt();
+    // Apoapsis speed of the comet, a = 250 and e = 0.6
+    let comet_speed = (gm * 0.4 / 400.0).sqrt();
+    
+    let mut scene = SimulationState::from_bodies(vec![
+        Body::new(1, 8.0e3, Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 25.0, "#ffcc00"),
+        Body::new(2, 1.0, Vec2::new(100.0, 0.0), Vec2::new(0.0, circular(100.0)), 6.0, "#ff9999"),
+        Body::new(3, 1.0, Vec2::new(550.0, 0.0), Vec2::new(0.0, circular(550.0)), 10.0, "#3366ff"),
+        Body::new(4, 0.1, Vec2::new(-400.0, 0.0), Vec2::new(0.0, -comet_speed), 4.0, "#ffffff"),
+    ], G);
+    scene.time_multiplier = 5.0;
+    scene.tutorial = Some(Tutorial {
+        title: String::from("Orbital speed and distance"),
+        steps: vec![
+            TutorialStep {
+                pause: false,
+                ..checkpoint(0.0, "The two planets move on circular orbits, the comet on a stretched one. Press play \
+                                   and compare how fast each of them goes.", None, Vec::new())
+            },
+            checkpoint(43.0, "The inner planet is half way round already while the outer one has covered about 14 \
+                              degrees. Circular orbit speed falls off as 1 / sqrt(r).", None, vec![2, 3]),
+            checkpoint(170.0, "The comet is at periapsis, closest to the star, and moving fastest: four times its \
+                               speed at apoapsis.", Some(4), vec![4]),
+            checkpoint(340.0, "Back at apoapsis the comet is slowest again. It sweeps out equal areas in equal times, \
+                               which is Kepler's second law.", Some(4), vec![4]),
+        ],
+        next_step: 0,
+    });
+    scene
+}
+
+// Two bodies colliding head on with gravity off, total momentum 30 * 20 - 10 * 30 = 300. They touch at t = 7.4.
+pub fn collision_momentum() -> SimulationState {
+    let mut scene = SimulationState::from_bodies(vec![
+        Body::new(1, 30.0, Vec2::new(-200.0, 0.0), Vec2::new(20.0, 0.0), 20.0, "#ff6600"),
+        Body::new(2, 10.0, Vec2::new(200.0, 0.0), Vec2::new(-30.0, 0.0), 10.0, "#33ccff"),
+    ], 0.0);
+    scene.tutorial = Some(Tutorial {
+        title: String::from("Momentum in collisions"),
+        steps: vec![
+            TutorialStep {
+                pause: false,
+                ..checkpoint(0.0, "Gravity is switched off and the two bodies head for each other. The total \
+                                   momentum is 30 * 20 - 10 * 30 = 300 to the right.", None, vec![1, 2])
+            },
+            checkpoint(7.0, "Just before they touch. Watch the lighter body, it changes its velocity the most.",
+                       Some(2), vec![2]),
+            checkpoint(10.0, "After the bounce the velocities changed but the total momentum is still 300: the \
+                              impulses on the two bodies are equal and opposite. Some kinetic energy was lost, the \
+                              bounce is not perfectly elastic.", None, vec![1, 2]),
+            checkpoint(12.0, "In merge collision mode the same collision would leave one body of mass 40, moving on \
+                              at 300 / 40 = 7.5.", None, Vec::new()),
+        ],
+        next_step: 0,
+    });
+    scene
+}
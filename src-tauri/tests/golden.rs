@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+use afghantracklist_lib::golden::{check_all, GoldenFixture};
+
+const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+#[test]
+fn golden_runs_match_the_fixtures() {
+    let report = check_all(Path::new(FIXTURE_DIR)).unwrap();
+    
+    assert!(!report.runs.is_empty(), "no fixtures in {}", FIXTURE_DIR);
+    for run in &report.runs {
+        assert!(run.passed, "{} drifted: max error {} ({})", run.name, run.max_error, run.detail);
+    }
+    assert!(report.passed);
+}
+
+#[test]
+fn drift_beyond_the_tolerance_fails() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("golden-drift-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    
+    let json = std::fs::read_to_string(Path::new(FIXTURE_DIR).join("head_on_collision.json")).unwrap();
+    let mut fixture: GoldenFixture = serde_json::from_str(&json).unwrap();
+    fixture.expected[0].position.x += 1e-6;
+    std::fs::write(dir.join("head_on_collision.json"), serde_json::to_string(&fixture).unwrap()).unwrap();
+    
+    let report = check_all(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(!report.passed);
+    assert!(report.runs[0].max_error > 1e-9);
+}
@@ -0,0 +1,27 @@
+use afghantracklist_lib::physics::{AppState, SimulationState, Vec2};
+
+#[test]
+fn each_app_state_owns_its_simulation() {
+    let first = AppState::new(SimulationState::new());
+    let second = AppState::new(SimulationState::new());
+    first.simulation().is_running = true;
+    
+    for _ in 0..100 {
+        first.simulation().step();
+    }
+    
+    assert!(first.simulation().elapsed_time > 0.0);
+    assert_eq!(second.simulation().elapsed_time, 0.0);
+    let (moved, resting) = (first.simulation().bodies[1].position, second.simulation().bodies[1].position);
+    assert!((moved - resting).length() > 0.0);
+}
+
+#[test]
+fn fresh_state_starts_from_the_default_system() {
+    let state = AppState::new(SimulationState::new());
+    let id = state.simulation().add_body(1.0, Vec2::new(5.0e3, 0.0), Vec2::new(0.0, 0.0), 1.0, String::from("#ffffff")).unwrap();
+    
+    let fresh = AppState::new(SimulationState::new());
+    assert!(fresh.simulation().bodies.iter().all(|body| body.id != id));
+    assert_eq!(fresh.simulation().bodies.len() + 1, state.simulation().bodies.len());
+}